@@ -0,0 +1,92 @@
+//! Copies of a database as one stream of pages.
+//!
+//! A backup is `[BACKUP_MAGIC][page_size u32]`, then one frame
+//! `[page i32][page bytes]` per page in use, in page order, page 0 first.
+
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::{read_checked_page, storage::Storage, table::DatabaseError, wal, Database};
+
+const BACKUP_MAGIC: &[u8; 8] = b"RUSTUBBK";
+
+impl<S: Storage> Database<S> {
+    /// Write every page in use to `out`, see [`Database::restore`].
+    ///
+    /// Spare pages are left out, so the backup is no larger than the data.
+    /// Blob values live in their own file and are not part of the backup.
+    pub fn backup(&mut self, out: &mut impl Write) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        self.flush()?;
+        let mut pages: Vec<i32> = self.in_use_pages.iter().copied().collect();
+        pages.sort_unstable();
+
+        out.write_all(BACKUP_MAGIC)?;
+        out.write_all(&self.layout.page_size.to_be_bytes())?;
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            // from the file, cached pages have no up to date checksum
+            read_checked_page(&mut self.reader, self.layout, page, &mut page_buf)?;
+            out.write_all(&page.to_be_bytes())?;
+            out.write_all(&page_buf)?;
+        }
+        Ok(out.flush()?)
+    }
+}
+
+impl Database {
+    /// Create a database file at `path` from a backup made by [`Database::backup`],
+    /// truncating the file if there is one.
+    ///
+    /// Each page goes back to where it was,
+    /// pages left out of the backup are zeros.
+    pub fn restore(path: impl AsRef<Path>, mut input: impl Read) -> Result<(), DatabaseError> {
+        let mut header = [0; BACKUP_MAGIC.len() + 4];
+        input.read_exact(&mut header)?;
+        if header[..BACKUP_MAGIC.len()] != BACKUP_MAGIC[..] {
+            return Err(bad_backup("not a backup"));
+        }
+        let page_size = u32::from_be_bytes(header[BACKUP_MAGIC.len()..].try_into().unwrap());
+        if !page_size.is_power_of_two() {
+            return Err(bad_backup("page size is not a power of two"));
+        }
+
+        // a log left by an old file must not be replayed onto the new one
+        wal::remove(path.as_ref())?;
+        let mut file = File::create(path)?;
+        let mut page_buf = vec![0; page_size as usize];
+        let mut last = None;
+        loop {
+            let mut page = [0; 4];
+            match input.read_exact(&mut page) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            let page = i32::from_be_bytes(page);
+            let in_order = match last {
+                None => page == 0,
+                Some(last) => page > last,
+            };
+            if !in_order {
+                return Err(bad_backup("pages out of order"));
+            }
+            input.read_exact(&mut page_buf)?;
+            // seeking past the end leaves a gap of zeros
+            file.seek(SeekFrom::Start(page as u64 * page_size as u64))?;
+            file.write_all(&page_buf)?;
+            last = Some(page);
+        }
+        if last.is_none() {
+            return Err(bad_backup("no pages"));
+        }
+        Ok(file.sync_all()?)
+    }
+}
+
+fn bad_backup(message: &str) -> DatabaseError {
+    io::Error::new(ErrorKind::InvalidData, message).into()
+}
@@ -0,0 +1,130 @@
+//! Sidecar file holding the values of blob columns.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::table::BLOB_POINTER_LEN;
+
+/// Values of blob columns, addressed by `[offset u64][len u32]` pointers.
+///
+/// Free space is only known in memory,
+/// the caller passes the values still referenced when opening.
+pub(crate) struct BlobFile {
+    file: File,
+    len: u64,
+    // offset -> length of free extents, never adjacent
+    free: BTreeMap<u64, u64>,
+}
+
+impl BlobFile {
+    /// Open or create the blob file,
+    /// treating everything outside the `used` pointers as free.
+    pub(crate) fn open(path: &Path, used: &[[u8; BLOB_POINTER_LEN as usize]]) -> io::Result<Self> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let len = file.metadata()?.len();
+        let mut used: Vec<(u64, u64)> = used.iter().map(decode_pointer).collect();
+        used.sort_unstable();
+
+        let mut free = BTreeMap::new();
+        let mut pos = 0;
+        for (offset, extent_len) in used {
+            if offset > pos {
+                free.insert(pos, offset - pos);
+            }
+            pos = pos.max(offset + extent_len);
+        }
+        if len > pos {
+            free.insert(pos, len - pos);
+        }
+        Ok(BlobFile { file, len, free })
+    }
+
+    /// Store a value in the first free extent that fits,
+    /// or at the end of the file.
+    pub(crate) fn put(&mut self, value: &[u8]) -> io::Result<[u8; BLOB_POINTER_LEN as usize]> {
+        let Ok(value_len) = u32::try_from(value.len()) else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "blob longer than u32::MAX",
+            ));
+        };
+        let value_len = value_len as u64;
+        let offset = match self
+            .free
+            .iter()
+            .find(|(_, extent_len)| **extent_len >= value_len)
+            .map(|(offset, extent_len)| (*offset, *extent_len))
+        {
+            Some((offset, extent_len)) => {
+                self.free.remove(&offset);
+                if extent_len > value_len {
+                    self.free.insert(offset + value_len, extent_len - value_len);
+                }
+                offset
+            }
+            None => {
+                let offset = self.len;
+                self.len += value_len;
+                offset
+            }
+        };
+
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(value)?;
+        let mut pointer = [0; BLOB_POINTER_LEN as usize];
+        pointer[..8].copy_from_slice(&offset.to_be_bytes());
+        pointer[8..].copy_from_slice(&(value_len as u32).to_be_bytes());
+        Ok(pointer)
+    }
+
+    /// Read the value a pointer refers to.
+    pub(crate) fn get(&mut self, pointer: &[u8]) -> io::Result<Vec<u8>> {
+        let (offset, len) = decode_pointer(pointer);
+        if offset.checked_add(len).is_none_or(|end| end > self.len) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "blob pointer past the end of the blob file",
+            ));
+        }
+        let mut value = vec![0; len as usize];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut value)?;
+        Ok(value)
+    }
+
+    /// Give the extent of a pointer back to the allocator.
+    pub(crate) fn free(&mut self, pointer: &[u8]) {
+        let (mut offset, mut len) = decode_pointer(pointer);
+        if len == 0 {
+            return;
+        }
+        // merge with the neighbours
+        if let Some((&prev, &prev_len)) = self.free.range(..offset).next_back() {
+            if prev + prev_len == offset {
+                self.free.remove(&prev);
+                offset = prev;
+                len += prev_len;
+            }
+        }
+        if let Some(next_len) = self.free.remove(&(offset + len)) {
+            len += next_len;
+        }
+        self.free.insert(offset, len);
+    }
+}
+
+fn decode_pointer(pointer: impl AsRef<[u8]>) -> (u64, u64) {
+    let pointer = pointer.as_ref();
+    let offset = u64::from_be_bytes(pointer[..8].try_into().unwrap());
+    let len = u32::from_be_bytes(pointer[8..12].try_into().unwrap());
+    (offset, len as u64)
+}
@@ -0,0 +1,180 @@
+//! Pages kept in memory between reads.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
+
+pub(crate) type Frame = Box<[u8]>;
+
+struct Entry {
+    frame: Frame,
+    // holds bytes not yet written to the file
+    dirty: bool,
+    last_use: u64,
+}
+
+/// Page frames keyed by page index, evicting the least recently used.
+///
+/// Dirty frames are handed back to the caller when evicted or taken,
+/// the cache never writes to the file itself.
+pub(crate) struct PageCache {
+    capacity: usize,
+    entries: HashMap<i32, Entry>,
+    // last use -> page, oldest first
+    lru: BTreeMap<u64, i32>,
+    tick: u64,
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+}
+
+impl PageCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        PageCache {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            lru: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// The frame of a page, if cached, marking it as just used.
+    pub(crate) fn get(&mut self, page: i32) -> Option<&Frame> {
+        let Some(entry) = self.entries.get_mut(&page) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        self.tick += 1;
+        self.lru.remove(&entry.last_use);
+        self.lru.insert(self.tick, page);
+        entry.last_use = self.tick;
+        Some(&entry.frame)
+    }
+
+    /// The frame of a page, if cached,
+    /// without counting the lookup or marking the page as used.
+    pub(crate) fn peek(&self, page: i32) -> Option<&Frame> {
+        self.entries.get(&page).map(|entry| &entry.frame)
+    }
+
+    /// Cache a frame, which stays dirty if the page already was.
+    ///
+    /// Returns the evicted frame if it was dirty.
+    pub(crate) fn insert(&mut self, page: i32, frame: Frame, dirty: bool) -> Option<(i32, Frame)> {
+        let mut dirty = dirty;
+        if let Some(old) = self.entries.remove(&page) {
+            self.lru.remove(&old.last_use);
+            dirty |= old.dirty;
+        }
+        let mut evicted = None;
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.lru.pop_first() {
+                match self.entries.remove(&oldest) {
+                    Some(old) if old.dirty => evicted = Some((oldest, old.frame)),
+                    _ => {}
+                }
+            }
+        }
+        self.tick += 1;
+        self.lru.insert(self.tick, page);
+        self.entries.insert(
+            page,
+            Entry {
+                frame,
+                dirty,
+                last_use: self.tick,
+            },
+        );
+        evicted
+    }
+
+    /// Copy `bytes` into the frame of a page at `offset`, if cached,
+    /// leaving the frame dirty.
+    ///
+    /// Returns whether the page was cached.
+    pub(crate) fn patch(&mut self, page: i32, offset: usize, bytes: &[u8]) -> bool {
+        let Some(entry) = self.entries.get_mut(&page) else {
+            return false;
+        };
+        entry.frame[offset..offset + bytes.len()].copy_from_slice(bytes);
+        entry.dirty = true;
+        true
+    }
+
+    /// Forget the given pages.
+    ///
+    /// Fails without forgetting any of them if one is dirty,
+    /// as its changes would be lost.
+    pub(crate) fn invalidate(&mut self, pages: impl IntoIterator<Item = i32>) -> io::Result<()> {
+        let pages: Vec<i32> = pages
+            .into_iter()
+            .filter(|page| self.entries.contains_key(page))
+            .collect();
+        if let Some(page) = pages.iter().find(|page| self.entries[page].dirty) {
+            return Err(io::Error::other(format!(
+                "page {page} changed both in and around the page cache"
+            )));
+        }
+        for page in pages {
+            if let Some(old) = self.entries.remove(&page) {
+                self.lru.remove(&old.last_use);
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget every page from `first` on, see [`PageCache::invalidate`].
+    pub(crate) fn invalidate_from(&mut self, first: i32) -> io::Result<()> {
+        let pages: Vec<i32> = self
+            .entries
+            .keys()
+            .copied()
+            .filter(|&p| p >= first)
+            .collect();
+        self.invalidate(pages)
+    }
+
+    /// Forget every page, see [`PageCache::invalidate`].
+    pub(crate) fn clear(&mut self) -> io::Result<()> {
+        let pages: Vec<i32> = self.entries.keys().copied().collect();
+        self.invalidate(pages)
+    }
+
+    /// Forget every page, dropping the changes of dirty ones.
+    pub(crate) fn discard(&mut self) {
+        self.entries.clear();
+        self.lru.clear();
+    }
+
+    /// The dirty pages, in no particular order.
+    pub(crate) fn dirty_pages(&self) -> Vec<i32> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(&page, _)| page)
+            .collect()
+    }
+
+    /// Copies of the dirty frames, lowest page first.
+    /// The frames are clean afterwards.
+    pub(crate) fn take_dirty(&mut self) -> Vec<(i32, Frame)> {
+        let mut res: Vec<(i32, Frame)> = self
+            .entries
+            .iter_mut()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(&page, entry)| {
+                entry.dirty = false;
+                (page, entry.frame.clone())
+            })
+            .collect();
+        res.sort_unstable_by_key(|(page, _)| *page);
+        res
+    }
+}
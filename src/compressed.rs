@@ -0,0 +1,502 @@
+//! Databases kept compressed on disk, one compressed frame per page.
+//!
+//! A compressed file is a [`HEADER_LEN`] byte header
+//! `[COMPRESSED_MAGIC][version u32][page_size u32][len u64][table_offset u64][table_capacity u32]`,
+//! then frames `[frame_len u32][deflate bytes]` and the page table in any order.
+//! The page table holds `[offset u64][capacity u32]` per page of the database,
+//! where the frame of the page starts and how many bytes it may take,
+//! a zero offset standing for a page of zeros.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec_with_limit};
+
+use crate::{read_layout, storage::Storage, table::DatabaseError, wal, Database, DatabaseOptions};
+
+const COMPRESSED_MAGIC: &[u8; 8] = b"RUSTUBCZ";
+/// Version of the compressed file format, see the module docs.
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: u64 = 36;
+const ENTRY_LEN: u64 = 12;
+/// Deflate level, from 0 to 10.
+const LEVEL: u8 = 6;
+/// Pages [`Database::compress`] compresses at a time.
+const FLUSH_PAGES: u64 = 64;
+
+/// A database file whose pages are stored compressed,
+/// see [`Database::compress`] and [`Database::open_compressed`].
+///
+/// Reads and writes see the bytes of the uncompressed database.
+/// Pages written are compressed when the handle is flushed,
+/// into their old frame if they still fit, else at the end of the file,
+/// so a file rewritten often grows until it is compressed again.
+///
+/// Handles cloned from one another share the file.
+#[derive(Debug)]
+pub struct CompressedStorage {
+    inner: Arc<Mutex<Compressed>>,
+    pos: u64,
+}
+
+#[derive(Debug)]
+struct Compressed {
+    file: File,
+    page_size: u64,
+    /// Length of the uncompressed bytes.
+    len: u64,
+    /// Frame of each page as `(offset, capacity)`, `(0, 0)` for a page of zeros.
+    entries: Vec<(u64, u32)>,
+    table_offset: u64,
+    table_capacity: u32,
+    /// Where the next frame or table is appended.
+    end: u64,
+    /// Pages written since the last flush, uncompressed.
+    dirty: BTreeMap<u64, Box<[u8]>>,
+    /// Whether the header or the table changed since the last flush.
+    changed: bool,
+    /// The page decompressed last.
+    last_read: Option<(u64, Box<[u8]>)>,
+}
+
+impl CompressedStorage {
+    /// Create an empty compressed file for pages of `page_size` bytes,
+    /// truncating the file if there is one.
+    pub fn create(path: impl AsRef<Path>, page_size: u32) -> io::Result<Self> {
+        if !page_size.is_power_of_two() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "page size is not a power of two",
+            ));
+        }
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let mut inner = Compressed {
+            file,
+            page_size: page_size as u64,
+            len: 0,
+            entries: Vec::new(),
+            table_offset: HEADER_LEN,
+            table_capacity: 0,
+            end: HEADER_LEN,
+            dirty: BTreeMap::new(),
+            changed: true,
+            last_read: None,
+        };
+        inner.persist()?;
+        Ok(CompressedStorage::new(inner))
+    }
+
+    /// Open a compressed file made by [`CompressedStorage::create`].
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::options().read(true).write(true).open(path)?;
+        let mut header = [0; HEADER_LEN as usize];
+        file.read_exact(&mut header)?;
+        if header[..8] != COMPRESSED_MAGIC[..] {
+            return Err(bad_file("not a compressed database"));
+        }
+        let version = u32::from_be_bytes(header[8..12].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(bad_file("unknown compressed format version"));
+        }
+        let page_size = u32::from_be_bytes(header[12..16].try_into().unwrap()) as u64;
+        if !page_size.is_power_of_two() {
+            return Err(bad_file("page size is not a power of two"));
+        }
+        let len = u64::from_be_bytes(header[16..24].try_into().unwrap());
+        let table_offset = u64::from_be_bytes(header[24..32].try_into().unwrap());
+        let table_capacity = u32::from_be_bytes(header[32..36].try_into().unwrap());
+        let pages = len.div_ceil(page_size);
+        if pages > table_capacity as u64 {
+            return Err(bad_file("page table too small"));
+        }
+
+        let mut table = vec![0; (pages * ENTRY_LEN) as usize];
+        file.seek(SeekFrom::Start(table_offset))?;
+        file.read_exact(&mut table)?;
+        let entries = table
+            .chunks_exact(ENTRY_LEN as usize)
+            .map(|entry| {
+                (
+                    u64::from_be_bytes(entry[..8].try_into().unwrap()),
+                    u32::from_be_bytes(entry[8..].try_into().unwrap()),
+                )
+            })
+            .collect();
+        let end = file.metadata()?.len();
+        Ok(CompressedStorage::new(Compressed {
+            file,
+            page_size,
+            len,
+            entries,
+            table_offset,
+            table_capacity,
+            end,
+            dirty: BTreeMap::new(),
+            changed: false,
+            last_read: None,
+        }))
+    }
+
+    fn new(inner: Compressed) -> Self {
+        CompressedStorage {
+            inner: Arc::new(Mutex::new(inner)),
+            pos: 0,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Compressed> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Compressed {
+    /// The bytes of `page`, as last written.
+    fn page(&mut self, page: u64) -> io::Result<&[u8]> {
+        if self.dirty.contains_key(&page) {
+            return Ok(&self.dirty[&page]);
+        }
+        if self
+            .last_read
+            .as_ref()
+            .is_none_or(|(last, _)| *last != page)
+        {
+            let bytes = self.read_frame(page)?;
+            self.last_read = Some((page, bytes));
+        }
+        Ok(&self.last_read.as_ref().unwrap().1)
+    }
+
+    fn read_frame(&mut self, page: u64) -> io::Result<Box<[u8]>> {
+        let page_size = self.page_size as usize;
+        let (offset, capacity) = match self.entries.get(page as usize) {
+            Some(&(offset, capacity)) if offset != 0 => (offset, capacity),
+            _ => return Ok(vec![0; page_size].into_boxed_slice()),
+        };
+        let mut frame_len = [0; 4];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut frame_len)?;
+        let frame_len = u32::from_be_bytes(frame_len);
+        if frame_len > capacity {
+            return Err(bad_file("frame longer than its capacity"));
+        }
+        let mut frame = vec![0; frame_len as usize];
+        self.file.read_exact(&mut frame)?;
+        match decompress_to_vec_with_limit(&frame, page_size) {
+            Ok(bytes) if bytes.len() == page_size => Ok(bytes.into_boxed_slice()),
+            _ => Err(bad_file("frame does not decompress to a page")),
+        }
+    }
+
+    /// Read `buf.len()` bytes from `pos`, which must all be below `len`.
+    fn read_at(&mut self, mut pos: u64, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let (page, offset) = (pos / self.page_size, (pos % self.page_size) as usize);
+            let n = buf.len().min(self.page_size as usize - offset);
+            let (head, rest) = buf.split_at_mut(n);
+            head.copy_from_slice(&self.page(page)?[offset..offset + n]);
+            pos += n as u64;
+            buf = rest;
+        }
+        Ok(())
+    }
+
+    fn write_at(&mut self, mut pos: u64, mut buf: &[u8]) -> io::Result<()> {
+        let end = pos + buf.len() as u64;
+        while !buf.is_empty() {
+            let (page, offset) = (pos / self.page_size, (pos % self.page_size) as usize);
+            let n = buf.len().min(self.page_size as usize - offset);
+            if !self.dirty.contains_key(&page) {
+                let bytes = self.page(page)?.into();
+                self.dirty.insert(page, bytes);
+            }
+            self.dirty.get_mut(&page).unwrap()[offset..offset + n].copy_from_slice(&buf[..n]);
+            pos += n as u64;
+            buf = &buf[n..];
+        }
+        if end > self.len {
+            self.len = end;
+            self.changed = true;
+        }
+        Ok(())
+    }
+
+    fn set_len(&mut self, len: u64) -> io::Result<()> {
+        if len < self.len {
+            let pages = len.div_ceil(self.page_size);
+            self.dirty.split_off(&pages);
+            self.entries.truncate(pages as usize);
+            if self
+                .last_read
+                .as_ref()
+                .is_some_and(|(page, _)| *page >= len / self.page_size)
+            {
+                self.last_read = None;
+            }
+            // bytes past the end read as zeros once it grows again
+            let tail = (len % self.page_size) as usize;
+            if tail != 0 {
+                let last = len / self.page_size;
+                let mut bytes: Box<[u8]> = self.page(last)?.into();
+                bytes[tail..].fill(0);
+                self.dirty.insert(last, bytes);
+            }
+        }
+        self.len = len;
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Compress the pages written since the last call into the file,
+    /// then write the page table and the header.
+    fn persist(&mut self) -> io::Result<()> {
+        if self.dirty.is_empty() && !self.changed {
+            return Ok(());
+        }
+        let pages = self.len.div_ceil(self.page_size) as usize;
+        self.entries.resize(pages, (0, 0));
+        for (page, bytes) in mem::take(&mut self.dirty) {
+            let page = page as usize;
+            if bytes.iter().all(|b| *b == 0) {
+                self.entries[page] = (0, 0);
+                continue;
+            }
+            let frame = compress_to_vec(&bytes, LEVEL);
+            let frame_len = frame.len() as u32;
+            let entry = match self.entries[page] {
+                (offset, capacity) if offset != 0 && frame_len <= capacity => (offset, capacity),
+                _ => {
+                    let offset = self.end;
+                    self.end += 4 + frame_len as u64;
+                    (offset, frame_len)
+                }
+            };
+            self.file.seek(SeekFrom::Start(entry.0))?;
+            self.file.write_all(&frame_len.to_be_bytes())?;
+            self.file.write_all(&frame)?;
+            self.entries[page] = entry;
+        }
+
+        if pages > self.table_capacity as usize {
+            self.table_capacity = (pages * 2).max(64) as u32;
+            self.table_offset = self.end;
+            self.end += self.table_capacity as u64 * ENTRY_LEN;
+        }
+        let mut table = Vec::with_capacity(pages * ENTRY_LEN as usize);
+        for &(offset, capacity) in &self.entries {
+            table.extend_from_slice(&offset.to_be_bytes());
+            table.extend_from_slice(&capacity.to_be_bytes());
+        }
+        self.file.seek(SeekFrom::Start(self.table_offset))?;
+        self.file.write_all(&table)?;
+        if self.file.metadata()?.len() < self.end {
+            self.file.set_len(self.end)?;
+        }
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(COMPRESSED_MAGIC);
+        header.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+        header.extend_from_slice(&(self.page_size as u32).to_be_bytes());
+        header.extend_from_slice(&self.len.to_be_bytes());
+        header.extend_from_slice(&self.table_offset.to_be_bytes());
+        header.extend_from_slice(&self.table_capacity.to_be_bytes());
+        self.file.rewind()?;
+        self.file.write_all(&header)?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+impl Read for CompressedStorage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut inner = self.lock();
+        let n = inner.len.saturating_sub(self.pos).min(buf.len() as u64) as usize;
+        inner.read_at(self.pos, &mut buf[..n])?;
+        drop(inner);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for CompressedStorage {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write_at(self.pos, buf)?;
+        self.pos += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    /// Compress the pages written so far into the file, see [`CompressedStorage`].
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().persist()
+    }
+}
+
+impl Seek for CompressedStorage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.lock().len, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "seek before the start",
+            )),
+        }
+    }
+}
+
+impl Storage for CompressedStorage {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.lock().len)
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        self.lock().set_len(len)
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        let mut inner = self.lock();
+        inner.persist()?;
+        inner.file.sync_all()
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(CompressedStorage {
+            inner: Arc::clone(&self.inner),
+            pos: 0,
+        })
+    }
+}
+
+impl Database {
+    /// Write a compressed copy of the database file at `src` to `dst`,
+    /// to be opened with [`Database::open_compressed`].
+    ///
+    /// Pages of zeros take no space, every other page is deflated on its own.
+    /// The blob file, if any, is copied as it is.
+    /// Fails if a write-ahead log is waiting to be replayed into `src`,
+    /// open it once to replay it.
+    pub fn compress(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+        if wal::is_pending(src)? {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "write-ahead log not replayed, open the file first",
+            )
+            .into());
+        }
+        let mut input = BufReader::new(File::open(src)?);
+        let layout = read_layout(&mut input)?;
+        input.rewind()?;
+
+        wal::remove(dst)?;
+        let mut output = CompressedStorage::create(dst, layout.page_size)?;
+        let mut page_buf = vec![0; layout.page_size as usize];
+        for pages in 1.. {
+            let n = read_full(&mut input, &mut page_buf)?;
+            output.write_all(&page_buf[..n])?;
+            if n < page_buf.len() {
+                break;
+            }
+            // so that at most this many pages are held uncompressed
+            if pages % FLUSH_PAGES == 0 {
+                output.flush()?;
+            }
+        }
+        output.sync_all()?;
+        copy_blob_file(src, dst)
+    }
+
+    /// Write the uncompressed database held in the compressed file at `src`
+    /// to a database file at `dst`, truncating it if there is one.
+    ///
+    /// The blob file, if any, is copied as it is.
+    pub fn decompress(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+        let mut input = CompressedStorage::open(src)?;
+        wal::remove(dst)?;
+        let mut output = File::create(dst)?;
+        io::copy(&mut input, &mut output)?;
+        output.sync_all()?;
+        copy_blob_file(src, dst)
+    }
+}
+
+impl Database<CompressedStorage> {
+    /// Open a compressed file made by [`Database::compress`].
+    pub fn open_compressed(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        Database::open_compressed_with_options(path, DatabaseOptions::default())
+    }
+
+    /// Like [`Database::open_compressed`], with the given options.
+    ///
+    /// There is no write-ahead log for compressed files, `options.wal` must not be set.
+    pub fn open_compressed_with_options(
+        path: impl AsRef<Path>,
+        options: DatabaseOptions,
+    ) -> Result<Self, DatabaseError> {
+        if options.wal {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "no write-ahead log for compressed files",
+            )
+            .into());
+        }
+        let storage = CompressedStorage::open(path.as_ref())?;
+        Database::from_storage(storage, path.as_ref().to_path_buf(), options)
+    }
+}
+
+/// Fill `buf` as far as the input goes, returning how much was read.
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match input.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(read) => n += read,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+fn copy_blob_file(src: &Path, dst: &Path) -> Result<(), DatabaseError> {
+    let blob_path = |path: &Path| {
+        let mut path = path.as_os_str().to_owned();
+        path.push(".blob");
+        PathBuf::from(path)
+    };
+    match fs::copy(blob_path(src), blob_path(dst)) {
+        Ok(_) => Ok(()),
+        // nor should a blob file of an old `dst` stay
+        Err(e) if e.kind() == ErrorKind::NotFound => match fs::remove_file(blob_path(dst)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn bad_file(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,306 @@
+//! Tables as CSV text.
+
+use std::{
+    borrow::Cow,
+    fmt::Write as _,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    storage::Storage,
+    table::{
+        be_key_i32, be_key_i64, from_be_key_i32, from_be_key_i64, ColumnDef, ColumnType,
+        DatabaseError,
+    },
+    Database,
+};
+
+impl<S: Storage> Database<S> {
+    /// Write a table as CSV, a header line of column names
+    /// and then one line per live row, in storage order.
+    ///
+    /// Int columns of 4 or 8 bytes and UInt columns of up to 8 bytes
+    /// are written as decimal numbers,
+    /// text up to its first zero byte, and bool as `true` or `false`.
+    /// Blob columns are written as the lowercase hex of their value,
+    /// every other column as the lowercase hex of its bytes.
+    ///
+    /// Rows are read one data page at a time.
+    pub fn export_csv(
+        &mut self,
+        table_name: &str,
+        out: &mut impl Write,
+    ) -> Result<(), DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let header: Vec<_> = defs.iter().map(|def| csv_field(&def.name)).collect();
+        writeln!(out, "{}", header.join(","))?;
+
+        let mut line = String::new();
+        for n in 0..self.data_pages(table_name)?.len() {
+            for row in self.select_nth_page(table_name, n)? {
+                line.clear();
+                let mut start = 0;
+                for (i, def) in defs.iter().enumerate() {
+                    let value = row.get(start..start + def.size as usize).ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "column sizes exceed row length")
+                    })?;
+                    start += def.size as usize;
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    self.push_csv_value(&mut line, def, value)?;
+                }
+                writeln!(out, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert the rows of CSV input into a table,
+    /// the reverse of [`Database::export_csv`].
+    ///
+    /// The header line names the columns, in any order,
+    /// columns it leaves out are zeroed.
+    /// Values are parsed as `export_csv` writes them,
+    /// bool also accepts `1` and `0`. Empty lines are skipped.
+    ///
+    /// All of the input is parsed before anything is written,
+    /// a bad line gives [`DatabaseError::Csv`] and imports nothing.
+    /// The rows are then written by [`Database::insert_many`],
+    /// whose count of rows written is returned.
+    pub fn import_csv(
+        &mut self,
+        table_name: &str,
+        mut input: impl Read,
+    ) -> Result<usize, DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let mut records = parse_csv(&text)?.into_iter();
+        let Some((_, header)) = records.next() else {
+            return Err(csv_error(1, "no header line".to_owned()));
+        };
+
+        // for each field of a line, the index of its column
+        let mut columns = Vec::with_capacity(header.len());
+        for name in &header {
+            let Some(index) = defs.iter().position(|def| def.name == *name) else {
+                return Err(csv_error(1, format!("no column {name}")));
+            };
+            if columns.contains(&index) {
+                return Err(csv_error(1, format!("column {name} given twice")));
+            }
+            columns.push(index);
+        }
+        let mut offsets = Vec::with_capacity(defs.len());
+        let mut row_len = 0;
+        for def in &defs {
+            offsets.push(row_len);
+            row_len += def.size as usize;
+        }
+
+        let mut rows = Vec::new();
+        // blob values are stored once every line has parsed
+        let mut blobs = Vec::new();
+        for (line, fields) in records {
+            if fields.len() != columns.len() {
+                return Err(csv_error(
+                    line,
+                    format!("{} fields for {} columns", fields.len(), columns.len()),
+                ));
+            }
+            let row_start = rows.len();
+            rows.resize(row_start + row_len, 0);
+            for (field, &index) in fields.iter().zip(&columns) {
+                let def = &defs[index];
+                let start = row_start + offsets[index];
+                let value = &mut rows[start..start + def.size as usize];
+                match parse_csv_value(def, field, value) {
+                    Ok(None) => {}
+                    Ok(Some(blob)) => blobs.push((start, blob)),
+                    Err(message) => {
+                        return Err(csv_error(line, format!("column {}: {message}", def.name)))
+                    }
+                }
+            }
+        }
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        for (start, blob) in blobs {
+            let pointer = self.blob_file()?.put(&blob)?;
+            rows[start..start + pointer.len()].copy_from_slice(&pointer);
+        }
+        self.insert_many(table_name, &rows)
+    }
+
+    fn push_csv_value(
+        &mut self,
+        line: &mut String,
+        def: &ColumnDef<String>,
+        value: &[u8],
+    ) -> io::Result<()> {
+        match (def.column_type, value.len()) {
+            (ColumnType::Int, 4) => {
+                let _ = write!(line, "{}", from_be_key_i32(value.try_into().unwrap()));
+            }
+            (ColumnType::Int, 8) => {
+                let _ = write!(line, "{}", from_be_key_i64(value.try_into().unwrap()));
+            }
+            (ColumnType::UInt, 0..=8) => {
+                let mut int = [0; 8];
+                int[8 - value.len()..].copy_from_slice(value);
+                let _ = write!(line, "{}", u64::from_be_bytes(int));
+            }
+            (ColumnType::Text, _) => {
+                let end = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+                line.push_str(&csv_field(&String::from_utf8_lossy(&value[..end])));
+            }
+            (ColumnType::Bool, _) => {
+                let set = value.last().is_some_and(|b| *b != 0);
+                line.push_str(if set { "true" } else { "false" });
+            }
+            (ColumnType::Blob, _) => push_hex(line, &self.blob_file()?.get(value)?),
+            _ => push_hex(line, value),
+        }
+        Ok(())
+    }
+}
+
+fn csv_error(line: usize, message: String) -> DatabaseError {
+    DatabaseError::Csv { line, message }
+}
+
+/// Split CSV text into lines of fields, each with its line number.
+///
+/// Quoted fields may hold commas, doubled quotes and line breaks.
+fn parse_csv(text: &str) -> Result<Vec<(usize, Vec<String>)>, DatabaseError> {
+    let mut records = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while chars.peek().is_some() {
+        let start_line = line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        loop {
+            match chars.next() {
+                Some('"') if quoted => {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                Some('"') if field.is_empty() => quoted = true,
+                Some('\n') if !quoted => {
+                    line += 1;
+                    break;
+                }
+                Some('\r') if !quoted && chars.peek() == Some(&'\n') => {}
+                Some(',') if !quoted => fields.push(std::mem::take(&mut field)),
+                Some(c) => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+                None if quoted => {
+                    return Err(csv_error(
+                        start_line,
+                        "unterminated quoted field".to_owned(),
+                    ))
+                }
+                None => break,
+            }
+        }
+        if fields.is_empty() && field.is_empty() {
+            continue;
+        }
+        fields.push(field);
+        records.push((start_line, fields));
+    }
+    Ok(records)
+}
+
+/// Parse a field into `value`, which is zeroed and of the column size.
+///
+/// Blob values are returned instead, to be stored in the blob file.
+fn parse_csv_value(
+    def: &ColumnDef<String>,
+    field: &str,
+    value: &mut [u8],
+) -> Result<Option<Vec<u8>>, String> {
+    match (def.column_type, value.len()) {
+        (ColumnType::Int, 4) => {
+            let int = field.parse::<i32>().map_err(|e| e.to_string())?;
+            value.copy_from_slice(&be_key_i32(int));
+        }
+        (ColumnType::Int, 8) => {
+            let int = field.parse::<i64>().map_err(|e| e.to_string())?;
+            value.copy_from_slice(&be_key_i64(int));
+        }
+        (ColumnType::UInt, len @ 0..=8) => {
+            let int = field.parse::<u64>().map_err(|e| e.to_string())?;
+            let bytes = int.to_be_bytes();
+            if bytes[..8 - len].iter().any(|b| *b != 0) {
+                return Err(format!("{int} does not fit in {len} bytes"));
+            }
+            value.copy_from_slice(&bytes[8 - len..]);
+        }
+        (ColumnType::Text, len) => {
+            if field.len() > len {
+                return Err(format!("text of {} bytes, at most {len} fit", field.len()));
+            }
+            value[..field.len()].copy_from_slice(field.as_bytes());
+        }
+        (ColumnType::Bool, _) => {
+            let set = match field {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return Err(format!("{field:?} is not a bool")),
+            };
+            if let Some(last) = value.last_mut() {
+                *last = set as u8;
+            }
+        }
+        (ColumnType::Blob, _) => return parse_hex(field).map(Some),
+        (_, len) => {
+            let bytes = parse_hex(field)?;
+            if bytes.len() != len {
+                return Err(format!("{} bytes for a column of {len}", bytes.len()));
+            }
+            value.copy_from_slice(&bytes);
+        }
+    }
+    Ok(None)
+}
+
+fn parse_hex(field: &str) -> Result<Vec<u8>, String> {
+    if !field.len().is_multiple_of(2) || !field.is_ascii() {
+        return Err(format!("{field:?} is not hex"));
+    }
+    (0..field.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&field[i..i + 2], 16).map_err(|_| format!("{field:?} is not hex"))
+        })
+        .collect()
+}
+
+/// Quote a field if it holds a comma, a quote or a line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn push_hex(line: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        let _ = write!(line, "{b:02x}");
+    }
+}
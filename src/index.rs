@@ -0,0 +1,662 @@
+//! Indexes of the rows of a table by a byte range,
+//! kept in memory and saved to pages of their own.
+//!
+//! A table with indexes has an index page, named by its table option record,
+//! holding `[saved u8][count u8]` and then `[start u16][end u16][first page i32]`
+//! for each index. The entries of an index are kept in a chain of pages
+//! `[next page i32][count u16]` followed by `[key][page i32][slot u32]` entries.
+//! The chains hold the rows as of the last save only while `saved` is set.
+
+use std::{
+    cmp::Ordering,
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
+    io::{self, BufReader, ErrorKind, Read, Seek},
+    ops::{Bound, Range},
+};
+
+use crate::{
+    check_conditions, check_table_exists, is_empty_row, read_checked_page, row_data,
+    storage::Storage,
+    table::{DatabaseError, DeletePolicy, Filter, RowId},
+    Database, HeaderMeta, Layout,
+};
+
+// [saved u8][count u8] at the start of the index page
+const INDEX_PAGE_HEADER_LEN: usize = 2;
+// [start u16][end u16][first page i32]
+const INDEX_DEF_LEN: usize = 8;
+// [next page i32][count u16] at the start of an entry page
+const ENTRY_PAGE_HEADER_LEN: usize = 6;
+// [page i32][slot u32] after the key
+const ENTRY_POS_LEN: usize = 8;
+// byte of the table option record where the index page is
+const OPTION_INDEX_PAGE: usize = 7;
+
+/// Positions of the rows of a table by their bytes in `range`,
+/// see [`Database::create_index`].
+#[derive(Clone)]
+pub(crate) struct KeyIndex {
+    pub(crate) range: Range<usize>,
+    keys: BTreeMap<Vec<u8>, BTreeSet<RowId>>,
+    // key of each indexed row, to drop the rows of a page
+    rows: BTreeMap<RowId, Vec<u8>>,
+}
+
+impl KeyIndex {
+    fn new(range: Range<usize>) -> Self {
+        KeyIndex {
+            range,
+            keys: BTreeMap::new(),
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Index the live rows of a data page, `page_buf` holding its slots only.
+    fn add_page(
+        &mut self,
+        page: i32,
+        page_buf: &[u8],
+        row_len: usize,
+        delete_policy: DeletePolicy,
+    ) {
+        for (slot, row) in page_buf.chunks_exact(row_len).enumerate() {
+            if is_empty_row(row, delete_policy) {
+                continue;
+            }
+            let row_id = RowId {
+                page,
+                slot: slot as u32,
+            };
+            let key = row[self.range.clone()].to_vec();
+            self.keys.entry(key.clone()).or_default().insert(row_id);
+            self.rows.insert(row_id, key);
+        }
+    }
+
+    /// Forget the rows of a page.
+    fn remove_page(&mut self, page: i32) {
+        let first = RowId { page, slot: 0 };
+        let last = RowId {
+            page,
+            slot: u32::MAX,
+        };
+        let row_ids: Vec<RowId> = self.rows.range(first..=last).map(|(id, _)| *id).collect();
+        for row_id in row_ids {
+            let key = self.rows.remove(&row_id).unwrap();
+            if let Entry::Occupied(mut entry) = self.keys.entry(key) {
+                entry.get_mut().remove(&row_id);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.keys.clear();
+        self.rows.clear();
+    }
+
+    /// Entries that fit in an entry page.
+    fn entries_per_page(&self, layout: Layout) -> usize {
+        (layout.page_body() as usize - ENTRY_PAGE_HEADER_LEN) / (self.range.len() + ENTRY_POS_LEN)
+    }
+}
+
+/// Indexes a table may have, as many as the index page has room for.
+fn max_indexes(layout: Layout) -> usize {
+    ((layout.page_body() as usize - INDEX_PAGE_HEADER_LEN) / INDEX_DEF_LEN).min(u8::MAX as usize)
+}
+
+/// Read the index page of a table and the entry pages it names.
+///
+/// Returns the indexes, with their entries if they were saved,
+/// the entry pages and whether the indexes were saved.
+pub(crate) fn read_indexes(
+    reader: &mut BufReader<impl Read + Seek>,
+    layout: Layout,
+    index_page: i32,
+) -> io::Result<(Vec<KeyIndex>, Vec<i32>, bool)> {
+    let mut page_buf = vec![0; layout.page_size as usize];
+    read_checked_page(reader, layout, index_page, &mut page_buf)?;
+    let saved = page_buf[0] != 0;
+    let count = page_buf[1] as usize;
+    if count > max_indexes(layout) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "index count overflow",
+        ));
+    }
+    let defs: Vec<(Range<usize>, i32)> = page_buf
+        [INDEX_PAGE_HEADER_LEN..INDEX_PAGE_HEADER_LEN + count * INDEX_DEF_LEN]
+        .chunks_exact(INDEX_DEF_LEN)
+        .map(|def| {
+            let start = u16::from_be_bytes([def[0], def[1]]) as usize;
+            let end = u16::from_be_bytes([def[2], def[3]]) as usize;
+            (
+                start..end,
+                i32::from_be_bytes(def[4..8].try_into().unwrap()),
+            )
+        })
+        .collect();
+
+    let mut indexes = Vec::with_capacity(defs.len());
+    let mut entry_pages = Vec::new();
+    for (range, first_page) in defs {
+        let mut index = KeyIndex::new(range);
+        let entry_len = index.range.len() + ENTRY_POS_LEN;
+        let mut page = first_page;
+        while page != 0 {
+            if page == index_page || entry_pages.contains(&page) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "index entry pages loop",
+                ));
+            }
+            entry_pages.push(page);
+            read_checked_page(reader, layout, page, &mut page_buf)?;
+            let count = u16::from_be_bytes([page_buf[4], page_buf[5]]) as usize;
+            if saved {
+                let entries = page_buf[ENTRY_PAGE_HEADER_LEN..]
+                    .chunks_exact(entry_len)
+                    .take(count);
+                for entry in entries {
+                    let (key, pos) = entry.split_at(index.range.len());
+                    let row_id = RowId {
+                        page: i32::from_be_bytes(pos[..4].try_into().unwrap()),
+                        slot: u32::from_be_bytes(pos[4..].try_into().unwrap()),
+                    };
+                    index.keys.entry(key.to_vec()).or_default().insert(row_id);
+                    index.rows.insert(row_id, key.to_vec());
+                }
+            }
+            page = i32::from_be_bytes(page_buf[..4].try_into().unwrap());
+        }
+        indexes.push(index);
+    }
+    Ok((indexes, entry_pages, saved))
+}
+
+/// Which pages the indexes of freshly loaded tables miss, see [`read_indexes`].
+///
+/// Indexes not saved since the rows last changed are built again on first use.
+pub(crate) fn stale_after_load(header_table: &HashMap<String, HeaderMeta>) -> Option<HashSet<i32>> {
+    if header_table
+        .values()
+        .any(|meta| !meta.indexes.is_empty() && !meta.indexes_saved)
+    {
+        None
+    } else {
+        Some(HashSet::new())
+    }
+}
+
+impl<S: Storage> Database<S> {
+    /// Index the rows of a table by their bytes in `range`,
+    /// so that [`Database::select`] with an equality condition on exactly `range`
+    /// reads only the pages holding matching rows.
+    ///
+    /// The index is built by a scan and kept in memory,
+    /// brought up to date with the pages written since whenever it is used.
+    /// Its range is kept in the file, and its entries are saved
+    /// by [`Database::sync`] and when the database is dropped,
+    /// so that opening the database loads it.
+    /// An index whose table changed after it was last saved
+    /// is built again on first use instead.
+    pub fn create_index(
+        &mut self,
+        table_name: &str,
+        range: Range<usize>,
+    ) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        if range.is_empty() || range.end > meta.data_len() as usize {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "index range out of row").into());
+        }
+        if meta.indexes.iter().any(|index| index.range == range) {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                "the table has an index on this range",
+            )
+            .into());
+        }
+        if meta.indexes.len() >= max_indexes(self.layout) {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "too many indexes").into());
+        }
+        if range.len() + ENTRY_POS_LEN > self.layout.page_body() as usize - ENTRY_PAGE_HEADER_LEN {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "index key too long").into());
+        }
+        // the other indexes are then up to date with the new one
+        self.refresh_indexes()?;
+
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let slots = self.layout.rows_per_page(meta.row_len) as usize;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let mut index = KeyIndex::new(range);
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut page_buf)?;
+            index.add_page(page, &page_buf[..slots * row_len], row_len, delete_policy);
+        }
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.indexes.push(index);
+        }
+        Ok(self.write_index_page(table_name)?)
+    }
+
+    /// Drop the index of a table on `range`, and tell whether there was one.
+    pub fn drop_index(
+        &mut self,
+        table_name: &str,
+        range: Range<usize>,
+    ) -> Result<bool, DatabaseError> {
+        self.check_poisoned()?;
+        let Some(meta) = self.header_table.get_mut(table_name) else {
+            return Err(DatabaseError::TableNotFound);
+        };
+        let count = meta.indexes.len();
+        meta.indexes.retain(|index| index.range != range);
+        if meta.indexes.len() == count {
+            return Ok(false);
+        }
+        self.write_index_page(table_name)?;
+        Ok(true)
+    }
+
+    /// Write the ranges of the indexes of a table to its index page,
+    /// marked as not saved, and free the entry pages.
+    ///
+    /// The index page is allocated for the first index and freed with the last one.
+    pub(crate) fn write_index_page(&mut self, table_name: &str) -> io::Result<()> {
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (col_def_offset, alloc_hint) = (meta.col_def_offset, meta.alloc_hint);
+        let old_page = meta.index_page;
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        page_buf[1] = meta.indexes.len() as u8;
+        for (index, def) in meta.indexes.iter().zip(
+            page_buf[INDEX_PAGE_HEADER_LEN..]
+                .chunks_exact_mut(INDEX_DEF_LEN)
+                .take(max_indexes(self.layout)),
+        ) {
+            def[..2].copy_from_slice(&(index.range.start as u16).to_be_bytes());
+            def[2..4].copy_from_slice(&(index.range.end as u16).to_be_bytes());
+        }
+        let index_page = match (meta.indexes.is_empty(), old_page) {
+            (true, _) => 0,
+            (false, 0) => self.alloc_page(alloc_hint)?,
+            (false, page) => page,
+        };
+
+        self.poisoned = true;
+        if index_page != 0 {
+            self.write_page(index_page, &page_buf)?;
+        } else if old_page != 0 {
+            // cleared for whoever reuses it
+            page_buf.fill(0);
+            self.write_page(old_page, &page_buf)?;
+        }
+        if index_page != old_page {
+            let option_pos = self
+                .layout
+                .def_pos(col_def_offset, self.layout.table_option_record());
+            self.write_at(
+                option_pos + OPTION_INDEX_PAGE as u64,
+                &index_page.to_be_bytes(),
+            )?;
+        }
+        self.flush()?;
+
+        let Some(meta) = self.header_table.get_mut(table_name) else {
+            return Ok(());
+        };
+        let entry_pages = std::mem::take(&mut meta.index_entry_pages);
+        meta.index_page = index_page;
+        meta.indexes_saved = false;
+        for page in entry_pages {
+            self.free_page(page);
+        }
+        if index_page == 0 && old_page != 0 {
+            self.free_page(old_page);
+        }
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Save the entries of the indexes changed since they were last saved
+    /// to fresh entry pages, and mark them as saved.
+    pub(crate) fn save_indexes(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        let names: Vec<String> = self
+            .header_table
+            .iter()
+            .filter(|(_, meta)| !meta.indexes.is_empty() && !meta.indexes_saved)
+            .map(|(name, _)| name.clone())
+            .collect();
+        if names.is_empty() {
+            return Ok(());
+        }
+        self.refresh_indexes()?;
+        for name in names {
+            self.save_table_indexes(&name)?;
+        }
+        Ok(())
+    }
+
+    fn save_table_indexes(&mut self, table_name: &str) -> io::Result<()> {
+        let layout = self.layout;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (index_page, alloc_hint) = (meta.index_page, meta.alloc_hint);
+        let page_counts: Vec<usize> = meta
+            .indexes
+            .iter()
+            .map(|index| index.rows.len().div_ceil(index.entries_per_page(layout)))
+            .collect();
+        // the chains are not in use while the index page says unsaved
+        let old_pages = meta.index_entry_pages.clone();
+        for &page in &old_pages {
+            self.free_page(page);
+        }
+        let mut new_pages = Vec::with_capacity(page_counts.iter().sum());
+        while new_pages.len() < new_pages.capacity() {
+            match self.alloc_page(alloc_hint) {
+                Ok(page) => new_pages.push(page),
+                Err(e) => {
+                    for &page in &new_pages {
+                        self.free_page(page);
+                    }
+                    for &page in &old_pages {
+                        self.in_use_pages.insert(page);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let mut pages = new_pages.iter().copied();
+        let mut first_pages = Vec::with_capacity(page_counts.len());
+        let mut images = Vec::with_capacity(new_pages.len());
+        for (index, &page_count) in meta.indexes.iter().zip(&page_counts) {
+            let chain: Vec<i32> = pages.by_ref().take(page_count).collect();
+            first_pages.push(chain.first().copied().unwrap_or(0));
+            let entries: Vec<(&RowId, &Vec<u8>)> = index.rows.iter().collect();
+            let per_page = index.entries_per_page(layout);
+            for (i, chunk) in entries.chunks(per_page.max(1)).enumerate() {
+                let mut page_buf = vec![0; layout.page_size as usize];
+                let next = chain.get(i + 1).copied().unwrap_or(0);
+                page_buf[..4].copy_from_slice(&next.to_be_bytes());
+                page_buf[4..6].copy_from_slice(&(chunk.len() as u16).to_be_bytes());
+                let entry_len = index.range.len() + ENTRY_POS_LEN;
+                for ((row_id, key), entry) in chunk
+                    .iter()
+                    .zip(page_buf[ENTRY_PAGE_HEADER_LEN..].chunks_exact_mut(entry_len))
+                {
+                    let (key_bytes, pos) = entry.split_at_mut(key.len());
+                    key_bytes.copy_from_slice(key);
+                    pos[..4].copy_from_slice(&row_id.page.to_be_bytes());
+                    pos[4..].copy_from_slice(&row_id.slot.to_be_bytes());
+                }
+                images.push((chain[i], page_buf));
+            }
+        }
+
+        self.poisoned = true;
+        for (page, page_buf) in &images {
+            self.write_page(*page, page_buf)?;
+        }
+        self.flush()?;
+        // the entries are in place before they are marked as saved
+        let mut page_buf = vec![0; layout.page_size as usize];
+        self.read_page(index_page, &mut page_buf)?;
+        page_buf[0] = 1;
+        for (first_page, def) in first_pages
+            .iter()
+            .zip(page_buf[INDEX_PAGE_HEADER_LEN..].chunks_exact_mut(INDEX_DEF_LEN))
+        {
+            def[4..8].copy_from_slice(&first_page.to_be_bytes());
+        }
+        self.write_page(index_page, &page_buf)?;
+        self.flush()?;
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.index_entry_pages = new_pages;
+            meta.indexes_saved = true;
+        }
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Remember pages written to for [`Database::refresh_indexes`],
+    /// `None` if which ones is unknown.
+    ///
+    /// Saved indexes of the tables the pages belong to are saved no longer,
+    /// which is written to their index pages.
+    pub(crate) fn mark_index_pages(
+        &mut self,
+        pages: Option<impl IntoIterator<Item = i32>>,
+    ) -> io::Result<()> {
+        if self
+            .header_table
+            .values()
+            .all(|meta| meta.indexes.is_empty())
+        {
+            return Ok(());
+        }
+        let pages: Option<HashSet<i32>> = pages.map(|pages| pages.into_iter().collect());
+        let mut unsaved = Vec::new();
+        for meta in self.header_table.values_mut() {
+            let written = match &pages {
+                Some(pages) => {
+                    pages.contains(&meta.meta_offset)
+                        || meta.table_offsets.values().any(|page| pages.contains(page))
+                }
+                None => true,
+            };
+            if meta.indexes_saved && written {
+                meta.indexes_saved = false;
+                unsaved.push(meta.index_page);
+            }
+        }
+        match (&mut self.stale_index_pages, pages) {
+            (Some(stale), Some(pages)) => stale.extend(pages),
+            (stale, _) => *stale = None,
+        }
+        for page in unsaved {
+            self.write_at(self.layout.page_pos(page), &[0])?;
+        }
+        Ok(())
+    }
+
+    /// Bring the indexes up to date with the pages written since they last were.
+    pub(crate) fn refresh_indexes(&mut self) -> io::Result<()> {
+        if self
+            .stale_index_pages
+            .as_ref()
+            .is_some_and(|pages| pages.is_empty())
+        {
+            return Ok(());
+        }
+        let names: Vec<String> = self
+            .header_table
+            .iter()
+            .filter(|(_, meta)| !meta.indexes.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect();
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for name in names {
+            let meta = check_table_exists(&self.header_table, &name)?;
+            let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+            let slots = self.layout.rows_per_page(meta.row_len) as usize;
+            let owned: HashSet<i32> = meta.table_offsets.values().copied().collect();
+            let pages: Vec<i32> = match &self.stale_index_pages {
+                Some(pages) => pages.iter().copied().collect(),
+                // which pages changed is unknown, start over
+                None => {
+                    if let Some(meta) = self.header_table.get_mut(&name) {
+                        meta.indexes.iter_mut().for_each(KeyIndex::clear);
+                    }
+                    owned.iter().copied().collect()
+                }
+            };
+            for page in pages {
+                // pages the table gave up are dropped only
+                let live = owned.contains(&page);
+                if live {
+                    self.read_page(page, &mut page_buf)?;
+                }
+                if let Some(meta) = self.header_table.get_mut(&name) {
+                    for index in &mut meta.indexes {
+                        index.remove_page(page);
+                        if live {
+                            index.add_page(
+                                page,
+                                &page_buf[..slots * row_len],
+                                row_len,
+                                delete_policy,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        self.stale_index_pages = Some(HashSet::new());
+        Ok(())
+    }
+
+    /// The rows [`Database::select`] returns, found through an index
+    /// if one of `conditions` tests an indexed range for equality.
+    pub(crate) fn select_indexed<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<Option<Vec<Vec<u8>>>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        let lookup = conditions.iter().find_map(|c| {
+            let (range, key) = c.equality()?;
+            let index = meta.indexes.iter().position(|index| index.range == range)?;
+            Some((index, key.to_vec()))
+        });
+        let Some((index, key)) = lookup else {
+            return Ok(None);
+        };
+        self.refresh_indexes()?;
+
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let slots = self.layout.rows_per_page(meta.row_len);
+        // rows come in storage order, by meta record then slot
+        let records: HashMap<i32, u8> = meta
+            .table_offsets
+            .iter()
+            .map(|(&record_number, &page)| (page, record_number))
+            .collect();
+        let mut row_ids: Vec<(u8, RowId)> = meta.indexes[index]
+            .keys
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter(|row_id| row_id.slot < slots)
+            .filter_map(|row_id| Some((*records.get(&row_id.page)?, *row_id)))
+            .collect();
+        row_ids.sort_unstable();
+
+        let mut rows = Vec::new();
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        let mut loaded = None;
+        for (_, row_id) in row_ids {
+            if loaded != Some(row_id.page) {
+                self.read_page(row_id.page, &mut page_buf)?;
+                loaded = Some(row_id.page);
+            }
+            let start = row_id.slot as usize * row_len;
+            let row = &page_buf[start..start + row_len];
+            if !is_empty_row(row, delete_policy) && conditions.iter().all(|c| c.matches(row)) {
+                rows.push(row_data(row, delete_policy).to_vec());
+            }
+        }
+        Ok(Some(rows))
+    }
+
+    /// The rows [`Database::range_scan`] returns, in key order,
+    /// if the table has an index on `key_range`.
+    pub(crate) fn range_scan_indexed(
+        &mut self,
+        table_name: &str,
+        key_range: &Range<usize>,
+        start: &[u8],
+        end: &[u8],
+        inclusive_end: bool,
+    ) -> io::Result<Option<Vec<Vec<u8>>>> {
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let Some(index) = meta
+            .indexes
+            .iter()
+            .position(|index| index.range == *key_range)
+        else {
+            return Ok(None);
+        };
+        // BTreeMap::range panics on an empty or reversed range
+        match start.cmp(end) {
+            Ordering::Greater => return Ok(Some(Vec::new())),
+            Ordering::Equal if !inclusive_end => return Ok(Some(Vec::new())),
+            _ => {}
+        }
+        self.refresh_indexes()?;
+
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let slots = self.layout.rows_per_page(meta.row_len);
+        let end_bound = if inclusive_end {
+            Bound::Included(end)
+        } else {
+            Bound::Excluded(end)
+        };
+        let owned: HashSet<i32> = meta.table_offsets.values().copied().collect();
+        let row_ids: Vec<RowId> = meta.indexes[index]
+            .keys
+            .range::<[u8], _>((Bound::Included(start), end_bound))
+            .flat_map(|(_, row_ids)| row_ids)
+            .filter(|row_id| row_id.slot < slots && owned.contains(&row_id.page))
+            .copied()
+            .collect();
+
+        let mut rows = Vec::new();
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        let mut loaded = None;
+        for row_id in row_ids {
+            if loaded != Some(row_id.page) {
+                self.read_page(row_id.page, &mut page_buf)?;
+                loaded = Some(row_id.page);
+            }
+            let row_start = row_id.slot as usize * row_len;
+            let row = &page_buf[row_start..row_start + row_len];
+            let key = &row[key_range.clone()];
+            let in_range = key >= start
+                && match key.cmp(end) {
+                    Ordering::Less => true,
+                    Ordering::Equal => inclusive_end,
+                    Ordering::Greater => false,
+                };
+            if !is_empty_row(row, delete_policy) && in_range {
+                rows.push(row_data(row, delete_policy).to_vec());
+            }
+        }
+        Ok(Some(rows))
+    }
+}
+
+impl<S: Storage> Drop for Database<S> {
+    /// Save the indexes, see [`Database::create_index`].
+    ///
+    /// Errors are ignored, and nothing is saved while a transaction is open
+    /// or after a mutation failed halfway.
+    fn drop(&mut self) {
+        if !self.poisoned && self.transaction.is_none() {
+            let _ = self.save_indexes();
+        }
+    }
+}
@@ -1,132 +1,809 @@
 #![doc = include_str!("../README.md")]
 
 use std::{
-    collections::{HashMap, HashSet},
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Debug,
     fs::File,
     io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
     ops::Range,
-    path::Path,
+    path::{Path, PathBuf},
+    slice,
+    sync::Arc,
 };
 
-use table::{ColumnDef, Condition, CreateTableError};
+use blob::BlobFile;
+use cache::{Frame, PageCache};
+use index::KeyIndex;
+pub use readonly::ReadOnlyDatabase;
+#[cfg(feature = "serde")]
+pub use row_serde::{decode_row, encode_row};
+pub use shared::SharedDatabase;
+use storage::{FileStorage, MemoryStorage, Storage};
+use table::{
+    default_bytes, ColumnDef, ColumnStats, ColumnType, Condition, CreateTableError, DatabaseError,
+    DeletePolicy, Filter, InsertReport, Predicate, RowId, StorageError, TableInfo, TableOptions,
+    TableStats, UpdateReport, BLOB_POINTER_LEN,
+};
+use wal::Wal;
 
+mod backup;
+mod blob;
+mod cache;
+#[cfg(feature = "compression")]
+mod compressed;
+mod csv;
+mod index;
+mod readonly;
+#[cfg(feature = "serde")]
+mod row_serde;
+mod shared;
+pub mod storage;
 pub mod table;
+mod wal;
 
-const PAGE_SIZE: u32 = 4 * 1024;
-const TABLE_NAME_MAX_LEN: u8 = 23;
-const COLUMN_NAME_MAX_LEN: u8 = 28;
-const HEADER_TABLE_ROW_LEN: u8 = 32;
-const HEADER_TABLE_RECORD_COUNT: u8 = (PAGE_SIZE / HEADER_TABLE_ROW_LEN as u32) as u8;
+const DEFAULT_PAGE_SIZE: u32 = 4 * 1024;
 const META_TABLE_ROW_LEN: u8 = 32;
-const META_TABLE_RECORD_COUNT: u8 = (PAGE_SIZE / META_TABLE_ROW_LEN as u32) as u8;
-const DEF_TABLE_ROW_LEN: u8 = 32;
-const DEF_TABLE_RECORD_COUNT: u8 = (PAGE_SIZE / DEF_TABLE_ROW_LEN as u32) as u8;
+const TOMBSTONE: u8 = 0xFF;
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+// name length of the first header record when it holds the layout
+const LAYOUT_MARKER: u8 = 0xFF;
+// layout flag of databases whose pages end with a checksum
+const LAYOUT_CHECKSUMS: u8 = 1;
+// CRC32 of the rest of the page, at its end
+const CHECKSUM_LEN: u32 = 4;
+
+/// Page size and lengths of header and def records, fixed when the database is created.
+///
+/// Databases with the default 32 byte records, 4 KiB pages and no checksums
+/// have no layout record, others keep `[LAYOUT_MARKER][record_len][flags][page_size u32]`
+/// in the first header record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    record_len: u8,
+    checksums: bool,
+    page_size: u32,
+}
+
+impl Layout {
+    const DEFAULT: Layout = Layout {
+        record_len: 32,
+        checksums: false,
+        page_size: DEFAULT_PAGE_SIZE,
+    };
+
+    fn new(record_len: u8) -> io::Result<Layout> {
+        match record_len {
+            32 | 64 | 128 => Ok(Layout {
+                record_len,
+                ..Layout::DEFAULT
+            }),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "record length must be 32, 64 or 128",
+            )),
+        }
+    }
+
+    /// The layout with `page_size` byte pages,
+    /// which must be a power of two up to 64 KiB
+    /// with room for the layout record and one table.
+    fn with_page_size(self, page_size: u32) -> io::Result<Layout> {
+        let layout = Layout { page_size, ..self };
+        if !page_size.is_power_of_two()
+            || page_size > 1 << 16
+            || page_size <= CHECKSUM_LEN
+            || layout.record_count() <= layout.first_table_record()
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "page size must be a power of two up to 64 KiB holding two header records",
+            ));
+        }
+        Ok(layout)
+    }
+
+    /// Bytes of a page available to records and rows.
+    fn page_body(self) -> u32 {
+        if self.checksums {
+            self.page_size - CHECKSUM_LEN
+        } else {
+            self.page_size
+        }
+    }
+
+    /// Records in a header or def page, at most 255.
+    fn record_count(self) -> u8 {
+        (self.page_body() / self.record_len as u32).min(u8::MAX as u32) as u8
+    }
+
+    /// Records in a meta page, at most 255.
+    fn meta_record_count(self) -> u8 {
+        (self.page_body() / META_TABLE_ROW_LEN as u32).min(u8::MAX as u32) as u8
+    }
+
+    /// How many rows fit in a data page,
+    /// the bytes left at the end of the page are never read.
+    fn rows_per_page(self, row_len: u16) -> u32 {
+        self.page_body().checked_div(row_len as u32).unwrap_or(0)
+    }
+
+    /// The first header record that may hold a table.
+    fn first_table_record(self) -> u8 {
+        if self == Layout::DEFAULT {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// The last def record holds table options instead of a column.
+    fn table_option_record(self) -> u8 {
+        self.record_count() - 1
+    }
+
+    // [len][name][def i32][meta i32]
+    fn table_name_max_len(self) -> u8 {
+        self.record_len - 9
+    }
+
+    // [len][name][type u8][size u16]
+    fn column_name_max_len(self) -> u8 {
+        self.record_len - 4
+    }
+
+    /// Byte offset of a header record.
+    fn header_pos(self, header_record_offset: u8) -> u64 {
+        header_record_offset as u64 * self.record_len as u64
+    }
+
+    /// Byte offset of a page.
+    fn page_pos(self, page: i32) -> u64 {
+        page as u64 * self.page_size as u64
+    }
+
+    /// Byte offset of a def record in the def page.
+    fn def_pos(self, col_def_offset: i32, record: u8) -> u64 {
+        self.page_pos(col_def_offset) + record as u64 * self.record_len as u64
+    }
+}
 
 /// The struct used to operate with the underlying file system.
-pub struct Database {
-    reader: BufReader<File>,
-    writer: BufWriter<File>,
+///
+/// The bytes are kept in a file unless another [`Storage`] is given.
+pub struct Database<S: Storage = FileStorage> {
+    reader: BufReader<S>,
+    writer: TrackedWriter<S>,
     header_table: HashMap<String, HeaderMeta>,
     // absolute offset
     in_use_pages: HashSet<i32>,
+    // spare pages of the file, the last one is reused first;
+    // kept in memory only and rebuilt from the page usage on open
+    free_pages: Vec<i32>,
+    // length of the file as last seen, pages from here on are allocated by growing it
+    file_len: u64,
+    // pages written since the indexes were last brought up to date, None if unknown
+    stale_index_pages: Option<HashSet<i32>>,
+    // set while a mutation is writing, left set if it fails halfway
+    poisoned: bool,
+    // opened by open_readonly, indexes are not saved on drop
+    read_only: bool,
+    options: DatabaseOptions,
+    layout: Layout,
+    // empty without a database file
+    path: PathBuf,
+    // opened on first use
+    blobs: Option<BlobFile>,
+    cache: PageCache,
+    // state to go back to on rollback, the page images are kept by the writer
+    transaction: Option<Transaction>,
+}
+
+/// In-memory state saved by [`Database::begin`].
+struct Transaction {
+    header_table: HashMap<String, HeaderMeta>,
+    in_use_pages: HashSet<i32>,
+    free_pages: Vec<i32>,
+    file_len: u64,
+}
+
+/// Options used when opening a database.
+#[derive(Debug, Clone)]
+pub struct DatabaseOptions {
+    /// The maximum number of pages the file may hold,
+    /// including the header page.
+    ///
+    /// Allocations beyond it fail with [`StorageError::QuotaExceeded`].
+    pub max_pages: Option<i32>,
+    /// Picks pages for new tables and for data pages,
+    /// when no spare page of the file is waiting to be reused,
+    /// that is only when the file has to grow.
+    ///
+    /// Contiguous runs asked for by [`TableOptions::contiguous_pages`]
+    /// are always placed at the first free run.
+    pub allocator: Arc<dyn Allocator>,
+    /// Pages kept in memory by the page cache, zero disables it.
+    ///
+    /// Scans read whole pages through the cache,
+    /// and pages written by other means are dropped from it on flush.
+    pub cache_pages: usize,
+    /// Log written pages to a `.wal` file next to the database
+    /// before writing them to the database file,
+    /// each flush of a mutation as one unit.
+    ///
+    /// [`Database::open`] replays the complete units left in the log
+    /// whether or not this is set.
+    pub wal: bool,
+    /// When written bytes are synced to disk.
+    pub durability: DurabilityMode,
+    /// Pages the file grows by when a page past its end is allocated,
+    /// so that bulk loads extend it once per chunk rather than once per page.
+    ///
+    /// The new pages are zeros and spare until allocated.
+    /// The file never grows past `max_pages`, and zero is taken as one.
+    /// Calls that shrink the file cut it after the last page in use.
+    pub grow_pages: u32,
+}
+
+/// When a database syncs its file, see [`DatabaseOptions::durability`].
+///
+/// Writes handed to the file survive the process
+/// but not necessarily a power failure until they are synced.
+/// With the write-ahead log, each mutation is synced through the log anyway.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DurabilityMode {
+    /// Hand the writes to the file after every mutation, without syncing.
+    #[default]
+    FlushPerOp,
+    /// Hold the writes in memory until [`Database::sync`] or [`Database::commit`]
+    /// writes them to the file and syncs it,
+    /// for batch loads that sync once at the end.
+    ///
+    /// Reads see the held writes, other handles to the file do not.
+    /// Writes still held when the database is dropped are lost.
+    /// Values stored in the blob file are written to it as usual.
+    /// The write-ahead log cannot be used with this mode.
+    Manual,
+    /// Sync after every mutation, before it returns.
+    Fsync,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        DatabaseOptions {
+            max_pages: None,
+            allocator: Arc::new(LinearAllocator),
+            cache_pages: 64,
+            wal: false,
+            durability: DurabilityMode::default(),
+            grow_pages: 64,
+        }
+    }
+}
+
+/// Format of a new database, see [`Database::create_database_with_options`].
+#[derive(Debug, Clone)]
+pub struct CreateOptions {
+    /// Length of header and def records, 32, 64 or 128.
+    pub record_len: u8,
+    /// End every page with a CRC32 of the rest,
+    /// checked when the page is read through the page cache.
+    ///
+    /// Pages hold 4 bytes less.
+    pub checksums: bool,
+    /// Bytes per page, a power of two up to 64 KiB.
+    ///
+    /// Header, def and meta pages use at most 255 records of larger pages.
+    pub page_size: u32,
+}
+
+impl Default for CreateOptions {
+    fn default() -> Self {
+        CreateOptions {
+            record_len: 32,
+            checksums: false,
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+/// Placement policy for new pages.
+pub trait Allocator: Debug + Send + Sync {
+    /// Return a page not in `in_use_pages`.
+    ///
+    /// `hint` is where the table would like its pages,
+    /// and pages from `max_pages` on must not be returned.
+    fn allocate(
+        &self,
+        in_use_pages: &HashSet<i32>,
+        hint: i32,
+        max_pages: Option<i32>,
+    ) -> io::Result<i32>;
+}
+
+/// The default allocator,
+/// takes the first free page from the hint on, then from the start.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearAllocator;
+
+impl Allocator for LinearAllocator {
+    fn allocate(
+        &self,
+        in_use_pages: &HashSet<i32>,
+        hint: i32,
+        max_pages: Option<i32>,
+    ) -> io::Result<i32> {
+        find_spare_page(in_use_pages, hint, max_pages)
+    }
+}
+
+/// Rows of a table accepted by every filter, see [`Database::select_iter`].
+pub struct RowIterator<'a, F, S: Storage = FileStorage> {
+    db: &'a mut Database<S>,
+    conditions: &'a [F],
+    meta_page: Box<[u8]>,
+    // next meta record to look at
+    record: usize,
+    page: Box<[u8]>,
+    // next slot of `page` out of `slots`
+    slot: usize,
+    slots: usize,
+    row_len: usize,
+    delete_policy: DeletePolicy,
+}
+
+impl<F: Filter, S: Storage> Iterator for RowIterator<'_, F, S> {
+    type Item = Result<Vec<u8>, DatabaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.slot < self.slots {
+                let row = &self.page[self.slot * self.row_len..(self.slot + 1) * self.row_len];
+                self.slot += 1;
+                if !is_empty_row(row, self.delete_policy)
+                    && self.conditions.iter().all(|c| c.matches(row))
+                {
+                    return Some(Ok(row_data(row, self.delete_policy).to_vec()));
+                }
+            }
+
+            // move on to the next data page
+            let record = self
+                .meta_page
+                .chunks_exact(META_TABLE_ROW_LEN as usize)
+                .take(self.db.layout.meta_record_count() as usize)
+                .nth(self.record)?;
+            self.record += 1;
+            let table_offset = i32::from_be_bytes(record[..4].try_into().unwrap());
+            if table_offset == 0 {
+                continue;
+            }
+            if let Err(e) = self.db.read_page(table_offset, &mut self.page) {
+                // stop after the error
+                self.record = self.db.layout.meta_record_count() as usize;
+                return Some(Err(e.into()));
+            }
+            self.slot = 0;
+            self.slots = self.db.layout.rows_per_page(self.row_len as u16) as usize;
+        }
+    }
+}
+
+/// Page cache counters, see [`Database::cache_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Page reads served from memory.
+    pub hits: u64,
+    /// Page reads that went to the file.
+    pub misses: u64,
+}
+
+/// Page usage of the file, see [`Database::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseStats {
+    /// Pages the file holds, including the header page.
+    pub file_pages: u64,
+    /// Pages in use, including the header page.
+    pub used_pages: usize,
+    /// Pages of the file not in use, freed or grown ahead of need.
+    ///
+    /// New pages past the end of the file may be allocated too,
+    /// up to [`DatabaseOptions::max_pages`].
+    pub free_pages: u64,
+    /// Each table, sorted by name.
+    pub tables: Vec<(String, TableUsage)>,
+}
+
+/// Pages of one table, see [`DatabaseStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableUsage {
+    pub row_len: u16,
+    pub data_pages: usize,
+    /// Data pages plus the def, meta and index pages.
+    pub total_pages: usize,
+}
+
+/// The rows present when [`Database::snapshot`] was called.
+///
+/// This is a simple consistency aid, not MVCC:
+/// rows added later are hidden and deleted rows stay hidden,
+/// but a row overwritten in place is read with its new bytes.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    // table -> data page -> slots holding a row
+    live: HashMap<String, BTreeMap<i32, Vec<bool>>>,
+}
+
+/// A buffered writer that remembers its position,
+/// so seeking to where it already is costs nothing.
+struct TrackedWriter<S: Storage> {
+    inner: BufWriter<S>,
+    // unknown until the first absolute seek
+    pos: Option<u64>,
+    // pages written to since the last take_touched, None if unknown
+    touched: Option<HashSet<i32>>,
+    // set while a transaction is open
+    undo: Option<UndoLog<S>>,
+    // if set, writes are held here and logged on flush
+    wal: Option<Wal>,
+    // pages get their checksum on flush
+    checksums: bool,
+    // pages written to since their checksum was last updated
+    unsealed: HashSet<i32>,
+    page_size: u64,
+}
+
+/// Contents of the pages before a transaction first wrote to them.
+struct UndoLog<S> {
+    // reads the file independently of the writer
+    source: S,
+    // page -> original bytes, shorter than a page at the end of the file
+    pages: HashMap<i32, Vec<u8>>,
+}
+
+impl<S: Storage> TrackedWriter<S> {
+    fn new(file: S, page_size: u32) -> Self {
+        TrackedWriter {
+            inner: BufWriter::new(file),
+            pos: None,
+            touched: Some(HashSet::new()),
+            undo: None,
+            wal: None,
+            checksums: false,
+            unsealed: HashSet::new(),
+            page_size: page_size as u64,
+        }
+    }
+
+    /// Update the checksums of the pages written to since the last call,
+    /// reading them back from the file.
+    fn seal_written(&mut self) -> io::Result<()> {
+        if self.unsealed.is_empty() {
+            return Ok(());
+        }
+        self.inner.flush()?;
+        let mut page_buf = vec![0; self.page_size as usize];
+        for page in std::mem::take(&mut self.unsealed) {
+            let file = self.inner.get_mut();
+            file.seek(SeekFrom::Start(page as u64 * self.page_size))?;
+            // the file may end inside the page
+            page_buf.fill(0);
+            let mut read = 0;
+            while read < page_buf.len() {
+                match file.read(&mut page_buf[read..])? {
+                    0 => break,
+                    n => read += n,
+                }
+            }
+            seal_page(&mut page_buf);
+            file.seek(SeekFrom::Start(
+                (page as u64 + 1) * self.page_size - CHECKSUM_LEN as u64,
+            ))?;
+            file.write_all(&page_buf[page_buf.len() - CHECKSUM_LEN as usize..])?;
+        }
+        if let Some(pos) = self.pos {
+            self.inner.seek(SeekFrom::Start(pos))?;
+        }
+        Ok(())
+    }
+
+    /// The position, asking the file if unknown.
+    fn position(&mut self) -> io::Result<u64> {
+        match self.pos {
+            Some(pos) => Ok(pos),
+            None => {
+                let pos = self.inner.stream_position()?;
+                self.pos = Some(pos);
+                Ok(pos)
+            }
+        }
+    }
+
+    /// Save the original contents of the pages `len` bytes from the position cover,
+    /// unless already saved.
+    fn save_originals(&mut self, len: usize) -> io::Result<()> {
+        if self.undo.is_none() {
+            return Ok(());
+        }
+        let pos = self.position()?;
+        let undo = self.undo.as_mut().unwrap();
+        let first = pos / self.page_size;
+        let last = (pos + len as u64 - 1) / self.page_size;
+        for page in first..=last {
+            if undo.pages.contains_key(&(page as i32)) {
+                continue;
+            }
+            // pending writes only cover pages saved already
+            let mut image = Vec::with_capacity(self.page_size as usize);
+            undo.source.seek(SeekFrom::Start(page * self.page_size))?;
+            (&mut undo.source)
+                .take(self.page_size)
+                .read_to_end(&mut image)?;
+            undo.pages.insert(page as i32, image);
+        }
+        Ok(())
+    }
+
+    /// Pages written to since the last call,
+    /// or `None` if some write happened at an unknown position.
+    fn take_touched(&mut self) -> Option<HashSet<i32>> {
+        self.touched.replace(HashSet::new())
+    }
+
+    fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+}
+
+impl<S: Storage> Write for TrackedWriter<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.save_originals(buf.len())?;
+            if self.checksums {
+                self.position()?;
+            }
+        }
+        if self.wal.is_some() && !buf.is_empty() {
+            let pos = self.position()?;
+            // held pages are sealed as a whole when logged
+            self.wal.as_mut().unwrap().write_at(pos, buf)?;
+            if let Some(touched) = &mut self.touched {
+                let first = pos / self.page_size;
+                let last = (pos + buf.len() as u64 - 1) / self.page_size;
+                touched.extend((first..=last).map(|page| page as i32));
+            }
+            self.pos = Some(pos + buf.len() as u64);
+            return Ok(buf.len());
+        }
+        match self.inner.write(buf) {
+            Ok(n) => {
+                match (self.pos, &mut self.touched) {
+                    (Some(pos), Some(touched)) if n > 0 => {
+                        let first = pos / self.page_size;
+                        let last = (pos + n as u64 - 1) / self.page_size;
+                        touched.extend((first..=last).map(|page| page as i32));
+                    }
+                    (None, _) => self.touched = None,
+                    _ => {}
+                }
+                if let (true, Some(pos)) = (self.checksums && n > 0, self.pos) {
+                    let first = pos / self.page_size;
+                    let last = (pos + n as u64 - 1) / self.page_size;
+                    self.unsealed.extend((first..=last).map(|page| page as i32));
+                }
+                self.pos = self.pos.map(|pos| pos + n as u64);
+                Ok(n)
+            }
+            Err(e) => {
+                self.pos = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// With a write-ahead log, log the held pages as one unit
+    /// before writing them to the file.
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(wal) = &mut self.wal {
+            let pages = wal.commit(self.checksums)?;
+            if !pages.is_empty() {
+                for (page, frame) in pages {
+                    self.inner
+                        .seek(SeekFrom::Start(page as u64 * self.page_size))?;
+                    self.inner.write_all(&frame[..])?;
+                }
+                self.inner.flush()?;
+                self.inner.get_ref().sync_all()?;
+                wal.clear()?;
+            }
+        }
+        self.seal_written()?;
+        self.inner.flush()
+    }
+}
+
+impl<S: Storage> Seek for TrackedWriter<S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match (pos, self.pos) {
+            (SeekFrom::Start(target), Some(current)) if target == current => Ok(current),
+            (SeekFrom::Current(0), Some(current)) => Ok(current),
+            // the file is only written on flush, where it matters
+            (SeekFrom::Start(target), _) if self.wal.is_some() => {
+                self.pos = Some(target);
+                Ok(target)
+            }
+            _ => {
+                self.pos = None;
+                let pos = self.inner.seek(pos)?;
+                self.pos = Some(pos);
+                Ok(pos)
+            }
+        }
+    }
 }
 
+#[derive(Clone)]
 struct HeaderMeta {
     col_def_offset: i32,
     meta_offset: i32,
-    // relative to meta table
-    table_offsets: Vec<i32>,
+    // meta record number -> data page index
+    table_offsets: BTreeMap<u8, i32>,
     header_record_offset: u8,
+    // stored length, including the presence flag if any
     row_len: u16,
+    column_count: u8,
+    // byte ranges of blob pointers in a row
+    blob_columns: Vec<Range<usize>>,
+    delete_policy: DeletePolicy,
+    // page to start looking for spare pages from
+    alloc_hint: i32,
+    // data page -> free slots, lowest page first
+    free_slots: BTreeMap<i32, u32>,
+    // data pages with tombstoned slots, filled before the other free_slots pages
+    tombstone_pages: BTreeSet<i32>,
+    // set by analyze_table, not kept up to date
+    stats: Option<TableStats>,
+    // row lengths replaced by recompute_row_len or add_column since opening,
+    // without flags
+    previous_row_lens: Vec<u16>,
+    // column index and byte range in a row of the primary key
+    primary_key: Option<(u8, Range<usize>)>,
+    // column index and byte range in a row of the insertion sequence
+    sequence: Option<(u8, Range<usize>)>,
+    // last insertion sequence number handed out, 0 before the first insert
+    last_sequence: u64,
+    // up to date once refresh_indexes has run
+    indexes: Vec<KeyIndex>,
+    // page of the index ranges, 0 if the table has no indexes
+    index_page: i32,
+    // pages of the saved index entries
+    index_entry_pages: Vec<i32>,
+    // whether the entry pages hold the indexes as they are
+    indexes_saved: bool,
+}
+
+/// Data pages written by [`Database::repack_rows`], in meta record order.
+struct Repacked {
+    pages: Vec<i32>,
+    // empty slots of the last page
+    free: u32,
+    // pages the table no longer uses
+    left_over: Vec<i32>,
+}
+
+impl HeaderMeta {
+    /// Length of the rows callers give and get back.
+    fn data_len(&self) -> u16 {
+        self.row_len - flag_len(self.delete_policy)
+    }
+
+    /// The index page and index entry pages of the table.
+    fn index_pages(&self) -> impl Iterator<Item = i32> + '_ {
+        (self.index_page != 0)
+            .then_some(self.index_page)
+            .into_iter()
+            .chain(self.index_entry_pages.iter().copied())
+    }
+
+    /// The bytes to store for a row of [`HeaderMeta::data_len`] bytes.
+    fn stored_row<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        match self.delete_policy {
+            DeletePolicy::PresenceFlag => {
+                let mut row = data.to_vec();
+                row.push(1);
+                Cow::Owned(row)
+            }
+            _ => Cow::Borrowed(data),
+        }
+    }
 }
 
 impl Database {
     /// Open a database file.
-    pub fn open(path: impl AsRef<Path>) -> io::Result<Database> {
-        let mut reader = BufReader::new(File::open(&path)?);
-        let mut header_table = HashMap::new();
-        let mut name = [0; TABLE_NAME_MAX_LEN as usize];
-        let mut int32 = [0; 4];
-        let mut in_use_pages = HashSet::from([0]);
-        for header_record_offset in 0..HEADER_TABLE_RECORD_COUNT {
-            let mut table_name_len = [0];
-            reader.read_exact(&mut table_name_len)?;
-            let table_name_len = table_name_len[0];
-            if table_name_len == 0 {
-                reader.seek_relative(HEADER_TABLE_ROW_LEN as i64 - 1)?;
-            } else {
-                if table_name_len > TABLE_NAME_MAX_LEN {
-                    return Err(io::Error::new(
-                        ErrorKind::InvalidData,
-                        "table name overflow",
-                    ));
-                }
-                let table_name_len = table_name_len as usize;
-                reader.read_exact(&mut name[..table_name_len])?;
-                // implicit transform
-                let name = String::from_utf8_lossy(&name[0..table_name_len]);
-                reader.read_exact(&mut int32)?;
-                let col_def_offset = i32::from_be_bytes(int32);
-                reader.read_exact(&mut int32)?;
-                let meta_offset = i32::from_be_bytes(int32);
-                header_table.insert(
-                    name.into_owned(),
-                    HeaderMeta {
-                        col_def_offset,
-                        meta_offset,
-                        header_record_offset,
-                        table_offsets: Vec::new(),
-                        row_len: 0,
-                    },
-                );
-                in_use_pages.insert(col_def_offset);
-                in_use_pages.insert(meta_offset);
-            }
-        }
-        // now reader should be at 4096
-        for HeaderMeta {
-            meta_offset,
-            table_offsets,
-            col_def_offset,
-            row_len,
-            ..
-        } in header_table.values_mut()
-        {
-            let meta_offset = *meta_offset;
-            reader.seek(SeekFrom::Start(PAGE_SIZE as u64 * meta_offset as u64))?;
-            for _ in 0..META_TABLE_RECORD_COUNT {
-                let mut int32 = [0; 4];
-                reader.read_exact(&mut int32)?;
-                if int32 == [0; 4] {
-                    continue;
-                }
-                let table_offset = i32::from_be_bytes(int32);
-                let table_absolute_offset = table_offset + meta_offset;
-                in_use_pages.insert(table_absolute_offset);
-                table_offsets.push(table_offset);
-            }
+    pub fn open(path: impl AsRef<Path>) -> Result<Database, DatabaseError> {
+        Database::open_with_options(path, DatabaseOptions::default())
+    }
 
-            let col_def_offset = *col_def_offset;
-            reader.seek(SeekFrom::Start(PAGE_SIZE as u64 * col_def_offset as u64))?;
-            for _ in 0..DEF_TABLE_RECORD_COUNT {
-                let mut size = [0; 2];
-                reader.seek_relative(DEF_TABLE_ROW_LEN as i64 - 2)?;
-                reader.read_exact(&mut size)?;
-                let size = u16::from_be_bytes(size);
-                if size == 0 {
-                    break;
-                }
-                *row_len += size;
-            }
+    /// Open a database file with the given options.
+    pub fn open_with_options(
+        path: impl AsRef<Path>,
+        options: DatabaseOptions,
+    ) -> Result<Database, DatabaseError> {
+        Database::open_file(path.as_ref(), options, true)
+    }
+
+    /// Open a database file without write access, see [`ReadOnlyDatabase`].
+    ///
+    /// Fails if a write-ahead log is waiting to be replayed,
+    /// open the file writable once to replay it.
+    pub fn open_readonly(path: impl AsRef<Path>) -> Result<ReadOnlyDatabase, DatabaseError> {
+        Database::open_file(path.as_ref(), DatabaseOptions::default(), false)
+            .map(ReadOnlyDatabase::new)
+    }
+
+    fn open_file(
+        path: &Path,
+        options: DatabaseOptions,
+        writable: bool,
+    ) -> Result<Database, DatabaseError> {
+        let hold_writes = writable && options.durability == DurabilityMode::Manual;
+        if hold_writes && options.wal {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "the write-ahead log cannot hold writes until a sync",
+            )
+            .into());
+        }
+        let mut storage = FileStorage::open(path, writable)?;
+        // the layout record is never rewritten, so the log cannot hold it
+        let layout = read_layout(&mut BufReader::new(storage.try_clone()?))?;
+        if writable {
+            wal::recover(path, layout.page_size)?;
+        } else if wal::is_pending(path)? {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "write-ahead log not replayed, open the file writable first",
+            )
+            .into());
+        }
+        if hold_writes {
+            storage = storage.hold_writes()?;
+        }
+        let wal = options.wal;
+        let mut db = Database::from_storage(storage, path.to_path_buf(), options)?;
+        db.read_only = !writable;
+        if wal {
+            db.writer.wal = Some(Wal::open(path, layout.page_size)?);
+        }
+        Ok(db)
+    }
+
+    /// Open a database file and run [`Database::check_integrity`] on it.
+    ///
+    /// Fails with the issues found, if any.
+    pub fn open_verified(path: impl AsRef<Path>) -> Result<Database, DatabaseError> {
+        let mut db = Database::open(path)?;
+        let issues = db.check_integrity()?;
+        if issues.is_empty() {
+            Ok(db)
+        } else {
+            Err(io::Error::new(ErrorKind::InvalidData, issues.join("; ")).into())
         }
+    }
 
-        let writer = BufWriter::new(File::options().write(true).open(path)?);
-        Ok(Database {
-            reader,
-            writer,
-            header_table,
-            in_use_pages,
-        })
+    /// Open a database file, creating it with the default options first
+    /// if there is no file at `path`.
+    ///
+    /// A file that is there but not a valid database is an error,
+    /// it is never overwritten.
+    pub fn open_or_create(path: impl AsRef<Path>) -> Result<Database, DatabaseError> {
+        match File::open(&path) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => Database::create_database(&path)?,
+            Err(e) => return Err(e.into()),
+        }
+        Database::open(path)
     }
 
     /// This function will create a file if it does not exist,
@@ -135,10 +812,144 @@ impl Database {
     /// Depending on the platform,
     /// this function may fail
     /// if the full directory path does not exist.
-    pub fn create_database(path: impl AsRef<Path>) -> io::Result<()> {
-        let file = File::create(path)?;
-        file.set_len(PAGE_SIZE as u64)?;
-        file.sync_all()
+    pub fn create_database(path: impl AsRef<Path>) -> Result<(), DatabaseError> {
+        Database::create_database_with_options(path, CreateOptions::default())
+    }
+
+    /// Like [`Database::create_database`],
+    /// with header and def records of `record_len` bytes instead of 32.
+    ///
+    /// `record_len` may be 32, 64 or 128.
+    /// Longer records allow longer table and column names,
+    /// at the cost of fewer tables and columns per table.
+    pub fn create_database_with_record_len(
+        path: impl AsRef<Path>,
+        record_len: u8,
+    ) -> Result<(), DatabaseError> {
+        Database::create_database_with_options(
+            path,
+            CreateOptions {
+                record_len,
+                ..CreateOptions::default()
+            },
+        )
+    }
+
+    /// Like [`Database::create_database`],
+    /// with pages of `page_size` bytes instead of 4 KiB.
+    ///
+    /// `page_size` must be a power of two up to 64 KiB,
+    /// large enough for two header records.
+    pub fn create_database_with_page_size(
+        path: impl AsRef<Path>,
+        page_size: u32,
+    ) -> Result<(), DatabaseError> {
+        Database::create_database_with_options(
+            path,
+            CreateOptions {
+                page_size,
+                ..CreateOptions::default()
+            },
+        )
+    }
+
+    /// Like [`Database::create_database`],
+    /// with the format choices of `options`.
+    pub fn create_database_with_options(
+        path: impl AsRef<Path>,
+        options: CreateOptions,
+    ) -> Result<(), DatabaseError> {
+        let page = first_page(options)?;
+        // a log left by an old file must not be replayed onto the new one
+        wal::remove(path.as_ref())?;
+        let mut file = File::create(path)?;
+        file.write_all(&page)?;
+        Ok(file.sync_all()?)
+    }
+}
+
+impl Database<MemoryStorage> {
+    /// Create a database in memory, with the default options,
+    /// gone once it is dropped.
+    ///
+    /// Blob columns need a file next to the database file,
+    /// so they cannot be used.
+    ///
+    /// ```
+    /// use rustub_storage::{
+    ///     table::{be_key_u32, ColumnDef, ColumnType, Condition},
+    ///     Database,
+    /// };
+    ///
+    /// let mut db = Database::open_in_memory()?;
+    /// let defs = [ColumnDef { name: "id", column_type: ColumnType::UInt, size: 4 }];
+    /// db.create_table("users", &defs)?;
+    /// db.insert("users", &be_key_u32(7))?;
+    ///
+    /// let rows = db.select("users", &[Condition::eq(0..4, be_key_u32(7))])?;
+    /// assert_eq!(rows, [be_key_u32(7)]);
+    /// # Ok::<(), rustub_storage::table::DatabaseError>(())
+    /// ```
+    pub fn open_in_memory() -> Result<Self, DatabaseError> {
+        Database::open_in_memory_with_options(CreateOptions::default(), DatabaseOptions::default())
+    }
+
+    /// Like [`Database::open_in_memory`],
+    /// with the format choices of `create` and the options of `options`.
+    ///
+    /// There is no write-ahead log in memory, `options.wal` must not be set.
+    pub fn open_in_memory_with_options(
+        create: CreateOptions,
+        options: DatabaseOptions,
+    ) -> Result<Self, DatabaseError> {
+        if options.wal {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "no write-ahead log for in-memory databases",
+            )
+            .into());
+        }
+        let mut storage = MemoryStorage::new();
+        storage.write_all(&first_page(create)?)?;
+        Database::from_storage(storage, PathBuf::new(), options)
+    }
+}
+
+impl<S: Storage> Database<S> {
+    /// Open a database in `storage`,
+    /// whose blob file and log are named after `path`.
+    fn from_storage(
+        storage: S,
+        path: PathBuf,
+        options: DatabaseOptions,
+    ) -> Result<Database<S>, DatabaseError> {
+        let mut reader = BufReader::new(storage.try_clone()?);
+        reader.seek(SeekFrom::Start(0))?;
+        let layout = read_layout(&mut reader)?;
+        let (header_table, in_use_pages) = load_metadata(&mut reader, layout)?;
+        let file_len = reader.get_ref().len()?;
+        let free_pages = spare_pages(&in_use_pages, file_len, layout);
+        let stale_index_pages = index::stale_after_load(&header_table);
+
+        let mut writer = TrackedWriter::new(storage, layout.page_size);
+        writer.checksums = layout.checksums;
+        Ok(Database {
+            reader,
+            writer,
+            header_table,
+            in_use_pages,
+            free_pages,
+            file_len,
+            stale_index_pages,
+            poisoned: false,
+            read_only: false,
+            layout,
+            path,
+            blobs: None,
+            cache: PageCache::new(options.cache_pages),
+            options,
+            transaction: None,
+        })
     }
 
     /// This function will create a table in the database.
@@ -156,96 +967,182 @@ impl Database {
         &mut self,
         table_name: &str,
         table_def: &[ColumnDef<impl AsRef<str>>],
-    ) -> io::Result<()> {
+    ) -> Result<(), DatabaseError> {
+        self.create_table_with_options(table_name, table_def, &TableOptions::default())
+    }
+
+    /// Like [`Database::create_table`], but in the given header slot.
+    ///
+    /// Creating the same tables at the same slots
+    /// gives the same header page whatever was created or dropped before.
+    pub fn create_table_at(
+        &mut self,
+        table_name: &str,
+        table_def: &[ColumnDef<impl AsRef<str>>],
+        header_slot: u8,
+    ) -> Result<(), DatabaseError> {
+        let options = TableOptions {
+            header_slot: Some(header_slot),
+            ..TableOptions::default()
+        };
+        self.create_table_with_options(table_name, table_def, &options)
+    }
+
+    /// Like [`Database::create_table`], with non-default table options.
+    pub fn create_table_with_options(
+        &mut self,
+        table_name: &str,
+        table_def: &[ColumnDef<impl AsRef<str>>],
+        options: &TableOptions,
+    ) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let layout = self.layout;
         let name_len = table_name.len();
-        if name_len > 23 || name_len == 0 {
-            return Err(io::Error::new(
-                ErrorKind::Other,
-                CreateTableError::TableNameInvalid,
-            ));
+        if name_len > layout.table_name_max_len() as usize || name_len == 0 {
+            return Err(CreateTableError::TableNameInvalid.into());
         }
-        if table_def.len() > DEF_TABLE_RECORD_COUNT as usize {
+        if table_def.len() > layout.table_option_record() as usize {
+            return Err(CreateTableError::TooManyColumns.into());
+        }
+        if options.contiguous_pages > self.layout.meta_record_count() as u16 {
             return Err(io::Error::new(
-                ErrorKind::Other,
-                CreateTableError::TooManyColumns,
-            ));
+                ErrorKind::InvalidInput,
+                "more contiguous pages than meta records",
+            )
+            .into());
         }
         if self.header_table.contains_key(table_name) {
-            return Err(io::Error::new(
-                ErrorKind::Other,
-                CreateTableError::TableExists,
-            ));
+            return Err(CreateTableError::TableExists.into());
         }
         for def in table_def {
-            if def.name.as_ref().len() > COLUMN_NAME_MAX_LEN as usize {
-                return Err(io::Error::new(
-                    ErrorKind::Other,
-                    CreateTableError::ColumnNameTooLong,
-                ));
-            }
+            check_column_def(layout, def)?;
         }
-        let row_size: u16 = table_def.iter().map(|d| d.size).sum();
-        if row_size as u32 > PAGE_SIZE {
-            return Err(io::Error::new(
-                ErrorKind::Other,
-                CreateTableError::ColumnTooBig,
-            ));
+        let row_size: u16 =
+            table_def.iter().map(|d| d.size).sum::<u16>() + flag_len(options.delete_policy);
+        if row_size as u32 > self.layout.page_body() {
+            return Err(CreateTableError::ColumnTooBig.into());
         }
+        let primary_key = match options.primary_key {
+            Some(index) => match table_def.get(index as usize) {
+                Some(def) if def.column_type == ColumnType::Blob => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidInput,
+                        "blob columns cannot be the primary key",
+                    )
+                    .into())
+                }
+                Some(_) => Some((index, column_range(table_def, index as usize))),
+                None => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidInput,
+                        "primary key column out of range",
+                    )
+                    .into())
+                }
+            },
+            None => None,
+        };
+        let sequence = match options.sequence_column {
+            Some(index) => match table_def.get(index as usize) {
+                Some(def) if def.column_type == ColumnType::UInt && def.size == 8 => {
+                    Some((index, column_range(table_def, index as usize)))
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidInput,
+                        "the sequence column must be an 8 byte uint column",
+                    )
+                    .into())
+                }
+            },
+            None => None,
+        };
 
-        let name_len = name_len as u8;
-        let reader = &mut self.reader;
+        let slots = match options.header_slot {
+            Some(slot) if (layout.first_table_record()..layout.record_count()).contains(&slot) => {
+                slot..slot + 1
+            }
+            Some(_) => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "header slot out of the header table",
+                )
+                .into())
+            }
+            None => layout.first_table_record()..layout.record_count(),
+        };
+
+        let mut header_page = vec![0; layout.page_size as usize];
+        self.read_page(0, &mut header_page)?;
 
-        set_reader_to_head(reader)?;
-        for header_record_offset in 0..HEADER_TABLE_RECORD_COUNT {
-            let mut len = [0];
-            reader.read_exact(&mut len)?;
-            let len = len[0];
+        for header_record_offset in slots {
+            let len =
+                header_page[(header_record_offset as u32 * layout.record_len as u32) as usize];
             if len == 0 {
-                let writer = &mut self.writer;
-                writer.seek(SeekFrom::Start(
-                    header_record_offset as u64 * HEADER_TABLE_ROW_LEN as u64,
-                ))?;
-                writer.write_all(&[name_len])?;
-                writer.write_all(table_name.as_bytes())?;
                 // TODO: full scan
-                let def_offset_page = find_spare_page(&self.in_use_pages)?;
-                // def table offset
-                writer.write_all(&def_offset_page.to_be_bytes())?;
-                let mut meta_offset_page = def_offset_page;
-                while meta_offset_page < i32::MAX {
-                    if !self.in_use_pages.contains(&meta_offset_page) {
-                        break;
+                let max_pages = self.options.max_pages;
+                let contiguous_pages = options.contiguous_pages as i32;
+                let run = if contiguous_pages > 0 {
+                    find_spare_run(&self.in_use_pages, contiguous_pages + 2, max_pages)
+                } else {
+                    None
+                };
+                let (def_offset_page, meta_offset_page, data_pages, alloc_hint) = match run {
+                    Some(start) => {
+                        let end = start + 2 + contiguous_pages;
+                        self.grow_for(end - 1)?;
+                        self.in_use_pages.extend(start..end);
+                        (start, start + 1, start + 2..end, start)
                     }
-                    meta_offset_page += 1;
-                }
-                if meta_offset_page == i32::MAX {
-                    return Err(io::Error::new(
-                        ErrorKind::Other,
-                        CreateTableError::StorageFull,
-                    ));
-                }
-                // meta table offset
-                writer.write_all(&meta_offset_page.to_be_bytes())?;
-                writer.seek(SeekFrom::Start(def_offset_page as u64 * PAGE_SIZE as u64))?;
-                self.in_use_pages
-                    .extend([meta_offset_page, def_offset_page]);
+                    // fall back to page by page allocation
+                    None => {
+                        let def_offset_page = self.alloc_page(0)?;
+                        let meta_offset_page = self
+                            .alloc_page(0)
+                            .inspect_err(|_| self.free_page(def_offset_page))?;
+                        (def_offset_page, meta_offset_page, 0..0, 0)
+                    }
+                };
+                let table_offsets: BTreeMap<u8, i32> = (0..).zip(data_pages.clone()).collect();
 
-                let rest_len = PAGE_SIZE as usize - table_def.len() * DEF_TABLE_ROW_LEN as usize;
-                for def in table_def {
-                    let name = def.name.as_ref();
-                    let len = name.len();
+                self.poisoned = true;
+                let record = header_record(layout, table_name, def_offset_page, meta_offset_page);
+                self.write_at(layout.header_pos(header_record_offset), &record)?;
 
-                    writer.write_all(&[len as u8])?;
-                    writer.write_all(name.as_bytes())?;
-                    writer.write_all(&[def.column_type])?;
-                    writer.write_all(&def.size.to_be_bytes())?;
+                let mut def_page = vec![0; layout.page_size as usize];
+                for (def, record) in table_def
+                    .iter()
+                    .zip(def_page.chunks_exact_mut(layout.record_len as usize))
+                {
+                    record.copy_from_slice(&def_record(layout, def));
                 }
-                writer.write_all(&vec![0; rest_len])?;
+                let option_pos = layout.table_option_record() as usize * layout.record_len as usize;
+                let option_record =
+                    &mut def_page[option_pos..option_pos + layout.record_len as usize];
+                option_record[1] = options.delete_policy as u8;
+                option_record[2..6].copy_from_slice(&alloc_hint.to_be_bytes());
+                // zero without a primary key
+                option_record[6] = options.primary_key.map_or(0, |index| index + 1);
+                // zero without a sequence column, the counter starts at zero
+                option_record[11] = options.sequence_column.map_or(0, |index| index + 1);
+                self.write_page(def_offset_page, &def_page)?;
 
-                writer.seek(SeekFrom::Start(meta_offset_page as u64 * PAGE_SIZE as u64))?;
-                writer.write_all(&[0; PAGE_SIZE as usize])?;
+                let mut meta_page = vec![0; self.layout.page_size as usize];
+                for (record_number, table_offset) in &table_offsets {
+                    let pos = *record_number as usize * META_TABLE_ROW_LEN as usize;
+                    meta_page[pos..pos + 4].copy_from_slice(&table_offset.to_be_bytes());
+                }
+                self.write_page(meta_offset_page, &meta_page)?;
+                let zeros = vec![0; self.layout.page_size as usize];
+                for page in data_pages {
+                    self.write_page(page, &zeros)?;
+                }
 
-                writer.flush()?;
+                self.flush()?;
+                let free_slots = table_offsets
+                    .values()
+                    .map(|&page| (page, self.layout.rows_per_page(row_size)))
+                    .collect();
                 // add to header metadata
                 self.header_table.insert(
                     table_name.to_string(),
@@ -253,336 +1150,3491 @@ impl Database {
                         col_def_offset: def_offset_page,
                         meta_offset: meta_offset_page,
                         header_record_offset,
-                        table_offsets: Vec::new(),
+                        table_offsets,
                         row_len: row_size,
+                        column_count: table_def.len() as u8,
+                        blob_columns: blob_columns(table_def),
+                        delete_policy: options.delete_policy,
+                        alloc_hint,
+                        free_slots,
+                        tombstone_pages: BTreeSet::new(),
+                        stats: None,
+                        previous_row_lens: Vec::new(),
+                        primary_key,
+                        sequence,
+                        last_sequence: 0,
+                        indexes: Vec::new(),
+                        index_page: 0,
+                        index_entry_pages: Vec::new(),
+                        indexes_saved: false,
                     },
                 );
+                self.poisoned = false;
                 return Ok(());
             }
         }
 
-        // header table full
-        Err(io::Error::new(
-            ErrorKind::Other,
-            CreateTableError::HeaderTableFull,
-        ))
+        if options.header_slot.is_some() {
+            return Err(CreateTableError::HeaderSlotTaken.into());
+        }
+        // header table full
+        Err(CreateTableError::HeaderTableFull.into())
+    }
+
+    pub fn drop(&mut self, table_name: &str) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        if let Some(meta) = self.header_table.remove(table_name) {
+            self.poisoned = true;
+            self.write_at(
+                self.layout.header_pos(meta.header_record_offset),
+                &vec![0; self.layout.record_len as usize],
+            )?;
+            // clear the pages so that whoever reuses them starts from zeros
+            let pages: Vec<i32> = [meta.col_def_offset, meta.meta_offset]
+                .into_iter()
+                .chain(meta.table_offsets.values().copied())
+                .chain(meta.index_pages())
+                .collect();
+            let zeros = vec![0; self.layout.page_size as usize];
+            for &page in &pages {
+                self.write_page(page, &zeros)?;
+            }
+            self.flush()?;
+            for page in pages {
+                self.free_page(page);
+            }
+            self.poisoned = false;
+            Ok(())
+        } else {
+            Err(DatabaseError::TableNotFound)
+        }
+    }
+
+    /// Remove every row of a table, keeping its columns and options.
+    ///
+    /// The data pages are cleared and freed, and the meta page is cleared.
+    pub fn truncate(&mut self, table_name: &str) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let (meta_offset, has_blobs) = (meta.meta_offset, !meta.blob_columns.is_empty());
+
+        let zeros = vec![0; self.layout.page_size as usize];
+        self.poisoned = true;
+        for &page in pages.iter().chain([&meta_offset]) {
+            self.write_page(page, &zeros)?;
+        }
+        self.flush()?;
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.table_offsets.clear();
+            meta.free_slots.clear();
+            meta.tombstone_pages.clear();
+            meta.stats = None;
+        }
+        for page in pages {
+            self.free_page(page);
+        }
+        // an unopened blob file finds its free space when opened
+        if has_blobs {
+            self.blobs = None;
+        }
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Append a column to a table,
+    /// holding the [`default_bytes`] of its type in every existing row.
+    ///
+    /// Rows grow, so every live row is read and the rows are packed again
+    /// from the first data page on,
+    /// which takes time proportional to the number of rows.
+    /// Data pages are allocated as needed, and those left over are freed.
+    pub fn add_column(
+        &mut self,
+        table_name: &str,
+        def: ColumnDef<impl AsRef<str>>,
+    ) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let layout = self.layout;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        if meta.column_count >= layout.table_option_record() {
+            return Err(CreateTableError::TooManyColumns.into());
+        }
+        check_column_def(layout, &def)?;
+        let row_len = match meta.row_len.checked_add(def.size) {
+            Some(row_len) if row_len as u32 <= layout.page_body() => row_len,
+            _ => return Err(CreateTableError::ColumnTooBig.into()),
+        };
+        let (col_def_offset, column_count, data_len) =
+            (meta.col_def_offset, meta.column_count, meta.data_len());
+
+        let default = default_bytes(def.column_type, def.size);
+        let repacked = self.repack_rows(table_name, row_len, |row| [row, &default].concat())?;
+        self.write_at(
+            layout.def_pos(col_def_offset, column_count),
+            &def_record(layout, &def),
+        )?;
+        self.flush()?;
+
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.previous_row_lens.push(data_len);
+            meta.row_len = row_len;
+            meta.column_count += 1;
+            if def.column_type == ColumnType::Blob {
+                let start = data_len as usize;
+                meta.blob_columns.push(start..start + def.size as usize);
+            }
+        }
+        self.finish_repack(table_name, repacked);
+        Ok(())
+    }
+
+    /// Remove a column from a table, with its bytes in every row.
+    ///
+    /// Later columns move up, so condition ranges past the column change.
+    /// Like [`Database::add_column`], every live row is packed again.
+    /// Under the zero fill policy, rows left holding only zeros are lost.
+    pub fn drop_column(
+        &mut self,
+        table_name: &str,
+        column_name: &str,
+    ) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let layout = self.layout;
+        let defs = self.get_table_def(table_name)?;
+        let Some(index) = defs.iter().position(|def| def.name == column_name) else {
+            return Err(io::Error::new(ErrorKind::NotFound, "no such column").into());
+        };
+        if defs.len() == 1 {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "cannot drop the only column").into(),
+            );
+        }
+        let range = column_range(&defs, index);
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let primary_key = match meta.primary_key.clone() {
+            Some((key, _)) if key as usize == index => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "cannot drop the primary key column",
+                )
+                .into());
+            }
+            Some((key, key_range)) if key as usize > index => Some((
+                key - 1,
+                key_range.start - range.len()..key_range.end - range.len(),
+            )),
+            primary_key => primary_key,
+        };
+        let sequence = match meta.sequence.clone() {
+            Some((column, _)) if column as usize == index => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "cannot drop the sequence column",
+                )
+                .into());
+            }
+            Some((column, column_range)) if column as usize > index => Some((
+                column - 1,
+                column_range.start - range.len()..column_range.end - range.len(),
+            )),
+            sequence => sequence,
+        };
+        let (col_def_offset, data_len) = (meta.col_def_offset, meta.data_len());
+        let row_len = meta.row_len - defs[index].size;
+        let dropped_blobs = defs[index].column_type == ColumnType::Blob && self.blobs.is_some();
+
+        let mut freed_blobs = Vec::new();
+        let repacked = self.repack_rows(table_name, row_len, |row| {
+            if dropped_blobs {
+                freed_blobs.push(row[range.clone()].to_vec());
+            }
+            [&row[..range.start], &row[range.end..]].concat()
+        })?;
+        let mut def_page = vec![0; layout.page_size as usize];
+        self.read_page(col_def_offset, &mut def_page)?;
+        // later defs move up one record, the last one is cleared
+        let record_len = layout.record_len as usize;
+        let defs_end = defs.len() * record_len;
+        def_page.copy_within((index + 1) * record_len..defs_end, index * record_len);
+        def_page[defs_end - record_len..defs_end].fill(0);
+        let option_pos = layout.table_option_record() as usize * record_len;
+        def_page[option_pos + 6] = primary_key.as_ref().map_or(0, |(key, _)| key + 1);
+        def_page[option_pos + 11] = sequence.as_ref().map_or(0, |(column, _)| column + 1);
+        self.write_page(col_def_offset, &def_page)?;
+        self.flush()?;
+
+        let mut defs = defs;
+        defs.remove(index);
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.previous_row_lens.push(data_len);
+            meta.row_len = row_len;
+            meta.column_count -= 1;
+            meta.blob_columns = blob_columns(&defs);
+            meta.primary_key = primary_key;
+            meta.sequence = sequence;
+            // indexes on the column go, those after it move up
+            meta.indexes
+                .retain(|index| index.range.end <= range.start || index.range.start >= range.end);
+            for index in &mut meta.indexes {
+                if index.range.start >= range.end {
+                    index.range = index.range.start - range.len()..index.range.end - range.len();
+                }
+            }
+        }
+        self.finish_repack(table_name, repacked);
+        if let Some(blobs) = &mut self.blobs {
+            for pointer in freed_blobs {
+                blobs.free(&pointer);
+            }
+        }
+        if self.header_table[table_name].index_page != 0 {
+            self.write_index_page(table_name)?;
+        }
+        Ok(())
+    }
+
+    /// The column def records of a table as stored on disk,
+    /// each padded to the record length,
+    /// up to the first empty record.
+    pub fn raw_column_defs(&mut self, table_name: &str) -> Result<Vec<u8>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let mut page = vec![0; self.layout.page_size as usize];
+        self.read_page(meta.col_def_offset, &mut page)?;
+
+        let record_len = self.layout.record_len as usize;
+        let count = (0..self.layout.table_option_record() as usize)
+            .take_while(|record| page[record * record_len] != 0)
+            .count();
+        page.truncate(count * record_len);
+        Ok(page)
+    }
+
+    /// Drop a table, then cut the file after the last page still in use.
+    ///
+    /// The file only shrinks if the dropped pages were at its tail,
+    /// pages freed elsewhere are just marked free like [`Database::drop`] does.
+    pub fn drop_table_and_shrink(&mut self, table_name: &str) -> Result<(), DatabaseError> {
+        self.drop(table_name)?;
+        Ok(self.shrink_tail()?)
+    }
+
+    /// Delete matching rows, free the data pages left without rows,
+    /// then cut the file after the last page still in use.
+    ///
+    /// Unlike [`Database::delete`],
+    /// emptied pages are freed under the tombstone policy too.
+    /// Returns the number of rows deleted.
+    pub fn purge<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+    ) -> Result<usize, DatabaseError> {
+        let res = self.delete(table_name, conditions)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        if meta.delete_policy == DeletePolicy::Tombstone {
+            let empty_pages: Vec<i32> = self
+                .page_fill(table_name)?
+                .into_iter()
+                .filter(|(_, count)| *count == 0)
+                .map(|(page, _)| page)
+                .collect();
+            let meta = check_table_exists(&self.header_table, table_name)?;
+            let freed_records: Vec<u8> = meta
+                .table_offsets
+                .iter()
+                .filter(|(_, page)| empty_pages.contains(page))
+                .map(|(record_number, _)| *record_number)
+                .collect();
+
+            let meta_pos = self.layout.page_pos(meta.meta_offset);
+            self.poisoned = true;
+            for record_number in &freed_records {
+                self.write_at(
+                    meta_pos + *record_number as u64 * META_TABLE_ROW_LEN as u64,
+                    &[0; META_TABLE_ROW_LEN as usize],
+                )?;
+            }
+            self.flush()?;
+            for page in &empty_pages {
+                self.free_page(*page);
+            }
+            if let Some(meta) = self.header_table.get_mut(table_name) {
+                for record_number in freed_records {
+                    if let Some(page) = meta.table_offsets.remove(&record_number) {
+                        meta.free_slots.remove(&page);
+                        meta.tombstone_pages.remove(&page);
+                    }
+                }
+            }
+            self.poisoned = false;
+        }
+        self.shrink_tail()?;
+        Ok(res)
+    }
+
+    /// Scan a table and keep per-column statistics for
+    /// [`Database::estimate_matches`].
+    ///
+    /// The statistics are not updated by later changes to the table.
+    pub fn analyze_table(&mut self, table_name: &str) -> Result<TableStats, DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let mut rows = 0;
+        let mut columns: Vec<(Range<usize>, HashSet<Vec<u8>>)> = Vec::with_capacity(defs.len());
+        let mut start = 0;
+        for def in &defs {
+            columns.push((start..start + def.size as usize, HashSet::new()));
+            start += def.size as usize;
+        }
+        if start > meta.data_len() as usize {
+            return Err(
+                io::Error::new(ErrorKind::InvalidData, "column sizes exceed row length").into(),
+            );
+        }
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut page_buf)?;
+            for buf in page_buf[..rows_len].chunks_exact(row_len.max(1)) {
+                if is_empty_row(buf, delete_policy) {
+                    continue;
+                }
+                rows += 1;
+                for (range, values) in &mut columns {
+                    values.insert(buf[range.clone()].to_vec());
+                }
+            }
+        }
+
+        let stats = TableStats {
+            rows,
+            columns: columns
+                .into_iter()
+                .map(|(_, values)| ColumnStats {
+                    min: values.iter().min().cloned().unwrap_or_default(),
+                    max: values.iter().max().cloned().unwrap_or_default(),
+                    distinct: values.len(),
+                })
+                .collect(),
+        };
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.stats = Some(stats.clone());
+        }
+        Ok(stats)
+    }
+
+    /// Estimate how many rows match all conditions,
+    /// from the statistics of the last [`Database::analyze_table`].
+    ///
+    /// This is only an estimate:
+    /// values are assumed evenly spread between min and max,
+    /// conditions are assumed independent,
+    /// and a condition not covering exactly one column counts as matching a third of the rows.
+    pub fn estimate_matches<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+    ) -> Result<usize, DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let Some(stats) = &meta.stats else {
+            return Err(io::Error::other("table not analyzed").into());
+        };
+
+        let mut selectivity = 1.0;
+        for c in conditions {
+            let mut start = 0;
+            let column = defs.iter().zip(&stats.columns).find_map(|(def, column)| {
+                let range = start..start + def.size as usize;
+                start = range.end;
+                (range == c.range).then_some(column)
+            });
+            selectivity *= match column {
+                None => 1.0 / 3.0,
+                Some(column) if column.distinct == 0 => 0.0,
+                Some(column) => {
+                    let min = key_position(&column.min);
+                    let max = key_position(&column.max);
+                    let data = key_position(c.data.as_ref());
+                    // share of the rows below data
+                    let below = if max > min {
+                        ((data - min) / (max - min)).clamp(0.0, 1.0)
+                    } else if data > min {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                    match c.ord {
+                        Ordering::Equal => 1.0 / column.distinct as f64,
+                        Ordering::Less => below,
+                        Ordering::Greater => 1.0 - below,
+                    }
+                }
+            };
+        }
+
+        Ok((stats.rows as f64 * selectivity).round() as usize)
+    }
+
+    /// The columns of a table, in def order.
+    ///
+    /// Reading stops at the first empty def record.
+    pub fn get_table_def(
+        &mut self,
+        table_name: &str,
+    ) -> Result<Vec<ColumnDef<String>>, DatabaseError> {
+        self.check_poisoned()?;
+        if let Some(meta) = self.header_table.get(table_name) {
+            Ok(self.column_defs(meta.col_def_offset)?)
+        } else {
+            Err(DatabaseError::TableNotFound)
+        }
+    }
+
+    /// Render the schema of a table as text.
+    ///
+    /// Each column is printed as `name: type(size)` on its own line,
+    /// followed by the row length and the number of data pages.
+    pub fn describe(&mut self, table_name: &str) -> Result<String, DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let mut res = String::new();
+        for def in &defs {
+            res.push_str(&format!(
+                "{}: {}({})\n",
+                def.name, def.column_type, def.size
+            ));
+        }
+        if let Some((index, _)) = meta.primary_key {
+            res.push_str(&format!("primary key: {}\n", defs[index as usize].name));
+        }
+        if let Some((index, _)) = meta.sequence {
+            res.push_str(&format!("sequence: {}\n", defs[index as usize].name));
+        }
+        res.push_str(&format!("row_len: {}\n", meta.data_len()));
+        res.push_str(&format!("pages: {}\n", meta.table_offsets.len()));
+        Ok(res)
+    }
+
+    /// Check that a row fits the schema of a table.
+    ///
+    /// Besides the row length,
+    /// boolean columns must hold 0 or 1,
+    /// and text columns must be valid UTF-8 up to the first zero byte.
+    /// Other column types accept any bytes.
+    pub fn validate_row(&mut self, table_name: &str, data: &[u8]) -> Result<(), DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_row_len(meta, data.len())?;
+
+        let mut start = 0;
+        for def in defs {
+            let Some(value) = data.get(start..start + def.size as usize) else {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "column sizes exceed row length",
+                )
+                .into());
+            };
+            start += def.size as usize;
+            let valid = match def.column_type {
+                ColumnType::Bool => value
+                    .split_last()
+                    .is_none_or(|(last, rest)| *last <= 1 && rest.iter().all(|b| *b == 0)),
+                ColumnType::Text => {
+                    let end = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+                    std::str::from_utf8(&value[..end]).is_ok()
+                }
+                _ => true,
+            };
+            if !valid {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid value for column {}", def.name),
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that conditions fit the schema of a table.
+    ///
+    /// Every range must lie within the row and match the length of its value,
+    /// and the column a range starts in decides which orderings make sense:
+    /// bytes and bool columns only accept equality,
+    /// numeric and text columns accept any ordering.
+    pub fn validate_conditions<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+    ) -> Result<(), DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        for c in conditions {
+            if c.range.start >= c.range.end || c.range.end > meta.data_len() as usize {
+                return Err(
+                    io::Error::new(ErrorKind::InvalidInput, "condition range out of row").into(),
+                );
+            }
+            let mut start = 0;
+            let Some(def) = defs.iter().find(|def| {
+                start += def.size as usize;
+                c.range.start < start
+            }) else {
+                return Err(
+                    io::Error::new(ErrorKind::InvalidInput, "condition range out of row").into(),
+                );
+            };
+            let ordered = matches!(
+                def.column_type,
+                ColumnType::Int | ColumnType::UInt | ColumnType::Text
+            );
+            if !ordered && c.ord != Ordering::Equal {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("column {} only accepts equality", def.name),
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Data can contain only one row.
+    pub fn insert(&mut self, table_name: &str, data: &[u8]) -> Result<(), DatabaseError> {
+        self.insert_report(table_name, data).map(|_| ())
+    }
+
+    /// Like [`Database::insert`],
+    /// but tells where the row went and whether a data page was allocated for it.
+    pub fn insert_report(
+        &mut self,
+        table_name: &str,
+        data: &[u8],
+    ) -> Result<InsertReport, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_row_len(meta, data.len())?;
+        self.check_unique_keys(table_name, data)?;
+        let data = self.number_rows(table_name, data)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let stored = meta.stored_row(&data);
+        let data: &[u8] = &stored;
+        if let Some(row_id) = self.insert_into_free_slot(table_name, data)? {
+            return Ok(InsertReport {
+                row_id,
+                allocated_new_page: false,
+            });
+        }
+
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (meta_offset, delete_policy, alloc_hint) =
+            (meta.meta_offset, meta.delete_policy, meta.alloc_hint);
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let row_len = data.len().max(1);
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+
+        // tombstoned slots are reused before empty ones
+        if delete_policy == DeletePolicy::Tombstone {
+            for page in pages {
+                self.read_page(page, &mut page_buf)?;
+                let Some(slot) = page_buf[..rows_len]
+                    .chunks_exact(row_len)
+                    .position(|slot| slot.iter().all(|b| *b == TOMBSTONE))
+                else {
+                    continue;
+                };
+                self.poisoned = true;
+                page_buf[slot * row_len..][..data.len()].copy_from_slice(data);
+                self.write_page(page, &page_buf)?;
+                self.flush()?;
+                self.poisoned = false;
+                return Ok(InsertReport {
+                    row_id: RowId {
+                        page,
+                        slot: slot as u32,
+                    },
+                    allocated_new_page: false,
+                });
+            }
+        }
+
+        let mut meta_page = vec![0; self.layout.page_size as usize];
+        self.read_page(meta_offset, &mut meta_page)?;
+        for record_offset in 0..self.layout.meta_record_count() {
+            let pos = record_offset as usize * META_TABLE_ROW_LEN as usize;
+            let table_offset = i32::from_be_bytes(meta_page[pos..pos + 4].try_into().unwrap());
+            if table_offset == 0 {
+                let new_table = self.alloc_page(alloc_hint)?;
+                self.poisoned = true;
+                meta_page[pos..pos + 4].copy_from_slice(&new_table.to_be_bytes());
+                self.write_page(meta_offset, &meta_page)?;
+                page_buf.fill(0);
+                page_buf[..data.len()].copy_from_slice(data);
+                self.write_page(new_table, &page_buf)?;
+                self.flush()?;
+                if let Some(meta) = self.header_table.get_mut(table_name) {
+                    meta.table_offsets.insert(record_offset, new_table);
+                    let free = self.layout.rows_per_page(meta.row_len).saturating_sub(1);
+                    if free > 0 {
+                        meta.free_slots.insert(new_table, free);
+                    }
+                }
+                self.poisoned = false;
+                return Ok(InsertReport {
+                    row_id: RowId {
+                        page: new_table,
+                        slot: 0,
+                    },
+                    allocated_new_page: true,
+                });
+            }
+
+            self.read_page(table_offset, &mut page_buf)?;
+            let Some(slot) = page_buf[..rows_len]
+                .chunks_exact(row_len)
+                .position(|slot| slot.iter().all(|b| *b == 0))
+            else {
+                continue;
+            };
+            self.poisoned = true;
+            page_buf[slot * row_len..][..data.len()].copy_from_slice(data);
+            self.write_page(table_offset, &page_buf)?;
+            self.flush()?;
+            self.poisoned = false;
+            return Ok(InsertReport {
+                row_id: RowId {
+                    page: table_offset,
+                    slot: slot as u32,
+                },
+                allocated_new_page: false,
+            });
+        }
+
+        // every meta record points to a full page
+        Err(StorageError::TableFull.into())
+    }
+
+    /// Insert consecutive rows, flushing once at the end.
+    ///
+    /// Free slots of the table's pages are filled first,
+    /// then new pages are allocated.
+    /// Each page is written whole, once.
+    ///
+    /// Returns the number of rows written,
+    /// fewer than given if the table or the file runs out of room.
+    /// If not even the first row fits, the reason is returned as an error.
+    pub fn insert_many(&mut self, table_name: &str, rows: &[u8]) -> Result<usize, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, data_len) = (meta.row_len as usize, meta.data_len() as usize);
+        if data_len == 0 || !rows.len().is_multiple_of(data_len) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "data is not a whole number of rows",
+            )
+            .into());
+        }
+        self.check_unique_keys(table_name, rows)?;
+        let numbered = self.number_rows(table_name, rows)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let per_page = self.layout.rows_per_page(meta.row_len) as usize;
+        let (meta_offset, delete_policy, alloc_hint) =
+            (meta.meta_offset, meta.delete_policy, meta.alloc_hint);
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let free_records: Vec<u8> = (0..self.layout.meta_record_count())
+            .filter(|record_number| !meta.table_offsets.contains_key(record_number))
+            .collect();
+        let mut rows = numbered.chunks_exact(data_len);
+        let mut written = 0;
+        let mut free_slots = Vec::new();
+        let mut new_pages = Vec::new();
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+
+        self.poisoned = true;
+        for page in pages {
+            if rows.len() == 0 {
+                break;
+            }
+            self.read_page(page, &mut page_buf)?;
+            // fill the page in memory, then write it back once
+            let (mut free, mut filled) = (0, 0);
+            for slot in page_buf[..per_page * row_len].chunks_exact_mut(row_len) {
+                if !is_empty_row(slot, delete_policy) {
+                    continue;
+                }
+                match rows.next() {
+                    Some(row) => {
+                        store_row(slot, row, delete_policy);
+                        filled += 1;
+                    }
+                    None => free += 1,
+                }
+            }
+            if filled > 0 {
+                self.write_page(page, &page_buf)?;
+                written += filled;
+            }
+            let tombstones_left = page_buf[..per_page * row_len]
+                .chunks_exact(row_len)
+                .any(|slot| slot[0] == TOMBSTONE);
+            free_slots.push((page, free, tombstones_left));
+        }
+
+        let mut no_room = None;
+        for record_number in free_records {
+            if rows.len() == 0 {
+                break;
+            }
+            let page = match self.alloc_page(alloc_hint) {
+                Ok(page) => page,
+                Err(e) => {
+                    no_room = Some(e);
+                    break;
+                }
+            };
+            page_buf.fill(0);
+            let mut count = 0;
+            for (row, slot) in rows
+                .by_ref()
+                .take(per_page)
+                .zip(page_buf.chunks_exact_mut(row_len))
+            {
+                store_row(slot, row, delete_policy);
+                count += 1;
+            }
+            self.write_page(page, &page_buf)?;
+            self.write_at(
+                self.layout.page_pos(meta_offset)
+                    + record_number as u64 * META_TABLE_ROW_LEN as u64,
+                &page.to_be_bytes(),
+            )?;
+            new_pages.push((record_number, page, per_page - count));
+            written += count;
+        }
+        self.flush()?;
+
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            // like insert_into_free_slot, forget the slots the rows went into
+            for (page, free, tombstones_left) in free_slots {
+                if free > 0 {
+                    meta.free_slots.insert(page, free);
+                } else {
+                    meta.free_slots.remove(&page);
+                }
+                if !tombstones_left {
+                    meta.tombstone_pages.remove(&page);
+                }
+            }
+            for (record_number, page, free) in new_pages {
+                meta.table_offsets.insert(record_number, page);
+                if free > 0 {
+                    meta.free_slots.insert(page, free as u32);
+                }
+            }
+        }
+        self.poisoned = false;
+
+        if written == 0 && rows.len() > 0 {
+            return Err(no_room
+                .unwrap_or_else(|| io::Error::other(StorageError::TableFull))
+                .into());
+        }
+        Ok(written)
+    }
+
+    /// Insert a row given as one value per column.
+    ///
+    /// Blob columns take values of any length,
+    /// which are written to the blob file next to the database.
+    /// Other values must be as long as their column.
+    pub fn insert_values(
+        &mut self,
+        table_name: &str,
+        values: &[&[u8]],
+    ) -> Result<InsertReport, DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        if values.len() != defs.len() {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "one value per column is needed").into(),
+            );
+        }
+        let mut row = Vec::new();
+        let mut pointers = Vec::new();
+        for (def, value) in defs.iter().zip(values) {
+            if def.column_type == ColumnType::Blob {
+                let pointer = self.blob_file()?.put(value)?;
+                row.extend_from_slice(&pointer);
+                pointers.push(pointer);
+            } else if value.len() == def.size as usize {
+                row.extend_from_slice(value);
+            } else {
+                return Err(DatabaseError::ColumnLenMismatch {
+                    column: def.name.clone(),
+                    expected: def.size,
+                    got: value.len(),
+                });
+            }
+        }
+
+        let res = self.insert_report(table_name, &row);
+        if res.is_err() {
+            let blobs = self.blob_file()?;
+            for pointer in pointers {
+                blobs.free(&pointer);
+            }
+        }
+        res
+    }
+
+    /// Like [`Database::insert`], but takes one value per column
+    /// and checks each against the size of its column before joining them.
+    ///
+    /// Blob columns take the pointer bytes, see [`Database::insert_values`]
+    /// to store the blob itself.
+    pub fn insert_checked(
+        &mut self,
+        table_name: &str,
+        values: &[&[u8]],
+    ) -> Result<(), DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        if values.len() != defs.len() {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "one value per column is needed").into(),
+            );
+        }
+        let mut row = Vec::new();
+        for (def, value) in defs.iter().zip(values) {
+            if value.len() != def.size as usize {
+                return Err(DatabaseError::ColumnLenMismatch {
+                    column: def.name.clone(),
+                    expected: def.size,
+                    got: value.len(),
+                });
+            }
+            row.extend_from_slice(value);
+        }
+        self.insert(table_name, &row)
+    }
+
+    /// Like [`Database::select`],
+    /// but splits each row into one value per column,
+    /// reading blob columns from the blob file.
+    pub fn select_values<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+    ) -> Result<Vec<Vec<Vec<u8>>>, DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let rows = self.select(table_name, conditions)?;
+        let mut res = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut values = Vec::with_capacity(defs.len());
+            let mut start = 0;
+            for def in &defs {
+                let Some(value) = row.get(start..start + def.size as usize) else {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "column sizes exceed row length",
+                    )
+                    .into());
+                };
+                start += def.size as usize;
+                if def.column_type == ColumnType::Blob {
+                    values.push(self.blob_file()?.get(value)?);
+                } else {
+                    values.push(value.to_vec());
+                }
+            }
+            res.push(values);
+        }
+        Ok(res)
+    }
+
+    /// Rows accepted by every filter,
+    /// [`Condition`]s and [`table::RangeCondition`]s can be mixed as `&dyn Filter`.
+    pub fn select<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        if let Some(rows) = self.select_indexed(table_name, conditions)? {
+            return Ok(rows);
+        }
+        self.select_iter(table_name, conditions)?.collect()
+    }
+
+    /// Like [`Database::select`], but reads rows as the iterator advances,
+    /// one data page at a time.
+    pub fn select_iter<'a, F: Filter>(
+        &'a mut self,
+        table_name: &str,
+        conditions: &'a [F],
+    ) -> Result<RowIterator<'a, F, S>, DatabaseError> {
+        self.check_poisoned()?;
+        self.debug_check_row_len(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        let (meta_offset, row_len, delete_policy) =
+            (meta.meta_offset, meta.row_len, meta.delete_policy);
+        let mut meta_page = vec![0; self.layout.page_size as usize].into_boxed_slice();
+        self.read_page(meta_offset, &mut meta_page)?;
+        let page = vec![0; self.layout.page_size as usize].into_boxed_slice();
+
+        Ok(RowIterator {
+            db: self,
+            conditions,
+            meta_page,
+            record: 0,
+            page,
+            slot: 0,
+            slots: 0,
+            row_len: row_len as usize,
+            delete_policy,
+        })
+    }
+
+    /// Like [`Database::select`],
+    /// but skips the first `offset` matching rows and returns at most `limit` rows.
+    ///
+    /// The scan stops as soon as the limit is reached.
+    pub fn select_paged<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.select_iter(table_name, conditions)?
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Rows inserted after number `seq` of the table's insertion sequence,
+    /// with their numbers, oldest first.
+    ///
+    /// The table needs a [`TableOptions::sequence_column`].
+    /// This is insert-only change data capture:
+    /// deleted rows are simply no longer returned and updates are not seen,
+    /// so following it with the last number returned only picks up new rows.
+    pub fn select_since(
+        &mut self,
+        table_name: &str,
+        seq: u64,
+    ) -> Result<Vec<(u64, Vec<u8>)>, DatabaseError> {
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let Some((_, range)) = meta.sequence.clone() else {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "table has no sequence column").into(),
+            );
+        };
+        let newer = [Condition::gt(range.clone(), seq.to_be_bytes())];
+        let mut res: Vec<(u64, Vec<u8>)> = self
+            .select(table_name, &newer)?
+            .into_iter()
+            .map(|row| {
+                (
+                    u64::from_be_bytes(row[range.clone()].try_into().unwrap()),
+                    row,
+                )
+            })
+            .collect();
+        res.sort_unstable_by_key(|(seq, _)| *seq);
+        Ok(res)
+    }
+
+    /// Like [`Database::select`],
+    /// but each row is made of the bytes in `columns` only, concatenated in order.
+    ///
+    /// Ranges may overlap or come in any order.
+    pub fn select_columns<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+        columns: &[Range<usize>],
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        if let Some(range) = columns
+            .iter()
+            .find(|range| range.start > range.end || range.end > meta.data_len() as usize)
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("column range {:?} out of row", range),
+            )
+            .into());
+        }
+        let len = columns.iter().map(|range| range.len()).sum();
+
+        self.select_iter(table_name, conditions)?
+            .map(|row| {
+                let row = row?;
+                let mut res = Vec::with_capacity(len);
+                for range in columns {
+                    res.extend_from_slice(&row[range.clone()]);
+                }
+                Ok(res)
+            })
+            .collect()
+    }
+
+    /// Like [`Database::select`], with one predicate instead of a conjunction.
+    pub fn select_where<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        predicate: &Predicate<T>,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.select(table_name, slice::from_ref(predicate))
+    }
+
+    /// Number of rows [`Database::select`] would return,
+    /// without keeping the rows.
+    pub fn count<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<usize, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        let (meta_offset, row_len, delete_policy) =
+            (meta.meta_offset, meta.row_len, meta.delete_policy);
+        let rows = self.layout.rows_per_page(row_len) as usize;
+        let mut res = 0;
+
+        let mut meta_page = vec![0; self.layout.page_size as usize];
+        self.read_page(meta_offset, &mut meta_page)?;
+        let mut page = vec![0; self.layout.page_size as usize];
+        for record in meta_page
+            .chunks_exact(META_TABLE_ROW_LEN as usize)
+            .take(self.layout.meta_record_count() as usize)
+        {
+            let table_offset = i32::from_be_bytes(record[..4].try_into().unwrap());
+            if table_offset == 0 {
+                continue;
+            }
+
+            self.read_page(table_offset, &mut page)?;
+            let row_len = row_len as usize;
+            res += (0..rows)
+                .map(|slot| &page[slot * row_len..(slot + 1) * row_len])
+                .filter(|buf| {
+                    !is_empty_row(buf, delete_policy) && conditions.iter().all(|c| c.matches(buf))
+                })
+                .count();
+        }
+
+        Ok(res)
+    }
+
+    /// Like [`Database::select`], but returns each distinct row once,
+    /// in the order first seen.
+    ///
+    /// Rows are compared by all of their bytes.
+    /// Every distinct row is kept twice in memory until the scan ends.
+    pub fn select_distinct<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        let mut seen = HashSet::new();
+        let mut rows = self.select(table_name, conditions)?;
+        rows.retain(|row| seen.insert(row.clone()));
+        Ok(rows)
+    }
+
+    /// Record which slots of every table hold a row,
+    /// for [`Database::select_snapshot`].
+    ///
+    /// Every data page is read once.
+    pub fn snapshot(&mut self) -> Result<Snapshot, DatabaseError> {
+        self.check_poisoned()?;
+        let tables: Vec<(String, Vec<i32>, u16, DeletePolicy)> = self
+            .header_table
+            .iter()
+            .map(|(name, meta)| {
+                let pages = meta.table_offsets.values().copied().collect();
+                (name.clone(), pages, meta.row_len, meta.delete_policy)
+            })
+            .collect();
+        let mut live = HashMap::with_capacity(tables.len());
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for (name, table_offsets, row_len, delete_policy) in tables {
+            let rows_len = self.layout.rows_per_page(row_len) as usize * row_len as usize;
+            let mut pages = BTreeMap::new();
+            for table_offset in table_offsets {
+                self.read_page(table_offset, &mut page_buf)?;
+                let slots = page_buf[..rows_len]
+                    .chunks_exact(row_len.max(1) as usize)
+                    .map(|buf| !is_empty_row(buf, delete_policy))
+                    .collect();
+                pages.insert(table_offset, slots);
+            }
+            live.insert(name, pages);
+        }
+
+        Ok(Snapshot { live })
+    }
+
+    /// Like [`Database::select`],
+    /// but only looks at slots that held a row when `snapshot` was taken.
+    pub fn select_snapshot<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+        snapshot: &Snapshot,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        let Some(pages) = snapshot.live.get(table_name) else {
+            return Err(io::Error::other("table not in snapshot").into());
+        };
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let per_page = self.layout.rows_per_page(meta.row_len) as usize;
+        let table_offsets: HashSet<i32> = meta.table_offsets.values().copied().collect();
+        let mut res = Vec::new();
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for (&table_offset, slots) in pages {
+            // the page was freed since
+            if !table_offsets.contains(&table_offset) {
+                continue;
+            }
+            if slots.len() != per_page {
+                return Err(io::Error::other("row length changed since the snapshot").into());
+            }
+            self.read_page(table_offset, &mut page_buf)?;
+            for (buf, &was_live) in page_buf.chunks_exact(row_len.max(1)).zip(slots) {
+                if was_live
+                    && !is_empty_row(buf, delete_policy)
+                    && conditions.iter().all(|c| c.matches(buf))
+                {
+                    res.push(row_data(buf, delete_policy).to_vec());
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Select rows matching any of the condition sets in one scan,
+    /// each tagged with the index of the first set it matched.
+    pub fn classify<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        predicates: &[Vec<Condition<T>>],
+    ) -> Result<Vec<(usize, Vec<u8>)>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        for conditions in predicates {
+            check_conditions(conditions, meta.data_len())?;
+        }
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let mut res = Vec::new();
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut page_buf)?;
+            for buf in page_buf[..rows_len].chunks_exact(row_len.max(1)) {
+                if is_empty_row(buf, delete_policy) {
+                    continue;
+                }
+                if let Some(tag) = predicates
+                    .iter()
+                    .position(|conditions| conditions.iter().all(|c| c.matches(buf)))
+                {
+                    res.push((tag, row_data(buf, delete_policy).to_vec()));
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Select rows whose bytes in `key_range` are at least `start`
+    /// and below `end`, or up to `end` if `inclusive_end` is set.
+    ///
+    /// With an index on exactly `key_range`, see [`Database::create_index`],
+    /// only the rows the index has in range are read, and they come in key order.
+    /// Otherwise every data page is scanned, and rows come in storage order.
+    pub fn range_scan<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        key_range: Range<usize>,
+        start: T,
+        end: T,
+        inclusive_end: bool,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        if key_range.start > key_range.end || key_range.end > meta.data_len() as usize {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "key range out of row").into());
+        }
+        let indexed = self.range_scan_indexed(
+            table_name,
+            &key_range,
+            start.as_ref(),
+            end.as_ref(),
+            inclusive_end,
+        )?;
+        if let Some(rows) = indexed {
+            return Ok(rows);
+        }
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let mut res = Vec::new();
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut page_buf)?;
+            for buf in page_buf[..rows_len].chunks_exact(row_len.max(1)) {
+                if is_empty_row(buf, delete_policy) {
+                    continue;
+                }
+                let key = &buf[key_range.clone()];
+                let below_end = match key.cmp(end.as_ref()) {
+                    Ordering::Less => true,
+                    Ordering::Equal => inclusive_end,
+                    Ordering::Greater => false,
+                };
+                if key >= start.as_ref() && below_end {
+                    res.push(row_data(buf, delete_policy).to_vec());
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Bytes in `range` of each live row, in storage order.
+    ///
+    /// Pages are read one at a time as the iterator advances,
+    /// so only one page of values is held in memory.
+    pub fn column_iter(
+        &mut self,
+        table_name: &str,
+        range: Range<usize>,
+    ) -> Result<impl Iterator<Item = Result<Vec<u8>, DatabaseError>> + '_, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        if range.start > range.end || range.end > meta.data_len() as usize {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "column range out of row").into());
+        }
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let rows = self.layout.rows_per_page(meta.row_len) as usize;
+        // a table without columns has no rows to split pages into
+        let pages: Vec<i32> = match row_len {
+            0 => Vec::new(),
+            _ => meta.table_offsets.values().copied().collect(),
+        };
+        let mut page = vec![0; self.layout.page_size as usize];
+
+        Ok(pages.into_iter().flat_map(move |table_offset| {
+            let values: Vec<Result<Vec<u8>, DatabaseError>> =
+                match self.read_page(table_offset, &mut page) {
+                    Ok(()) => page[..rows * row_len]
+                        .chunks_exact(row_len)
+                        .filter(|row| !is_empty_row(row, delete_policy))
+                        .map(|row| Ok(row[range.clone()].to_vec()))
+                        .collect(),
+                    Err(e) => vec![Err(e.into())],
+                };
+            values
+        }))
+    }
+
+    pub fn select_pos(
+        &mut self,
+        table_name: &str,
+        data_table_page_offset: i32,
+        row_range: Range<i32>,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, data_len) = (meta.row_len as usize, meta.data_len() as usize);
+        let mut pos = self.layout.page_pos(data_table_page_offset)
+            + meta.row_len as u64 * row_range.start as u64;
+
+        let mut res = Vec::with_capacity((row_range.end - row_range.start) as usize);
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        let mut loaded = None;
+        for _ in row_range {
+            // rows past the end of the page are read from the pages after it
+            let mut r = Vec::with_capacity(row_len);
+            while r.len() < row_len {
+                let page = (pos / self.layout.page_size as u64) as i32;
+                let offset = (pos - self.layout.page_pos(page)) as usize;
+                if loaded != Some(page) {
+                    self.read_page(page, &mut page_buf)?;
+                    loaded = Some(page);
+                }
+                let len = (row_len - r.len()).min(page_buf.len() - offset);
+                r.extend_from_slice(&page_buf[offset..offset + len]);
+                pos += len as u64;
+            }
+            r.truncate(data_len);
+            res.push(r);
+        }
+
+        Ok(res)
+    }
+
+    pub fn update<F: Filter, N: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+        new_value: &[Condition<N>],
+    ) -> Result<usize, DatabaseError> {
+        self.update_explain(table_name, conditions, new_value)
+            .map(|report| report.updated)
+    }
+
+    /// Like [`Database::update`],
+    /// but also counts the rows each condition matched on its own.
+    pub fn update_explain<F: Filter, N: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+        new_value: &[Condition<N>],
+    ) -> Result<UpdateReport, DatabaseError> {
+        self.check_poisoned()?;
+        self.debug_check_row_len(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        check_conditions(new_value, meta.data_len())?;
+        let (meta_offset, delete_policy) = (meta.meta_offset, meta.delete_policy);
+        let row_len = meta.row_len as usize;
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let mut res = UpdateReport {
+            updated: 0,
+            matched: vec![0; conditions.len()],
+        };
+
+        let mut meta_page = vec![0; self.layout.page_size as usize];
+        self.read_page(meta_offset, &mut meta_page)?;
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for record in meta_page
+            [..self.layout.meta_record_count() as usize * META_TABLE_ROW_LEN as usize]
+            .chunks_exact(META_TABLE_ROW_LEN as usize)
+        {
+            let table_offset = i32::from_be_bytes(record[..4].try_into().unwrap());
+            if table_offset == 0 {
+                continue;
+            }
+
+            self.read_page(table_offset, &mut page_buf)?;
+            let mut updated = false;
+            for buf in page_buf[..rows_len].chunks_exact_mut(row_len.max(1)) {
+                if is_empty_row(buf, delete_policy) {
+                    continue;
+                }
+                // every condition is checked, so each gets its own count
+                let mut all = true;
+                for (c, matched) in conditions.iter().zip(&mut res.matched) {
+                    if c.matches(buf) {
+                        *matched += 1;
+                    } else {
+                        all = false;
+                    }
+                }
+                if all {
+                    for field in new_value {
+                        buf[field.range.clone()].copy_from_slice(field.data.as_ref());
+                    }
+                    res.updated += 1;
+                    updated = true;
+                }
+            }
+            if updated {
+                self.poisoned = true;
+                self.write_page(table_offset, &page_buf)?;
+            }
+        }
+        self.flush()?;
+        self.poisoned = false;
+
+        Ok(res)
+    }
+
+    /// Like [`Database::update`], with one predicate instead of a conjunction.
+    pub fn update_where<C: AsRef<[u8]>, N: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        predicate: &Predicate<C>,
+        new_value: &[Condition<N>],
+    ) -> Result<usize, DatabaseError> {
+        self.update(table_name, slice::from_ref(predicate), new_value)
+    }
+
+    /// Append `suffix` to the value in `column_range` of every matching row.
+    ///
+    /// The current value ends at its first zero byte.
+    /// Nothing is written if the result would not fit in any of the rows.
+    pub fn update_append<C: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<C>],
+        column_range: Range<usize>,
+        suffix: &[u8],
+    ) -> Result<usize, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        if column_range.start > column_range.end || column_range.end > meta.data_len() as usize {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "column range out of row").into());
+        }
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        // position of the first byte to write
+        let mut appends = Vec::new();
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut page_buf)?;
+            for (slot, buf) in page_buf[..rows_len]
+                .chunks_exact(row_len.max(1))
+                .enumerate()
+            {
+                if !is_empty_row(buf, delete_policy) && conditions.iter().all(|c| c.matches(buf)) {
+                    let value = &buf[column_range.clone()];
+                    let len = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+                    if len + suffix.len() > value.len() {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidInput,
+                            "appended value overflows the column",
+                        )
+                        .into());
+                    }
+                    let row_start = self.layout.page_pos(page) + (slot * row_len) as u64;
+                    appends.push(row_start + (column_range.start + len) as u64);
+                }
+            }
+        }
+
+        self.poisoned = true;
+        for &pos in &appends {
+            self.write_at(pos, suffix)?;
+        }
+        self.flush()?;
+        self.poisoned = false;
+
+        Ok(appends.len())
+    }
+
+    pub fn update_pos<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        data_table_page_offset: i32,
+        row_range: Range<i32>,
+        data: T,
+    ) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let data = meta.stored_row(data.as_ref()).repeat(row_range.len());
+        let pos = self.layout.page_pos(data_table_page_offset)
+            + meta.row_len as u64 * row_range.start as u64;
+        self.poisoned = true;
+        self.write_at(pos, &data)?;
+        self.flush()?;
+        self.poisoned = false;
+        Ok(())
+    }
+
+    pub fn delete<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<usize, DatabaseError> {
+        self.delete_returning(table_name, conditions)
+            .map(|rows| rows.len())
+    }
+
+    /// Like [`Database::delete`], but returns the deleted rows,
+    /// in storage order.
+    ///
+    /// Blob columns still hold their pointers,
+    /// but the values they point to are freed.
+    pub fn delete_returning<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.delete_up_to(table_name, conditions, usize::MAX)
+    }
+
+    /// Like [`Database::delete`], with one predicate instead of a conjunction.
+    pub fn delete_where<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        predicate: &Predicate<T>,
+    ) -> Result<usize, DatabaseError> {
+        self.delete(table_name, slice::from_ref(predicate))
+    }
+
+    pub fn delete_pos(
+        &mut self,
+        table_name: &str,
+        data_table_page_offset: i32,
+        row_range: Range<i32>,
+    ) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let count = row_range.len() as u32;
+        let fill = vec![delete_fill(meta.delete_policy); meta.row_len as usize * row_range.len()];
+        let pos = self.layout.page_pos(data_table_page_offset)
+            + meta.row_len as u64 * row_range.start as u64;
+        self.poisoned = true;
+        self.write_at(pos, &fill)?;
+        self.flush()?;
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            *meta.free_slots.entry(data_table_page_offset).or_default() += count;
+            if meta.delete_policy == DeletePolicy::Tombstone && count > 0 {
+                meta.tombstone_pages.insert(data_table_page_offset);
+            }
+        }
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Remove up to `limit` matching rows and return them.
+    ///
+    /// Matching rows are deleted per the table's delete policy,
+    /// and everything is flushed once at the end.
+    /// Data pages left without rows and blob values of the removed rows are freed,
+    /// as by [`Database::delete_returning`].
+    pub fn take_rows<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+        limit: usize,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.delete_up_to(table_name, conditions, limit)
+    }
+
+    /// Delete up to `limit` matching rows and return them,
+    /// freeing the data pages left without rows unless tombstoned.
+    fn delete_up_to<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+        limit: usize,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.check_poisoned()?;
+        self.debug_check_row_len(table_name)?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        check_conditions(conditions, meta.data_len())?;
+        let (meta_offset, delete_policy) = (meta.meta_offset, meta.delete_policy);
+        let row_len = meta.row_len as usize;
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let table_offsets = meta.table_offsets.clone();
+        let blob_columns = meta.blob_columns.clone();
+        let mut res = Vec::new();
+        let mut freed_records = Vec::new();
+        let mut free_slots = Vec::new();
+        let mut tombstone_pages = Vec::new();
+        let mut freed_blobs = Vec::new();
+
+        // only visit pages known to belong to the table
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for (record_number, table_offset) in table_offsets {
+            self.read_page(table_offset, &mut page_buf)?;
+            let mut empty_page = true;
+            let mut free = 0;
+            let mut deleted = false;
+            for buf in page_buf[..rows_len].chunks_exact_mut(row_len.max(1)) {
+                if is_empty_row(buf, delete_policy) {
+                    // nothing to delete
+                    free += 1;
+                } else if res.len() < limit && conditions.iter().all(|c| c.matches(buf)) {
+                    res.push(row_data(buf, delete_policy).to_vec());
+                    for range in &blob_columns {
+                        freed_blobs.push(buf[range.clone()].to_vec());
+                    }
+                    buf.fill(delete_fill(delete_policy));
+                    free += 1;
+                    deleted = true;
+                } else {
+                    empty_page = false;
+                }
+            }
+            if deleted {
+                self.poisoned = true;
+                self.write_page(table_offset, &page_buf)?;
+            }
+            // tombstoned pages keep their slots
+            if empty_page && delete_policy != DeletePolicy::Tombstone {
+                self.poisoned = true;
+                self.write_at(
+                    self.layout.page_pos(meta_offset)
+                        + record_number as u64 * META_TABLE_ROW_LEN as u64,
+                    &[0; META_TABLE_ROW_LEN as usize],
+                )?;
+                freed_records.push(record_number);
+            } else if free > 0 {
+                free_slots.push((table_offset, free));
+                if deleted && delete_policy == DeletePolicy::Tombstone {
+                    tombstone_pages.push(table_offset);
+                }
+            }
+            if res.len() >= limit {
+                break;
+            }
+        }
+        self.flush()?;
+        let mut freed_pages = Vec::new();
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            for record_number in freed_records {
+                if let Some(page) = meta.table_offsets.remove(&record_number) {
+                    meta.free_slots.remove(&page);
+                    meta.tombstone_pages.remove(&page);
+                    freed_pages.push(page);
+                }
+            }
+            meta.free_slots.extend(free_slots);
+            meta.tombstone_pages.extend(tombstone_pages);
+        }
+        for page in freed_pages {
+            self.free_page(page);
+        }
+        // an unopened blob file finds its free space when opened
+        if let Some(blobs) = &mut self.blobs {
+            for pointer in freed_blobs {
+                blobs.free(&pointer);
+            }
+        }
+        self.poisoned = false;
+
+        Ok(res)
+    }
+
+    /// Move all pages of a table to the lowest free contiguous page range.
+    ///
+    /// The def page comes first, then the meta page,
+    /// then the data pages in meta record order.
+    /// Pages the table already owns count as free.
+    pub fn move_table(&mut self, table_name: &str) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let old_pages: Vec<i32> = [meta.col_def_offset, meta.meta_offset]
+            .into_iter()
+            .chain(meta.table_offsets.values().copied())
+            .collect();
+        let count = old_pages.len() as i32;
+        let limit = self.options.max_pages.unwrap_or(i32::MAX);
+        let mut start = 0;
+        while (start..start + count)
+            .any(|p| self.in_use_pages.contains(&p) && !old_pages.contains(&p))
+        {
+            start += 1;
+            if start > limit - count {
+                return Err(StorageError::QuotaExceeded.into());
+            }
+        }
+
+        let new_table_offsets: BTreeMap<u8, i32> = meta
+            .table_offsets
+            .keys()
+            .zip(start + 2..)
+            .map(|(&record_number, page)| (record_number, page))
+            .collect();
+        let header_record_offset = meta.header_record_offset;
+
+        // read everything first, so the new range may overlap the old one
+        let mut pages = Vec::with_capacity(old_pages.len());
+        for &page in &old_pages {
+            let mut buf = vec![0; self.layout.page_size as usize];
+            self.read_page(page, &mut buf)?;
+            pages.push(buf);
+        }
+        for (record_number, page) in &new_table_offsets {
+            let pos = *record_number as usize * META_TABLE_ROW_LEN as usize;
+            pages[1][pos..pos + 4].copy_from_slice(&page.to_be_bytes());
+        }
+
+        self.grow_for(start + count - 1)?;
+        self.poisoned = true;
+        for (page, buf) in (start..).zip(&pages) {
+            self.write_page(page, buf)?;
+        }
+        let record = header_record(self.layout, table_name, start, start + 1);
+        self.write_at(self.layout.header_pos(header_record_offset), &record)?;
+        // rows leave the old pages without them being written
+        self.mark_index_pages(Some(old_pages.iter().copied()))?;
+        self.flush()?;
+
+        for page in old_pages {
+            self.free_page(page);
+        }
+        self.in_use_pages.extend(start..start + count);
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.col_def_offset = start;
+            meta.meta_offset = start + 1;
+            meta.table_offsets = new_table_offsets;
+            meta.free_slots.clear();
+            meta.tombstone_pages.clear();
+        }
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Like [`Database::select`],
+    /// but hands matching rows to `f` in batches of up to `batch_size` rows
+    /// instead of collecting all of them.
+    ///
+    /// The scan stops at the first error returned by `f`.
+    pub fn select_batched<T, F>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+        batch_size: usize,
+        mut f: F,
+    ) -> Result<(), DatabaseError>
+    where
+        T: AsRef<[u8]>,
+        F: FnMut(&[Vec<u8>]) -> Result<(), DatabaseError>,
+    {
+        self.check_poisoned()?;
+        if batch_size == 0 {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "batch size must be positive").into(),
+            );
+        }
+        let mut batch = Vec::with_capacity(batch_size);
+
+        for row in self.select_iter(table_name, conditions)? {
+            batch.push(row?);
+            if batch.len() == batch_size {
+                f(&batch)?;
+                batch.clear();
+            }
+        }
+        if !batch.is_empty() {
+            f(&batch)?;
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Database::select`],
+    /// but fails with [`StorageError::ResultTooLarge`]
+    /// once the selected rows take more than `max_bytes` bytes.
+    pub fn select_bounded<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+        max_bytes: usize,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        let mut res = Vec::new();
+        let mut bytes = 0;
+        self.select_batched(table_name, conditions, 1, |rows| {
+            bytes += rows.iter().map(Vec::len).sum::<usize>();
+            if bytes > max_bytes {
+                return Err(StorageError::ResultTooLarge.into());
+            }
+            res.extend_from_slice(rows);
+            Ok(())
+        })?;
+        Ok(res)
+    }
+
+    /// Write the pages the page cache holds modified, then flush the file.
+    ///
+    /// Every mutation does this before returning,
+    /// so pages are only held modified while one is running.
+    pub fn flush_all(&mut self) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        Ok(self.flush()?)
+    }
+
+    /// Hand every pending write to the file and sync it to disk,
+    /// the writes held by [`DurabilityMode::Manual`] included.
+    ///
+    /// Indexes changed since they were last saved are saved first,
+    /// see [`Database::create_index`].
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.check_poisoned()?;
+        self.save_indexes()?;
+        self.flush()?;
+        self.writer.get_ref().sync_all()
+    }
+
+    /// Hits and misses of the page cache since opening.
+    pub fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.cache.hits,
+            misses: self.cache.misses,
+        }
+    }
+
+    /// How the pages of the file are used, from the in-memory metadata
+    /// and the length of the file.
+    pub fn stats(&self) -> Result<DatabaseStats, DatabaseError> {
+        let file_pages = self.writer.get_ref().len()? / self.layout.page_size as u64;
+        let used_pages = self.in_use_pages.len();
+        let mut tables: Vec<(String, TableUsage)> = self
+            .header_table
+            .iter()
+            .map(|(name, meta)| {
+                let data_pages = meta.table_offsets.len();
+                let usage = TableUsage {
+                    row_len: meta.data_len(),
+                    data_pages,
+                    total_pages: data_pages + 2 + meta.index_pages().count(),
+                };
+                (name.clone(), usage)
+            })
+            .collect();
+        tables.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(DatabaseStats {
+            file_pages,
+            used_pages,
+            free_pages: file_pages.saturating_sub(used_pages as u64),
+            tables,
+        })
+    }
+
+    /// Start a transaction, ended by [`Database::commit`] or [`Database::rollback`].
+    ///
+    /// The first write to each page saves its contents in an undo log kept in memory.
+    /// It only protects against an explicit rollback, not against crashes:
+    /// a crash while the transaction is open leaves its writes in the file.
+    /// Crashes are only covered with [`DatabaseOptions::wal`] enabled,
+    /// and then one flush at a time, not the transaction as a whole.
+    /// Values stored in the blob file are not rolled back.
+    ///
+    /// Fails with [`StorageError::TransactionOpen`] if one is open already.
+    pub fn begin(&mut self) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        if self.transaction.is_some() {
+            return Err(StorageError::TransactionOpen.into());
+        }
+        self.flush()?;
+        // the indexes saved below must not miss pages written before
+        self.refresh_indexes()?;
+        self.writer.undo = Some(UndoLog {
+            source: self.writer.get_ref().try_clone()?,
+            pages: HashMap::new(),
+        });
+        self.transaction = Some(Transaction {
+            header_table: self.header_table.clone(),
+            in_use_pages: self.in_use_pages.clone(),
+            free_pages: self.free_pages.clone(),
+            file_len: self.writer.get_ref().len()?,
+        });
+        Ok(())
+    }
+
+    /// Flush and sync the writes of the open transaction, and forget their undo log.
+    pub fn commit(&mut self) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        if self.transaction.is_none() {
+            return Err(StorageError::NoTransaction.into());
+        }
+        self.flush()?;
+        self.writer.get_ref().sync_all()?;
+        self.writer.undo = None;
+        self.transaction = None;
+        Ok(self.shrink_tail()?)
+    }
+
+    /// Put back the pages the open transaction wrote to
+    /// and the in-memory state as of [`Database::begin`].
+    ///
+    /// This also clears the poisoned state left by a mutation failing inside the transaction.
+    pub fn rollback(&mut self) -> Result<(), DatabaseError> {
+        let Some(transaction) = self.transaction.take() else {
+            return Err(StorageError::NoTransaction.into());
+        };
+        self.poisoned = true;
+        // pending bytes are written before their pages are put back
+        self.writer.flush()?;
+        let undo = self.writer.undo.take().unwrap();
+        self.cache.discard();
+        for (page, image) in undo.pages {
+            self.writer
+                .seek(SeekFrom::Start(self.layout.page_pos(page)))?;
+            self.writer.write_all(&image)?;
+        }
+        self.writer.flush()?;
+        self.writer.get_ref().set_len(transaction.file_len)?;
+        self.file_len = transaction.file_len;
+        self.flush()?;
+
+        self.header_table = transaction.header_table;
+        self.in_use_pages = transaction.in_use_pages;
+        self.free_pages = transaction.free_pages;
+        // rescanned for the extents still referenced
+        self.blobs = None;
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Rebuild the in-memory metadata from the file, exactly as [`Database::open`] does.
+    ///
+    /// This is the way to recover
+    /// once the cached table offsets or page usage drift from the file,
+    /// and it also clears the poisoned state.
+    ///
+    /// The option record of every table is written back as rebuilt,
+    /// with allocation hints past the end of the file dropped.
+    pub fn rebuild_metadata(&mut self) -> Result<(), DatabaseError> {
+        self.flush()?;
+        self.cache.clear()?;
+        let (mut header_table, in_use_pages) = load_metadata(&mut self.reader, self.layout)?;
+        self.file_len = self.reader.get_ref().len()?;
+        let page_count = self.file_len / self.layout.page_size as u64;
+        for meta in header_table.values_mut() {
+            if meta.alloc_hint < 0 || meta.alloc_hint as u64 >= page_count {
+                meta.alloc_hint = 0;
+            }
+        }
+        self.free_pages = spare_pages(&in_use_pages, self.file_len, self.layout);
+        self.header_table = header_table;
+        self.in_use_pages = in_use_pages;
+        self.stale_index_pages = index::stale_after_load(&self.header_table);
+
+        self.poisoned = true;
+        let records: Vec<(u64, [u8; 19])> = self
+            .header_table
+            .values()
+            .map(|meta| {
+                let pos = self
+                    .layout
+                    .def_pos(meta.col_def_offset, self.layout.table_option_record());
+                // bytes 1.. of the record, as create_table lays them out
+                let mut record = [0; 19];
+                record[0] = meta.delete_policy as u8;
+                record[1..5].copy_from_slice(&meta.alloc_hint.to_be_bytes());
+                record[5] = meta.primary_key.as_ref().map_or(0, |(index, _)| index + 1);
+                record[6..10].copy_from_slice(&meta.index_page.to_be_bytes());
+                record[10] = meta.sequence.as_ref().map_or(0, |(index, _)| index + 1);
+                record[11..19].copy_from_slice(&meta.last_sequence.to_be_bytes());
+                (pos + 1, record)
+            })
+            .collect();
+        for (pos, record) in records {
+            self.write_at(pos, &record)?;
+        }
+        self.flush()?;
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Sum the column sizes in the def page of a table again,
+    /// and replace the cached row length with the result.
+    ///
+    /// Returns the new row length.
+    pub fn recompute_row_len(&mut self, table_name: &str) -> Result<u16, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let defs = self.column_defs(meta.col_def_offset)?;
+        let row_len = defs.iter().map(|def| def.size).sum();
+        let mut indexes_changed = false;
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            if meta.data_len() != row_len {
+                indexes_changed = meta.index_page != 0;
+                meta.previous_row_lens.push(meta.data_len());
+                // rows sit elsewhere in the pages now
+                meta.indexes
+                    .retain(|index| index.range.end <= row_len as usize);
+                self.stale_index_pages = None;
+            }
+            meta.row_len = row_len + flag_len(meta.delete_policy);
+            meta.column_count = defs.len() as u8;
+            meta.blob_columns = blob_columns(&defs);
+            meta.free_slots.clear();
+            meta.tombstone_pages.clear();
+        }
+        if indexes_changed {
+            self.write_index_page(table_name)?;
+        }
+        Ok(row_len)
+    }
+
+    /// Move every row of `src` into `dst`, then drop `src`.
+    ///
+    /// The rows are written with [`Database::insert_many`], so in one flush.
+    /// Both tables must have the same columns.
+    /// Returns the number of rows moved.
+    ///
+    /// The merge runs in its own transaction:
+    /// if not every row fits in `dst`, or anything else fails,
+    /// it is rolled back and both tables are left as they were.
+    /// Fails with [`StorageError::TransactionOpen`] if a transaction is open already.
+    pub fn merge_into(&mut self, dst: &str, src: &str) -> Result<usize, DatabaseError> {
+        if dst == src {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "cannot merge a table into itself",
+            )
+            .into());
+        }
+        if !self.same_schema(dst, src)? {
+            return Err(
+                io::Error::new(ErrorKind::InvalidInput, "tables have different columns").into(),
+            );
+        }
+        self.begin()?;
+        match self.move_rows(dst, src) {
+            Ok(moved) => {
+                self.commit()?;
+                Ok(moved)
+            }
+            Err(e) => {
+                self.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// The body of [`Database::merge_into`], dropping `src` only once all its rows are in `dst`.
+    fn move_rows(&mut self, dst: &str, src: &str) -> Result<usize, DatabaseError> {
+        let rows = self.select(src, &[] as &[Condition<&[u8]>])?;
+        let moved = self.insert_many(dst, &rows.concat())?;
+        if moved != rows.len() {
+            return Err(io::Error::other(format!(
+                "only {moved} of {} rows fit in {dst}",
+                rows.len()
+            ))
+            .into());
+        }
+        self.drop(src)?;
+        Ok(moved)
+    }
+
+    /// Check whether two tables have the same columns,
+    /// comparing names, types and sizes in order.
+    pub fn same_schema(&mut self, table_a: &str, table_b: &str) -> Result<bool, DatabaseError> {
+        let defs_a = self.get_table_def(table_a)?;
+        let defs_b = self.get_table_def(table_b)?;
+        Ok(defs_a == defs_b)
+    }
+
+    /// Read every data page of a table once through the page cache,
+    /// so that the following queries find them in memory,
+    /// or at least in the OS cache once the page cache is full.
+    ///
+    /// Nothing is changed.
+    pub fn warm(&mut self, table_name: &str) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let mut pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        pages.sort_unstable();
+
+        let mut buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut buf)?;
+        }
+        Ok(())
+    }
+
+    /// The data pages of a table, in meta record order.
+    pub fn data_pages(&self, table_name: &str) -> Result<Vec<i32>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        Ok(meta.table_offsets.values().copied().collect())
+    }
+
+    /// The rows stored in the `n`th data page of a table,
+    /// counted as in [`Database::data_pages`].
+    ///
+    /// Empty and deleted slots are skipped.
+    pub fn select_nth_page(
+        &mut self,
+        table_name: &str,
+        n: usize,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let Some(&page) = meta.table_offsets.values().nth(n) else {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "page index out of range").into());
+        };
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        self.read_page(page, &mut page_buf)?;
+
+        Ok(page_buf[..rows_len]
+            .chunks_exact(row_len.max(1))
+            .filter(|buf| !is_empty_row(buf, delete_policy))
+            .map(|buf| row_data(buf, delete_policy).to_vec())
+            .collect())
+    }
+
+    /// Look for metadata that does not fit the file,
+    /// and describe each problem found.
+    ///
+    /// Checks that every page of every table lies within the file,
+    /// no page belongs to two places,
+    /// and every table has columns.
+    pub fn check_integrity(&mut self) -> Result<Vec<String>, DatabaseError> {
+        self.check_poisoned()?;
+        let page_count = self.reader.get_ref().len()? / self.layout.page_size as u64;
+        let mut issues = Vec::new();
+        let mut owners: HashMap<i32, &str> = HashMap::from([(0, "header")]);
+
+        let mut tables: Vec<_> = self.header_table.iter().collect();
+        tables.sort_by_key(|(_, meta)| meta.header_record_offset);
+        for (name, meta) in tables {
+            if meta.data_len() == 0 {
+                issues.push(format!("table {name} has no columns"));
+            }
+            let pages = [meta.col_def_offset, meta.meta_offset]
+                .into_iter()
+                .chain(meta.table_offsets.values().copied())
+                .chain(meta.index_pages());
+            for page in pages {
+                if page < 0 || page as u64 >= page_count {
+                    issues.push(format!("page {page} of table {name} is outside the file"));
+                }
+                if let Some(owner) = owners.insert(page, name) {
+                    issues.push(format!(
+                        "page {page} of table {name} also belongs to {owner}"
+                    ));
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Pack the live rows of a table into as few data pages as possible,
+    /// in storage order.
+    ///
+    /// The rows are written to pages the table does not use yet,
+    /// then the meta page is switched over to them in one write,
+    /// and only then are the old pages cleared and freed.
+    /// Interrupted before the switch the table keeps its old pages,
+    /// after it the new ones, either way with all of its rows.
+    ///
+    /// Returns the number of data pages freed,
+    /// nothing is written if none would be.
+    pub fn vacuum(&mut self, table_name: &str) -> Result<usize, DatabaseError> {
+        self.check_poisoned()?;
+        let layout = self.layout;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (meta_offset, delete_policy, alloc_hint) =
+            (meta.meta_offset, meta.delete_policy, meta.alloc_hint);
+        let row_len = meta.row_len as usize;
+        let per_page = layout.rows_per_page(meta.row_len) as usize;
+        let old_pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+
+        let mut rows = Vec::new();
+        let mut page_buf = vec![0; layout.page_size as usize];
+        for &page in &old_pages {
+            self.read_page(page, &mut page_buf)?;
+            for slot in page_buf[..per_page * row_len].chunks_exact(row_len.max(1)) {
+                if !is_empty_row(slot, delete_policy) {
+                    rows.push(slot.to_vec());
+                }
+            }
+        }
+        let page_count = rows.len().div_ceil(per_page.max(1));
+        if page_count >= old_pages.len() {
+            return Ok(0);
+        }
+        let mut pages = Vec::with_capacity(page_count);
+        while pages.len() < page_count {
+            match self.alloc_page(alloc_hint) {
+                Ok(page) => pages.push(page),
+                Err(e) => {
+                    for &page in &pages {
+                        self.free_page(page);
+                    }
+                    return Err(e.into());
+                }
+            }
+        }
+
+        self.poisoned = true;
+        for (&page, chunk) in pages.iter().zip(rows.chunks(per_page.max(1))) {
+            page_buf.fill(0);
+            for (slot, row) in page_buf.chunks_exact_mut(row_len).zip(chunk) {
+                slot.copy_from_slice(row);
+            }
+            self.write_page(page, &page_buf)?;
+        }
+        self.flush()?;
+        page_buf.fill(0);
+        for (record_number, page) in pages.iter().enumerate() {
+            let pos = record_number * META_TABLE_ROW_LEN as usize;
+            page_buf[pos..pos + 4].copy_from_slice(&page.to_be_bytes());
+        }
+        self.write_page(meta_offset, &page_buf)?;
+        self.flush()?;
+        page_buf.fill(0);
+        for &page in &old_pages {
+            self.write_page(page, &page_buf)?;
+        }
+        self.flush()?;
+
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.free_slots.clear();
+            meta.tombstone_pages.clear();
+            if let Some(&last) = pages.last() {
+                let free = page_count * per_page - rows.len();
+                if free > 0 {
+                    meta.free_slots.insert(last, free as u32);
+                }
+            }
+            meta.table_offsets = (0..).zip(pages).collect();
+        }
+        for &page in &old_pages {
+            self.free_page(page);
+        }
+        self.poisoned = false;
+        Ok(old_pages.len() - page_count)
+    }
+
+    /// Rewrite the `n`th data page of a table,
+    /// counted as in [`Database::data_pages`],
+    /// with its rows packed at the start and the rest zeroed.
+    ///
+    /// Rows of the page may move to other slots.
+    /// Returns the number of tombstones removed.
+    pub fn compact_page(&mut self, table_name: &str, n: usize) -> Result<usize, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let Some(&page) = meta.table_offsets.values().nth(n) else {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "page index out of range").into());
+        };
+        let row_len = meta.row_len as usize;
+        let rows = self.layout.rows_per_page(meta.row_len) as usize;
+        let delete_policy = meta.delete_policy;
+        let mut buf = vec![0; self.layout.page_size as usize];
+        self.read_page(page, &mut buf)?;
+
+        let mut compacted = vec![0; self.layout.page_size as usize];
+        let mut live = 0;
+        let mut tombstones = 0;
+        for row in buf[..rows * row_len].chunks_exact(row_len.max(1)) {
+            if !is_empty_row(row, delete_policy) {
+                compacted[live * row_len..(live + 1) * row_len].copy_from_slice(row);
+                live += 1;
+            } else if row[0] == TOMBSTONE {
+                tombstones += 1;
+            }
+        }
+
+        self.poisoned = true;
+        self.write_page(page, &compacted)?;
+        self.flush()?;
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            if live < rows {
+                meta.free_slots.insert(page, (rows - live) as u32);
+            } else {
+                meta.free_slots.remove(&page);
+            }
+            meta.tombstone_pages.remove(&page);
+        }
+        self.poisoned = false;
+        Ok(tombstones)
+    }
+
+    /// Give a table a new name.
+    ///
+    /// Only its header record is rewritten, no page moves.
+    pub fn rename_table(&mut self, old: &str, new: &str) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, old)?;
+        if old == new {
+            return Ok(());
+        }
+        let layout = self.layout;
+        if new.len() > layout.table_name_max_len() as usize || new.is_empty() {
+            return Err(CreateTableError::TableNameInvalid.into());
+        }
+        if self.header_table.contains_key(new) {
+            return Err(CreateTableError::TableExists.into());
+        }
+        // the offsets follow the name, so the whole record is rewritten
+        let record = header_record(layout, new, meta.col_def_offset, meta.meta_offset);
+
+        let pos = layout.header_pos(meta.header_record_offset);
+
+        self.poisoned = true;
+        self.write_at(pos, &record)?;
+        self.flush()?;
+
+        let meta = self.header_table.remove(old).unwrap();
+        self.header_table.insert(new.to_string(), meta);
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Exchange the names of two tables,
+    /// so that each name resolves to the other table's data.
+    ///
+    /// Only the two header records are rewritten, in a single flush.
+    pub fn swap_tables(&mut self, table_a: &str, table_b: &str) -> Result<(), DatabaseError> {
+        self.check_poisoned()?;
+        let meta_a = check_table_exists(&self.header_table, table_a)?;
+        let meta_b = check_table_exists(&self.header_table, table_b)?;
+        if table_a == table_b {
+            return Ok(());
+        }
+        let layout = self.layout;
+        if table_a.len() > layout.table_name_max_len() as usize
+            || table_b.len() > layout.table_name_max_len() as usize
+        {
+            return Err(CreateTableError::TableNameInvalid.into());
+        }
+        let record_a = header_record(layout, table_b, meta_a.col_def_offset, meta_a.meta_offset);
+        let record_b = header_record(layout, table_a, meta_b.col_def_offset, meta_b.meta_offset);
+
+        let (pos_a, pos_b) = (
+            layout.header_pos(meta_a.header_record_offset),
+            layout.header_pos(meta_b.header_record_offset),
+        );
+
+        self.poisoned = true;
+        self.write_at(pos_a, &record_a)?;
+        self.write_at(pos_b, &record_b)?;
+        self.flush()?;
+
+        // each header record keeps its slot and pointers, only names move
+        let meta_a = self.header_table.remove(table_a).unwrap();
+        let meta_b = self.header_table.remove(table_b).unwrap();
+        self.header_table.insert(table_a.to_string(), meta_b);
+        self.header_table.insert(table_b.to_string(), meta_a);
+        self.poisoned = false;
+        Ok(())
+    }
+
+    /// Read `len` bytes at an absolute byte position of the file.
+    ///
+    /// This knows nothing about tables and works even when poisoned,
+    /// it is meant for recovery tools.
+    #[cfg(feature = "raw")]
+    pub fn read_bytes_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, DatabaseError> {
+        let file_len = self.reader.get_ref().len()?;
+        if offset
+            .checked_add(len as u64)
+            .is_none_or(|end| end > file_len)
+        {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "read out of file bounds").into());
+        }
+        let mut buf = vec![0; len];
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Like [`Database::select`], but column-major:
+    /// one buffer per column, holding that column of every matching row
+    /// back to back, so it can be handed to columnar formats.
+    ///
+    /// Integer columns of 1, 2, 4 or 8 bytes are decoded
+    /// to native-endian values, other columns are copied as stored.
+    ///
+    /// The rows are selected first and then transposed,
+    /// so this briefly holds the result twice
+    /// and copies every byte once more than `select`.
+    #[cfg(feature = "arrow")]
+    pub fn select_columnar<T: AsRef<[u8]>>(
+        &mut self,
+        table_name: &str,
+        conditions: &[Condition<T>],
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        let defs = self.get_table_def(table_name)?;
+        let rows = self.select(table_name, conditions)?;
+
+        let mut start = 0;
+        let mut res = Vec::with_capacity(defs.len());
+        for def in defs {
+            let range = start..start + def.size as usize;
+            start = range.end;
+            let mut column = Vec::with_capacity(rows.len() * def.size as usize);
+            for row in &rows {
+                let Some(value) = row.get(range.clone()) else {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "column sizes exceed row length",
+                    )
+                    .into());
+                };
+                decode_column_value(def.column_type, value, &mut column);
+            }
+            res.push(column);
+        }
+        Ok(res)
+    }
+
+    /// The number of live rows in each data page of a table,
+    /// paired with the page offset, in meta record order.
+    pub fn page_fill(&mut self, table_name: &str) -> Result<Vec<(i32, usize)>, DatabaseError> {
+        self.check_poisoned()?;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let rows_len = self.layout.rows_per_page(meta.row_len) as usize * row_len;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let mut res = Vec::with_capacity(pages.len());
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut page_buf)?;
+            let count = page_buf[..rows_len]
+                .chunks_exact(row_len.max(1))
+                .filter(|buf| !is_empty_row(buf, delete_policy))
+                .count();
+            res.push((page, count));
+        }
+
+        Ok(res)
+    }
+
+    /// Bytes of all pages a table owns,
+    /// its def, meta and index pages included.
+    ///
+    /// This counts whole pages, however full they are.
+    pub fn table_bytes(&self, table_name: &str) -> Result<u64, DatabaseError> {
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let pages = meta.table_offsets.len() + 2 + meta.index_pages().count();
+        Ok(pages as u64 * self.layout.page_size as u64)
+    }
+
+    /// Hash of the tables, their columns and their rows,
+    /// to tell whether two databases hold the same data.
+    ///
+    /// Databases are logically identical when they have tables of the same names,
+    /// with the same column definitions,
+    /// each holding the same multiset of rows.
+    /// Where rows and tables sit in the file does not matter,
+    /// and blob columns are hashed by value rather than by pointer.
+    ///
+    /// The hash is built from 64-bit FNV-1a, so it is stable across runs and platforms,
+    /// but it is meant to catch changes, not to resist forged collisions.
+    pub fn fingerprint(&mut self) -> Result<u64, DatabaseError> {
+        self.check_poisoned()?;
+        let mut hash = FNV_OFFSET_BASIS;
+
+        for name in self.list_tables() {
+            let mut table_hash = fnv1a(FNV_OFFSET_BASIS, &[name.len() as u8]);
+            table_hash = fnv1a(table_hash, name.as_bytes());
+            for def in self.get_table_def(&name)? {
+                table_hash = fnv1a(table_hash, &[def.name.len() as u8]);
+                table_hash = fnv1a(table_hash, def.name.as_bytes());
+                table_hash = fnv1a(table_hash, &[def.column_type.to_u8()]);
+                table_hash = fnv1a(table_hash, &def.size.to_be_bytes());
+            }
+
+            let meta = check_table_exists(&self.header_table, &name)?;
+            let row_len = meta.data_len() as usize;
+            let blob_ranges = meta.blob_columns.clone();
+            // blob values are read once the scan is done
+            let mut rows = Vec::new();
+            for row in self.column_iter(&name, 0..row_len)? {
+                let mut row = row?;
+                let mut pointers = Vec::with_capacity(blob_ranges.len());
+                for range in &blob_ranges {
+                    pointers.push(row[range.clone()].to_vec());
+                    row[range.clone()].fill(0);
+                }
+                rows.push((fnv1a(FNV_OFFSET_BASIS, &row), pointers));
+            }
+
+            // summing row hashes ignores the order of the rows
+            let mut rows_hash = 0u64;
+            let row_count = rows.len() as u64;
+            for (mut row_hash, pointers) in rows {
+                for pointer in pointers {
+                    let value = self.blob_file()?.get(&pointer)?;
+                    row_hash = fnv1a(row_hash, &(value.len() as u64).to_be_bytes());
+                    row_hash = fnv1a(row_hash, &value);
+                }
+                rows_hash = rows_hash.wrapping_add(row_hash);
+            }
+            table_hash = fnv1a(table_hash, &row_count.to_be_bytes());
+            table_hash = fnv1a(table_hash, &rows_hash.to_be_bytes());
+            hash = fnv1a(hash, &table_hash.to_be_bytes());
+        }
+
+        Ok(hash)
+    }
+
+    /// Names of all tables, sorted.
+    pub fn list_tables(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.header_table.keys().cloned().collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn table_exists(&self, table_name: &str) -> bool {
+        self.header_table.contains_key(table_name)
+    }
+
+    /// Summaries of all tables, in no particular order.
+    ///
+    /// Only in-memory metadata is used, the file is not read.
+    pub fn tables_info(&self) -> Vec<(String, TableInfo)> {
+        self.header_table
+            .iter()
+            .map(|(name, meta)| {
+                (
+                    name.clone(),
+                    TableInfo {
+                        row_len: meta.data_len(),
+                        page_count: meta.table_offsets.len(),
+                        column_count: meta.column_count,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+impl<S: Storage> Database<S> {
+    /// Write the live rows of a table again, each changed by `map`
+    /// to a row of `row_len` stored bytes,
+    /// packed from the first data page on.
+    ///
+    /// The table's data pages are reused in meta record order,
+    /// then more are allocated.
+    /// Leaves the database poisoned with the writes unflushed,
+    /// for the caller to add its own and finish with [`Database::finish_repack`].
+    fn repack_rows(
+        &mut self,
+        table_name: &str,
+        row_len: u16,
+        mut map: impl FnMut(&[u8]) -> Vec<u8>,
+    ) -> io::Result<Repacked> {
+        let layout = self.layout;
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let (meta_offset, delete_policy, alloc_hint) =
+            (meta.meta_offset, meta.delete_policy, meta.alloc_hint);
+        let old_row_len = meta.row_len as usize;
+        let old_pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+
+        // change every live row in memory first
+        let mut rows = Vec::new();
+        let mut page_buf = vec![0; layout.page_size as usize];
+        let old_rows = layout.rows_per_page(meta.row_len) as usize * old_row_len;
+        for &page in &old_pages {
+            self.read_page(page, &mut page_buf)?;
+            for slot in page_buf[..old_rows].chunks_exact(old_row_len.max(1)) {
+                if !is_empty_row(slot, delete_policy) {
+                    rows.push(map(row_data(slot, delete_policy)));
+                }
+            }
+        }
+
+        let per_page = layout.rows_per_page(row_len) as usize;
+        let page_count = rows.len().div_ceil(per_page);
+        if page_count > layout.meta_record_count() as usize {
+            return Err(io::Error::other(StorageError::TableFull));
+        }
+        let mut pages = old_pages[..page_count.min(old_pages.len())].to_vec();
+        while pages.len() < page_count {
+            match self.alloc_page(alloc_hint) {
+                Ok(page) => pages.push(page),
+                Err(e) => {
+                    for &page in &pages[old_pages.len()..] {
+                        self.free_page(page);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        self.poisoned = true;
+        for (&page, chunk) in pages.iter().zip(rows.chunks(per_page)) {
+            page_buf.fill(0);
+            for (slot, row) in page_buf.chunks_exact_mut(row_len as usize).zip(chunk) {
+                store_row(slot, row, delete_policy);
+            }
+            self.write_page(page, &page_buf)?;
+        }
+        page_buf.fill(0);
+        // pages left over are cleared like dropped ones
+        let left_over = old_pages[pages.len().min(old_pages.len())..].to_vec();
+        for &page in &left_over {
+            self.write_page(page, &page_buf)?;
+        }
+        for (record_number, page) in pages.iter().enumerate() {
+            let pos = record_number * META_TABLE_ROW_LEN as usize;
+            page_buf[pos..pos + 4].copy_from_slice(&page.to_be_bytes());
+        }
+        self.write_page(meta_offset, &page_buf)?;
+
+        let free = (page_count * per_page - rows.len()) as u32;
+        Ok(Repacked {
+            pages,
+            free,
+            left_over,
+        })
+    }
+
+    /// Point the table at its repacked pages once they are flushed,
+    /// and free the pages left over.
+    fn finish_repack(&mut self, table_name: &str, repacked: Repacked) {
+        if let Some(meta) = self.header_table.get_mut(table_name) {
+            meta.free_slots.clear();
+            meta.tombstone_pages.clear();
+            if let Some(&last) = repacked.pages.last() {
+                if repacked.free > 0 {
+                    meta.free_slots.insert(last, repacked.free);
+                }
+            }
+            meta.table_offsets = (0..).zip(repacked.pages).collect();
+            meta.stats = None;
+        }
+        for page in repacked.left_over {
+            self.free_page(page);
+        }
+        self.poisoned = false;
+    }
+
+    /// Fail with [`DatabaseError::DuplicateKey`] if two of `rows`,
+    /// or one of them and a row of the table, have the same primary key.
+    fn check_unique_keys(&mut self, table_name: &str, rows: &[u8]) -> io::Result<()> {
+        let meta = check_table_exists(&self.header_table, table_name)?;
+        let Some((_, range)) = meta.primary_key.clone() else {
+            return Ok(());
+        };
+        let (row_len, delete_policy) = (meta.row_len as usize, meta.delete_policy);
+        let per_page = self.layout.rows_per_page(meta.row_len) as usize;
+        let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+        let mut keys = HashSet::new();
+        for row in rows.chunks_exact(meta.data_len() as usize) {
+            if !keys.insert(&row[range.clone()]) {
+                return Err(io::Error::other(DatabaseError::DuplicateKey));
+            }
+        }
+
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        for page in pages {
+            self.read_page(page, &mut page_buf)?;
+            let taken = page_buf[..per_page * row_len]
+                .chunks_exact(row_len)
+                .any(|slot| {
+                    !is_empty_row(slot, delete_policy) && keys.contains(&slot[range.clone()])
+                });
+            if taken {
+                return Err(io::Error::other(DatabaseError::DuplicateKey));
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert into the lowest page known to have a deleted slot,
+    /// else into the lowest page known to have an empty one.
+    ///
+    /// Returns `None` if no page is known to have room.
+    /// Fill the sequence column of rows about to be inserted, if the table has one,
+    /// with the next numbers of its insertion sequence.
+    ///
+    /// The new last number is written to the option record,
+    /// to reach the file with the flush of the insert.
+    fn number_rows<'a>(&mut self, table_name: &str, rows: &'a [u8]) -> io::Result<Cow<'a, [u8]>> {
+        let Some(meta) = self.header_table.get_mut(table_name) else {
+            return Ok(Cow::Borrowed(rows));
+        };
+        let Some((_, range)) = meta.sequence.clone() else {
+            return Ok(Cow::Borrowed(rows));
+        };
+        let mut rows = rows.to_vec();
+        for row in rows.chunks_exact_mut(meta.data_len() as usize) {
+            meta.last_sequence += 1;
+            row[range.clone()].copy_from_slice(&meta.last_sequence.to_be_bytes());
+        }
+        let option_pos = self
+            .layout
+            .def_pos(meta.col_def_offset, self.layout.table_option_record());
+        let last = meta.last_sequence;
+        self.write_at(option_pos + 12, &last.to_be_bytes())?;
+        Ok(Cow::Owned(rows))
+    }
+
+    fn insert_into_free_slot(
+        &mut self,
+        table_name: &str,
+        data: &[u8],
+    ) -> io::Result<Option<RowId>> {
+        let row_len = data.len();
+        let mut page_buf = vec![0; self.layout.page_size as usize];
+        loop {
+            let Some(meta) = self.header_table.get_mut(table_name) else {
+                return Ok(None);
+            };
+            // tombstoned slots anywhere in the table come before empty ones
+            let mut tombstoned = None;
+            while let Some(page) = meta.tombstone_pages.pop_first() {
+                if meta.free_slots.remove(&page).is_some() {
+                    tombstoned = Some(page);
+                    break;
+                }
+            }
+            let page = match tombstoned {
+                Some(page) => page,
+                None => match meta.free_slots.pop_first() {
+                    Some((page, _)) => page,
+                    None => return Ok(None),
+                },
+            };
+            let rows = self.layout.rows_per_page(meta.row_len) as usize;
+            let delete_policy = meta.delete_policy;
+            self.read_page(page, &mut page_buf)?;
+            let free: Vec<usize> = (0..rows)
+                .filter(|i| is_empty_row(&page_buf[i * row_len..(i + 1) * row_len], delete_policy))
+                .collect();
+            // the count was stale, look at the next page
+            let Some(&first) = free.first() else {
+                continue;
+            };
+            let slot = free
+                .iter()
+                .copied()
+                .find(|i| page_buf[i * row_len] == TOMBSTONE)
+                .unwrap_or(first);
+            let tombstones_left = free
+                .iter()
+                .any(|&i| i != slot && page_buf[i * row_len] == TOMBSTONE);
+            if let Some(meta) = self.header_table.get_mut(table_name) {
+                if free.len() > 1 {
+                    meta.free_slots.insert(page, free.len() as u32 - 1);
+                }
+                if tombstones_left {
+                    meta.tombstone_pages.insert(page);
+                }
+            }
+
+            self.poisoned = true;
+            page_buf[slot * row_len..(slot + 1) * row_len].copy_from_slice(data);
+            self.write_page(page, &page_buf)?;
+            self.flush()?;
+            self.poisoned = false;
+            return Ok(Some(RowId {
+                page,
+                slot: slot as u32,
+            }));
+        }
+    }
+
+    /// Flush the writer and drop whatever the reader has buffered,
+    /// so that reads after this see the written bytes.
+    fn flush(&mut self) -> io::Result<()> {
+        let touched = self.writer.take_touched();
+        // cached copies of pages written around the cache are stale
+        match &touched {
+            Some(pages) => self.cache.invalidate(pages.iter().copied())?,
+            None => self.cache.clear()?,
+        }
+        self.mark_index_pages(touched)?;
+        self.mark_index_pages(Some(self.cache.dirty_pages()))?;
+        // after marking, which may change cached index pages
+        let dirty = self.cache.take_dirty();
+        for (page, frame) in dirty {
+            self.writer
+                .seek(SeekFrom::Start(self.layout.page_pos(page)))?;
+            self.writer.write_all(&frame[..])?;
+        }
+        self.writer.take_touched();
+        self.writer.flush()?;
+        if self.options.durability == DurabilityMode::Fsync {
+            self.writer.get_ref().sync_all()?;
+        }
+        let pos = self.reader.stream_position()?;
+        self.reader.seek(SeekFrom::Start(pos))?;
+        Ok(())
+    }
+
+    /// Read a whole page, through the page cache.
+    fn read_page(&mut self, page: i32, buf: &mut [u8]) -> io::Result<()> {
+        if let Some(frame) = self.cache.get(page) {
+            buf.copy_from_slice(&frame[..]);
+            return Ok(());
+        }
+        read_checked_page(&mut self.reader, self.layout, page, buf)?;
+        if self.cache.is_enabled() {
+            let evicted = self.cache.insert(page, buf.into(), false);
+            self.write_evicted(evicted)?;
+        }
+        Ok(())
     }
 
-    pub fn drop(&mut self, table_name: &str) -> io::Result<()> {
-        if let Some(meta) = self.header_table.remove(table_name) {
-            let writer = &mut self.writer;
-            writer.seek(SeekFrom::Start(
-                (meta.header_record_offset * HEADER_TABLE_ROW_LEN) as u64,
-            ))?;
-            writer.write_all(&[0; HEADER_TABLE_ROW_LEN as usize])?;
-            writer.flush()?;
+    /// Write a whole page, through the page cache.
+    ///
+    /// The page reaches the file on the next flush at the latest.
+    fn write_page(&mut self, page: i32, buf: &[u8]) -> io::Result<()> {
+        if self.cache.is_enabled() {
+            let evicted = self.cache.insert(page, buf.into(), true);
+            return self.write_evicted(evicted);
+        }
+        self.writer
+            .seek(SeekFrom::Start(self.layout.page_pos(page)))?;
+        self.writer.write_all(buf)
+    }
 
-            self.in_use_pages.remove(&meta.col_def_offset);
-            self.in_use_pages.remove(&meta.meta_offset);
-            for table_offset in meta.table_offsets {
-                self.in_use_pages.remove(&(table_offset + meta.meta_offset));
+    /// Write bytes at a position of the file,
+    /// into the frames of cached pages and around the cache for the others.
+    fn write_at(&mut self, mut pos: u64, mut bytes: &[u8]) -> io::Result<()> {
+        while !bytes.is_empty() {
+            let page = (pos / self.layout.page_size as u64) as i32;
+            let offset = (pos - self.layout.page_pos(page)) as usize;
+            let (head, rest) =
+                bytes.split_at(bytes.len().min(self.layout.page_size as usize - offset));
+            if !self.cache.patch(page, offset, head) {
+                self.writer.seek(SeekFrom::Start(pos))?;
+                self.writer.write_all(head)?;
+            }
+            pos += head.len() as u64;
+            bytes = rest;
+        }
+        Ok(())
+    }
+
+    fn write_evicted(&mut self, evicted: Option<(i32, Frame)>) -> io::Result<()> {
+        if let Some((page, frame)) = evicted {
+            self.writer
+                .seek(SeekFrom::Start(self.layout.page_pos(page)))?;
+            self.writer.write_all(&frame[..])?;
+            self.mark_index_pages(Some([page]))?;
+            // the cache no longer holds the page, so nothing goes stale
+            if let Some(touched) = &mut self.writer.touched {
+                touched.remove(&page);
             }
-            Ok(())
-        } else {
-            Err(io::Error::new(ErrorKind::Other, "table not found"))
         }
+        Ok(())
     }
 
-    pub fn get_table_def(&mut self, table_name: &str) -> io::Result<Vec<ColumnDef<String>>> {
-        if let Some(meta) = self.header_table.get(table_name) {
-            let def_offset = meta.col_def_offset;
-            let reader = &mut self.reader;
-
-            reader.seek(SeekFrom::Start(def_offset as u64 * PAGE_SIZE as u64))?;
-            let mut defs = Vec::new();
-            for _ in 0..DEF_TABLE_RECORD_COUNT {
-                let mut buf = [0; u8::MAX as usize];
-                reader.read_exact(&mut buf[..1])?;
-                let len = buf[0] as usize;
-                if len == 0 {
-                    // reader.seek_relative(DEF_TABLE_ROW_LEN as i64 - 1)?;
-                    // continue;
-                    break;
-                }
-                reader.read_exact(&mut buf[..len])?;
-                let name = String::from_utf8_lossy(&buf[..len]).to_string();
-                reader.read_exact(&mut buf[..1])?;
-                let column_type = buf[0];
-                reader.read_exact(&mut buf[..2])?;
-                let size = u16::from_be_bytes(buf[..2].try_into().unwrap());
-                defs.push(ColumnDef {
-                    name,
-                    column_type,
-                    size,
+    /// The blob file, opened on first use.
+    ///
+    /// Opening scans every table with blob columns,
+    /// extents no row points to are free.
+    fn blob_file(&mut self) -> io::Result<&mut BlobFile> {
+        if self.blobs.is_none() {
+            if self.path.as_os_str().is_empty() {
+                return Err(io::Error::new(
+                    ErrorKind::Unsupported,
+                    "blob columns need a database file",
+                ));
+            }
+            let tables: Vec<_> = self
+                .header_table
+                .values()
+                .filter(|meta| !meta.blob_columns.is_empty())
+                .map(|meta| {
+                    let pages: Vec<i32> = meta.table_offsets.values().copied().collect();
+                    let rows_len =
+                        self.layout.rows_per_page(meta.row_len) as usize * meta.row_len as usize;
+                    let columns = meta.blob_columns.clone();
+                    (
+                        pages,
+                        meta.row_len as usize,
+                        rows_len,
+                        meta.delete_policy,
+                        columns,
+                    )
                 })
+                .collect();
+            let mut used = Vec::new();
+            let mut page_buf = vec![0; self.layout.page_size as usize];
+            for (pages, row_len, rows_len, delete_policy, blob_columns) in tables {
+                for table_offset in pages {
+                    self.read_page(table_offset, &mut page_buf)?;
+                    for buf in page_buf[..rows_len].chunks_exact(row_len.max(1)) {
+                        if is_empty_row(buf, delete_policy) {
+                            continue;
+                        }
+                        for range in &blob_columns {
+                            used.push(buf[range.clone()].try_into().unwrap());
+                        }
+                    }
+                }
             }
-            Ok(defs)
-        } else {
-            Err(io::Error::new(ErrorKind::Other, "table not found"))
+            let mut path = self.path.clone().into_os_string();
+            path.push(".blob");
+            self.blobs = Some(BlobFile::open(Path::new(&path), &used)?);
         }
+        Ok(self.blobs.as_mut().unwrap())
     }
 
-    /// Data can contain only one row.
-    pub fn insert(&mut self, table_name: &str, data: &[u8]) -> io::Result<()> {
-        let meta = check_table_exists(&self.header_table, table_name)?;
-        if data.len() != meta.row_len as usize {
-            return Err(io::Error::new(
-                ErrorKind::Other,
-                "data len mismatch: only one row at a time is accepted",
-            ));
+    /// Take a page, the most recently freed one if any,
+    /// else the one the allocator picks from `hint`.
+    ///
+    /// Spare pages are not kept on disk: the list is built from the pages
+    /// no table uses when the file is opened, and grows with every page
+    /// freed and every page the file grows by.
+    fn alloc_page(&mut self, hint: i32) -> io::Result<i32> {
+        let limit = self.options.max_pages.unwrap_or(i32::MAX);
+        while let Some(page) = self.free_pages.pop() {
+            // contiguous runs take pages without going through the list
+            if page < limit && self.in_use_pages.insert(page) {
+                return Ok(page);
+            }
         }
+        let page =
+            self.options
+                .allocator
+                .allocate(&self.in_use_pages, hint, self.options.max_pages)?;
+        self.grow_for(page)?;
+        self.in_use_pages.insert(page);
+        Ok(page)
+    }
 
-        let reader = &mut self.reader;
+    /// Make sure the file holds `page`,
+    /// growing it by [`DatabaseOptions::grow_pages`] pages at a time.
+    fn grow_for(&mut self, page: i32) -> io::Result<()> {
+        let end = self.layout.page_pos(page + 1);
+        if end <= self.file_len {
+            return Ok(());
+        }
+        // writes past the end may have grown it since
+        self.file_len = self.writer.get_ref().len()?;
+        if end <= self.file_len {
+            return Ok(());
+        }
+        let step = self.options.grow_pages.max(1) as u64 * self.layout.page_size as u64;
+        let limit = self
+            .layout
+            .page_pos(self.options.max_pages.unwrap_or(i32::MAX));
+        let len = (self.file_len + step).min(limit).max(end);
+        self.writer.get_ref().set_len(len)?;
+        let grown = (self.file_len / self.layout.page_size as u64) as i32
+            ..(len / self.layout.page_size as u64) as i32;
+        self.file_len = len;
+        // the rest of the step is handed out before the allocator is asked again
+        let spare = grown
+            .rev()
+            .filter(|&grown| grown != page && !self.in_use_pages.contains(&grown));
+        self.free_pages.extend(spare);
+        Ok(())
+    }
 
-        for record_offset in 0..META_TABLE_RECORD_COUNT {
-            let mut buf = [0; 4];
-            reader.seek(SeekFrom::Start(
-                meta.meta_offset as u64 * PAGE_SIZE as u64
-                    + record_offset as u64 * META_TABLE_ROW_LEN as u64,
-            ))?;
-            reader.read_exact(&mut buf)?;
-            let table_offset = i32::from_be_bytes(buf);
-            if table_offset == 0 {
-                let new_table = find_spare_page(&self.in_use_pages)?;
-                let writer = &mut self.writer;
-                writer.seek(SeekFrom::Start(reader.stream_position()? - 4))?;
-                writer.write_all(&new_table.to_be_bytes())?;
-                writer.seek(SeekFrom::Start(new_table as u64 * PAGE_SIZE as u64))?;
-                self.in_use_pages.insert(new_table);
-                writer.write_all(data)?;
-                writer.write_all(&vec![0; PAGE_SIZE as usize - data.len()])?;
-                writer.flush()?;
-                return Ok(());
-            }
+    /// Give a page back for [`Database::alloc_page`] to hand out next.
+    fn free_page(&mut self, page: i32) {
+        if self.in_use_pages.remove(&page) {
+            self.free_pages.push(page);
+        }
+    }
 
-            let mut table_record_offset_byte = 0;
-            let row_len = data.len();
-            reader.seek(SeekFrom::Start(table_offset as u64))?;
-            while table_record_offset_byte < PAGE_SIZE {
-                let mut buf = vec![0; data.len()];
-                reader.read_exact(&mut buf)?;
-                if buf.into_iter().all(|b| b == 0) {
-                    let writer = &mut self.writer;
-                    writer.seek(SeekFrom::Start(
-                        reader.stream_position()? - data.len() as u64,
-                    ))?;
-                    writer.write_all(data)?;
-                    writer.flush()?;
-                    return Ok(());
+    /// Cut the file after the last page any table uses.
+    fn shrink_tail(&mut self) -> io::Result<()> {
+        // the cut pages may still be needed by a rollback
+        if self.transaction.is_some() {
+            return Ok(());
+        }
+        let last_page = self
+            .header_table
+            .values()
+            .flat_map(|meta| {
+                [meta.col_def_offset, meta.meta_offset]
+                    .into_iter()
+                    .chain(meta.table_offsets.values().copied())
+                    .chain(meta.index_pages())
+            })
+            .max()
+            .unwrap_or(0);
+        let len = self.layout.page_pos(last_page + 1);
+        // dirty frames of the cut pages are written first, then forgotten
+        self.flush()?;
+        let file = self.writer.get_ref();
+        if file.len()? > len {
+            self.poisoned = true;
+            file.set_len(len)?;
+            self.file_len = len;
+            self.cache.invalidate_from(last_page + 1)?;
+            self.free_pages.retain(|&page| page <= last_page);
+            self.flush()?;
+            self.poisoned = false;
+        }
+        Ok(())
+    }
+
+    /// The columns in a def page, read through the page cache.
+    fn column_defs(&mut self, col_def_offset: i32) -> io::Result<Vec<ColumnDef<String>>> {
+        let mut page = vec![0; self.layout.page_size as usize];
+        self.read_page(col_def_offset, &mut page)?;
+        parse_column_defs(&page, self.layout)
+    }
+
+    /// In debug builds,
+    /// check that the cached row length still matches the def page.
+    ///
+    /// The def page is read around the cache, so the check leaves its statistics alone.
+    fn debug_check_row_len(&mut self, table_name: &str) -> io::Result<()> {
+        if cfg!(debug_assertions) {
+            if let Some(meta) = self.header_table.get(table_name) {
+                let data_len = meta.data_len();
+                let page = meta.col_def_offset;
+                let mut buf = vec![0; self.layout.page_size as usize];
+                match self.cache.peek(page) {
+                    Some(frame) => buf.copy_from_slice(frame),
+                    None => read_checked_page(&mut self.reader, self.layout, page, &mut buf)?,
                 }
-                table_record_offset_byte += row_len as u32;
+                let defs = parse_column_defs(&buf, self.layout)?;
+                let row_len: u16 = defs.iter().map(|def| def.size).sum();
+                debug_assert_eq!(data_len, row_len, "cached row_len of {table_name} is stale");
             }
         }
+        Ok(())
+    }
 
-        Err(io::Error::new(
-            ErrorKind::Other,
-            CreateTableError::StorageFull,
-        ))
+    fn check_poisoned(&self) -> io::Result<()> {
+        if self.poisoned {
+            Err(io::Error::other(StorageError::Poisoned))
+        } else {
+            Ok(())
+        }
     }
+}
 
-    pub fn select<T: AsRef<[u8]>>(
-        &mut self,
-        table_name: &str,
-        conditions: &[Condition<T>],
-    ) -> io::Result<Vec<Vec<u8>>> {
-        let meta = check_table_exists(&self.header_table, table_name)?;
-        let reader = &mut self.reader;
-        let mut res = Vec::new();
+/// Read the record layout from the first header record.
+fn read_layout(reader: &mut BufReader<impl Read + Seek>) -> io::Result<Layout> {
+    reader.rewind()?;
+    let mut record = [0; 7];
+    reader.read_exact(&mut record)?;
+    if record[0] == LAYOUT_MARKER {
+        let layout = Layout::new(record[1])
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "unknown record length"))?;
+        let layout = Layout {
+            checksums: record[2] & LAYOUT_CHECKSUMS != 0,
+            ..layout
+        };
+        // zero in databases from before the page size was stored
+        match u32::from_be_bytes(record[3..7].try_into().unwrap()) {
+            0 => Ok(layout),
+            page_size => layout
+                .with_page_size(page_size)
+                .map_err(|_| io::Error::new(ErrorKind::InvalidData, "unknown page size")),
+        }
+    } else {
+        Ok(Layout::DEFAULT)
+    }
+}
 
-        for record_number in 0..META_TABLE_RECORD_COUNT {
-            reader.seek(SeekFrom::Start(
-                meta.meta_offset as u64 * PAGE_SIZE as u64
-                    + record_number as u64 * META_TABLE_ROW_LEN as u64,
-            ))?;
-            let mut table_offset = [0; 4];
-            reader.read_exact(&mut table_offset)?;
-            let table_offset = i32::from_be_bytes(table_offset);
+/// Scan the header, meta and def tables from the start of the file.
+fn load_metadata(
+    reader: &mut BufReader<impl Read + Seek>,
+    layout: Layout,
+) -> io::Result<(HashMap<String, HeaderMeta>, HashSet<i32>)> {
+    let mut header_table = HashMap::new();
+    let mut in_use_pages = HashSet::from([0]);
+    let mut page_buf = vec![0; layout.page_size as usize];
+    read_checked_page(reader, layout, 0, &mut page_buf)?;
+    for header_record_offset in layout.first_table_record()..layout.record_count() {
+        let record_pos = header_record_offset as usize * layout.record_len as usize;
+        let record = &page_buf[record_pos..record_pos + layout.record_len as usize];
+        let table_name_len = record[0];
+        if table_name_len != 0 {
+            if table_name_len > layout.table_name_max_len() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "table name overflow",
+                ));
+            }
+            let table_name_len = table_name_len as usize;
+            // implicit transform
+            let name = String::from_utf8_lossy(&record[1..1 + table_name_len]);
+            let offsets = &record[1 + table_name_len..];
+            let col_def_offset = i32::from_be_bytes(offsets[..4].try_into().unwrap());
+            let meta_offset = i32::from_be_bytes(offsets[4..8].try_into().unwrap());
+            header_table.insert(
+                name.into_owned(),
+                HeaderMeta {
+                    col_def_offset,
+                    meta_offset,
+                    header_record_offset,
+                    table_offsets: BTreeMap::new(),
+                    row_len: 0,
+                    column_count: 0,
+                    blob_columns: Vec::new(),
+                    delete_policy: DeletePolicy::ZeroFill,
+                    alloc_hint: 0,
+                    free_slots: BTreeMap::new(),
+                    tombstone_pages: BTreeSet::new(),
+                    stats: None,
+                    previous_row_lens: Vec::new(),
+                    primary_key: None,
+                    sequence: None,
+                    last_sequence: 0,
+                    indexes: Vec::new(),
+                    index_page: 0,
+                    index_entry_pages: Vec::new(),
+                    indexes_saved: false,
+                },
+            );
+            in_use_pages.insert(col_def_offset);
+            in_use_pages.insert(meta_offset);
+        }
+    }
+    for HeaderMeta {
+        meta_offset,
+        table_offsets,
+        col_def_offset,
+        row_len,
+        column_count,
+        blob_columns: blob_ranges,
+        delete_policy,
+        alloc_hint,
+        primary_key,
+        sequence,
+        last_sequence,
+        indexes,
+        index_page,
+        index_entry_pages,
+        indexes_saved,
+        ..
+    } in header_table.values_mut()
+    {
+        read_checked_page(reader, layout, *meta_offset, &mut page_buf)?;
+        let records = page_buf.chunks_exact(META_TABLE_ROW_LEN as usize);
+        for (record_number, record) in (0..layout.meta_record_count()).zip(records) {
+            let table_offset = i32::from_be_bytes(record[..4].try_into().unwrap());
             if table_offset == 0 {
                 continue;
             }
+            in_use_pages.insert(table_offset);
+            table_offsets.insert(record_number, table_offset);
+        }
 
-            reader.seek(SeekFrom::Start(table_offset as u64 * PAGE_SIZE as u64))?;
-            let mut cursor = 0;
-            let mut buf = vec![0; meta.row_len as usize];
-            while cursor < PAGE_SIZE {
-                reader.read_exact(&mut buf)?;
-                if conditions
-                    .iter()
-                    .all(|c| buf[c.range.clone()].cmp(c.data.as_ref()) == c.ord)
-                {
-                    res.push(buf.clone());
-                }
-                cursor += meta.row_len as u32;
+        read_checked_page(reader, layout, *col_def_offset, &mut page_buf)?;
+        let defs = parse_column_defs(&page_buf, layout)?;
+        *row_len = defs.iter().map(|def| def.size).sum();
+        *column_count = defs.len() as u8;
+        *blob_ranges = blob_columns(&defs);
+
+        let option_pos = layout.table_option_record() as usize * layout.record_len as usize;
+        let option_record = &page_buf[option_pos..option_pos + layout.record_len as usize];
+        *delete_policy = DeletePolicy::try_from(option_record[1])?;
+        *row_len += flag_len(*delete_policy);
+        *alloc_hint = i32::from_be_bytes(option_record[2..6].try_into().unwrap());
+        if let Some(index) = option_record[6].checked_sub(1) {
+            if index as usize >= defs.len() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "primary key column out of range",
+                ));
             }
+            *primary_key = Some((index, column_range(&defs, index as usize)));
+        }
+        *index_page = i32::from_be_bytes(option_record[7..11].try_into().unwrap());
+        if let Some(index) = option_record[11].checked_sub(1) {
+            if index as usize >= defs.len() {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    "sequence column out of range",
+                ));
+            }
+            *sequence = Some((index, column_range(&defs, index as usize)));
+        }
+        *last_sequence = u64::from_be_bytes(option_record[12..20].try_into().unwrap());
+        if *index_page != 0 {
+            (*indexes, *index_entry_pages, *indexes_saved) =
+                index::read_indexes(reader, layout, *index_page)?;
+            in_use_pages.insert(*index_page);
+            in_use_pages.extend(index_entry_pages.iter().copied());
         }
-
-        Ok(res)
     }
 
-    pub fn select_pos(
-        &mut self,
-        table_name: &str,
-        data_table_page_offset: i32,
-        row_range: Range<i32>,
-    ) -> io::Result<Vec<Vec<u8>>> {
-        let meta = check_table_exists(&self.header_table, table_name)?;
-        let reader = &mut self.reader;
-
-        reader.seek(SeekFrom::Start(
-            data_table_page_offset as u64 * PAGE_SIZE as u64
-                + meta.row_len as u64 * row_range.start as u64,
-        ))?;
+    Ok((header_table, in_use_pages))
+}
 
-        let mut res = Vec::with_capacity((row_range.end - row_range.start) as usize);
+/// Read a whole page from the file,
+/// failing if the database keeps checksums and the page's does not match.
+fn read_checked_page(
+    reader: &mut BufReader<impl Read + Seek>,
+    layout: Layout,
+    page: i32,
+    buf: &mut [u8],
+) -> io::Result<()> {
+    reader.seek(SeekFrom::Start(layout.page_pos(page)))?;
+    reader.read_exact(buf)?;
+    if layout.checksums && !page_checksum_ok(buf) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("checksum mismatch in page {page}"),
+        ));
+    }
+    Ok(())
+}
 
-        for _ in row_range {
-            let mut r = vec![0; meta.row_len as usize];
-            reader.read_exact(&mut r)?;
-            res.push(r);
+/// The column defs stored in a def page.
+///
+/// Defs are contiguous from the first record,
+/// so the first empty record ends them.
+/// Writers keep it that way: column names may not be empty,
+/// and removing a column must move the later defs up.
+fn parse_column_defs(page: &[u8], layout: Layout) -> io::Result<Vec<ColumnDef<String>>> {
+    let mut defs = Vec::new();
+    for record in page
+        .chunks_exact(layout.record_len as usize)
+        .take(layout.table_option_record() as usize)
+    {
+        let len = record[0] as usize;
+        if len == 0 {
+            break;
         }
-
-        Ok(res)
+        if len > layout.column_name_max_len() as usize {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "column name overflows its def record",
+            ));
+        }
+        let name = String::from_utf8_lossy(&record[1..1 + len]).to_string();
+        let column_type = ColumnType::from_u8(record[1 + len]);
+        let size = u16::from_be_bytes(record[2 + len..4 + len].try_into().unwrap());
+        defs.push(ColumnDef {
+            name,
+            column_type,
+            size,
+        })
     }
+    Ok(defs)
+}
 
-    pub fn update<C: AsRef<[u8]>, N: AsRef<[u8]>>(
-        &mut self,
-        table_name: &str,
-        conditions: &[Condition<C>],
-        new_value: &[Condition<N>],
-    ) -> io::Result<usize> {
-        let meta = check_table_exists(&self.header_table, table_name)?;
-        let reader = &mut self.reader;
-        let mut res = 0;
+/// Byte range in a row of the column at `index`.
+fn column_range<T: AsRef<str>>(defs: &[ColumnDef<T>], index: usize) -> Range<usize> {
+    let start: usize = defs[..index].iter().map(|def| def.size as usize).sum();
+    start..start + defs[index].size as usize
+}
 
-        for record_number in 0..META_TABLE_RECORD_COUNT {
-            reader.seek(SeekFrom::Start(
-                meta.meta_offset as u64 * PAGE_SIZE as u64
-                    + record_number as u64 * META_TABLE_ROW_LEN as u64,
-            ))?;
-            let mut table_offset = [0; 4];
-            reader.read_exact(&mut table_offset)?;
-            let table_offset = i32::from_be_bytes(table_offset);
-            if table_offset == 0 {
-                continue;
-            }
+/// Byte ranges of the blob columns in a row.
+fn blob_columns<T: AsRef<str>>(defs: &[ColumnDef<T>]) -> Vec<Range<usize>> {
+    let mut start = 0;
+    let mut res = Vec::new();
+    for def in defs {
+        let range = start..start + def.size as usize;
+        start = range.end;
+        if def.column_type == ColumnType::Blob && def.size == BLOB_POINTER_LEN {
+            res.push(range);
+        }
+    }
+    res
+}
 
-            reader.seek(SeekFrom::Start(table_offset as u64 * PAGE_SIZE as u64))?;
-            let mut cursor = 0;
-            let mut buf = vec![0; meta.row_len as usize];
-            while cursor < PAGE_SIZE {
-                reader.read_exact(&mut buf)?;
-                if conditions
-                    .iter()
-                    .all(|c| buf[c.range.clone()].cmp(c.data.as_ref()) == c.ord)
-                {
-                    let writer = &mut self.writer;
-                    let start = reader.stream_position()? - meta.row_len as u64;
+/// Check that `data_len` bytes make one row of the table.
+fn check_row_len(meta: &HeaderMeta, data_len: usize) -> io::Result<()> {
+    if data_len != meta.data_len() as usize {
+        return Err(io::Error::other(DatabaseError::RowLenMismatch {
+            expected: meta.data_len(),
+            got: data_len,
+            schema_changed: meta
+                .previous_row_lens
+                .iter()
+                .any(|&len| len as usize == data_len),
+        }));
+    }
+    Ok(())
+}
 
-                    for field in new_value {
-                        writer.seek(SeekFrom::Start(start + field.range.start as u64))?;
-                        writer.write_all(field.data.as_ref())?;
-                    }
-                    res += 1;
-                }
-                cursor += meta.row_len as u32;
+/// Check that every condition compares a range of the row
+/// with a value of the same length.
+fn check_conditions<F: Filter>(conditions: &[F], row_len: u16) -> io::Result<()> {
+    for c in conditions {
+        c.check(row_len as usize)?;
+    }
+    Ok(())
+}
+
+/// CRC32 (IEEE) of `bytes`.
+fn crc32(bytes: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
             }
+            table[i] = crc;
+            i += 1;
         }
-        self.writer.flush()?;
+        table
+    };
 
-        Ok(res)
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc = TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
     }
+    !crc
+}
 
-    pub fn update_pos<T: AsRef<[u8]>>(
-        &mut self,
-        table_name: &str,
-        data_table_page_offset: i32,
-        row_range: Range<i32>,
-        data: T,
-    ) -> io::Result<()> {
-        let meta = check_table_exists(&self.header_table, table_name)?;
-        let writer = &mut self.writer;
+/// Store the checksum of the rest of a page in its last bytes.
+fn seal_page(page: &mut [u8]) {
+    let (body, tail) = page.split_at_mut(page.len() - CHECKSUM_LEN as usize);
+    tail.copy_from_slice(&crc32(body).to_be_bytes());
+}
 
-        writer.seek(SeekFrom::Start(
-            data_table_page_offset as u64 * PAGE_SIZE as u64
-                + meta.row_len as u64 * row_range.start as u64,
-        ))?;
+/// Whether the last bytes of a page hold the checksum of the rest.
+///
+/// Pages of zeros pass, the file may have them where nothing was written yet.
+fn page_checksum_ok(page: &[u8]) -> bool {
+    let (body, tail) = page.split_at(page.len() - CHECKSUM_LEN as usize);
+    tail == crc32(body).to_be_bytes() || page.iter().all(|b| *b == 0)
+}
 
-        for _ in row_range {
-            writer.write_all(data.as_ref())?;
-        }
-        Ok(())
+/// Continue a 64-bit FNV-1a hash with `bytes`.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
     }
+    hash
+}
 
-    pub fn delete<T: AsRef<[u8]>>(
-        &mut self,
-        table_name: &str,
-        conditions: &[Condition<T>],
-    ) -> io::Result<usize> {
-        let meta = check_table_exists(&self.header_table, table_name)?;
-        let reader = &mut self.reader;
-        let mut res = 0;
+/// Map a value to a number growing with its byte order,
+/// using its first 8 bytes.
+fn key_position(value: &[u8]) -> f64 {
+    let mut int = [0; 8];
+    let len = value.len().min(8);
+    int[..len].copy_from_slice(&value[..len]);
+    u64::from_be_bytes(int) as f64
+}
 
-        for record_number in 0..META_TABLE_RECORD_COUNT {
-            reader.seek(SeekFrom::Start(
-                meta.meta_offset as u64 * PAGE_SIZE as u64
-                    + record_number as u64 * META_TABLE_ROW_LEN as u64,
-            ))?;
-            let mut table_offset = [0; 4];
-            reader.read_exact(&mut table_offset)?;
-            let table_offset = i32::from_be_bytes(table_offset);
-            if table_offset == 0 {
-                continue;
-            }
+/// Encode a column def record, padded to the record length.
+fn def_record<T: AsRef<str>>(layout: Layout, def: &ColumnDef<T>) -> Vec<u8> {
+    let mut record = vec![0; layout.record_len as usize];
+    let name = def.name.as_ref();
+    let name_len = name.len();
+    record[0] = name_len as u8;
+    record[1..1 + name_len].copy_from_slice(name.as_bytes());
+    record[1 + name_len] = def.column_type.to_u8();
+    record[2 + name_len..4 + name_len].copy_from_slice(&def.size.to_be_bytes());
+    record
+}
 
-            reader.seek(SeekFrom::Start(table_offset as u64 * PAGE_SIZE as u64))?;
-            let mut cursor = 0;
-            let mut buf = vec![0; meta.row_len as usize];
-            let mut empty_page = true;
-            while cursor < PAGE_SIZE {
-                reader.read_exact(&mut buf)?;
-                if conditions
-                    .iter()
-                    .all(|c| buf[c.range.clone()].cmp(c.data.as_ref()) == c.ord)
-                {
-                    res += 1;
-                } else if buf.iter().any(|b| *b != 0) {
-                    empty_page = false;
-                }
-                cursor += meta.row_len as u32;
-            }
-            if empty_page {
-                let writer = &mut self.writer;
-                writer.seek(SeekFrom::Start(
-                    meta.meta_offset as u64 * PAGE_SIZE as u64
-                        + record_number as u64 * META_TABLE_ROW_LEN as u64,
-                ))?;
-                writer.write_all(&[0; META_TABLE_ROW_LEN as usize])?;
-                self.in_use_pages.remove(&table_offset);
-            }
+/// Encode a header table record.
+fn header_record(
+    layout: Layout,
+    table_name: &str,
+    col_def_offset: i32,
+    meta_offset: i32,
+) -> Vec<u8> {
+    let mut record = vec![0; layout.record_len as usize];
+    let name_len = table_name.len();
+    record[0] = name_len as u8;
+    record[1..1 + name_len].copy_from_slice(table_name.as_bytes());
+    record[1 + name_len..5 + name_len].copy_from_slice(&col_def_offset.to_be_bytes());
+    record[5 + name_len..9 + name_len].copy_from_slice(&meta_offset.to_be_bytes());
+    record
+}
+
+/// The header page of a new database.
+fn first_page(options: CreateOptions) -> io::Result<Vec<u8>> {
+    let layout = Layout {
+        checksums: options.checksums,
+        ..Layout::new(options.record_len)?
+    }
+    .with_page_size(options.page_size)?;
+    let mut page = vec![0; layout.page_size as usize];
+    if layout.first_table_record() > 0 {
+        let flags = if layout.checksums {
+            LAYOUT_CHECKSUMS
+        } else {
+            0
+        };
+        page[..3].copy_from_slice(&[LAYOUT_MARKER, layout.record_len, flags]);
+        page[3..7].copy_from_slice(&layout.page_size.to_be_bytes());
+    }
+    if layout.checksums {
+        seal_page(&mut page);
+    }
+    Ok(page)
+}
+
+/// Check a column def on its own, the same for new and altered tables.
+fn check_column_def<T: AsRef<str>>(layout: Layout, def: &ColumnDef<T>) -> io::Result<()> {
+    if def.name.as_ref().len() > layout.column_name_max_len() as usize {
+        return Err(io::Error::other(CreateTableError::ColumnNameTooLong));
+    }
+    // an empty name would end the defs early
+    if def.name.as_ref().is_empty() {
+        return Err(io::Error::other(CreateTableError::ColumnNameEmpty));
+    }
+    if def.column_type == ColumnType::Blob && def.size != BLOB_POINTER_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "blob columns must be BLOB_POINTER_LEN bytes",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a slot holds no row, under the delete policy of its table.
+fn is_empty_row(row: &[u8], delete_policy: DeletePolicy) -> bool {
+    match delete_policy {
+        DeletePolicy::PresenceFlag => row.last().is_none_or(|flag| *flag == 0),
+        _ => {
+            row.iter().all(|b| *b == 0)
+                || (delete_policy == DeletePolicy::Tombstone && row.iter().all(|b| *b == TOMBSTONE))
         }
-        self.writer.flush()?;
+    }
+}
 
-        Ok(res)
+/// Bytes stored after each row for the presence flag.
+fn flag_len(delete_policy: DeletePolicy) -> u16 {
+    (delete_policy == DeletePolicy::PresenceFlag) as u16
+}
+
+/// The row a stored slot holds, without the presence flag.
+fn row_data(slot: &[u8], delete_policy: DeletePolicy) -> &[u8] {
+    &slot[..slot.len() - flag_len(delete_policy) as usize]
+}
+
+/// Copy a row into a stored slot, setting the presence flag if any.
+fn store_row(slot: &mut [u8], data: &[u8], delete_policy: DeletePolicy) {
+    slot[..data.len()].copy_from_slice(data);
+    if delete_policy == DeletePolicy::PresenceFlag {
+        slot[data.len()] = 1;
     }
+}
 
-    pub fn delete_pos(
-        &mut self,
-        table_name: &str,
-        data_table_page_offset: i32,
-        row_range: Range<i32>,
-    ) -> io::Result<()> {
-        let meta = check_table_exists(&self.header_table, table_name)?;
-        let writer = &mut self.writer;
+/// The byte deleted rows are filled with.
+fn delete_fill(delete_policy: DeletePolicy) -> u8 {
+    match delete_policy {
+        DeletePolicy::ZeroFill => 0,
+        DeletePolicy::Tombstone => TOMBSTONE,
+        DeletePolicy::PresenceFlag => 0,
+    }
+}
 
-        writer.seek(SeekFrom::Start(
-            data_table_page_offset as u64 * PAGE_SIZE as u64
-                + meta.row_len as u64 * row_range.start as u64,
-        ))?;
+/// Append a column value to a columnar buffer,
+/// integers in native byte order.
+#[cfg(feature = "arrow")]
+fn decode_column_value(column_type: ColumnType, value: &[u8], column: &mut Vec<u8>) {
+    use table::{from_be_key_i32, from_be_key_i64};
 
-        for _ in row_range {
-            writer.write_all(&vec![0; meta.row_len as usize])?;
+    match (column_type, value.len()) {
+        (ColumnType::Int, 4) => {
+            column.extend_from_slice(&from_be_key_i32(value.try_into().unwrap()).to_ne_bytes())
         }
-        Ok(())
+        (ColumnType::Int, 8) => {
+            column.extend_from_slice(&from_be_key_i64(value.try_into().unwrap()).to_ne_bytes())
+        }
+        (ColumnType::UInt, 1 | 2 | 4 | 8) => {
+            let mut int = [0; 8];
+            int[8 - value.len()..].copy_from_slice(value);
+            let int = u64::from_be_bytes(int).to_ne_bytes();
+            if cfg!(target_endian = "big") {
+                column.extend_from_slice(&int[8 - value.len()..]);
+            } else {
+                column.extend_from_slice(&int[..value.len()]);
+            }
+        }
+        _ => column.extend_from_slice(value),
     }
 }
 
@@ -593,34 +4645,297 @@ fn check_table_exists<'h>(
     if let Some(meta) = header_table.get(table_name) {
         Ok(meta)
     } else {
-        Err(io::Error::new(ErrorKind::Other, "table not found"))
+        Err(io::Error::other(DatabaseError::TableNotFound))
     }
 }
 
-fn set_reader_to_head<T: Read + Seek>(reader: &mut BufReader<T>) -> io::Result<()> {
-    let pos = reader.stream_position()? as i64;
-    if pos < 0 {
-        reader.rewind()?;
+/// Find the first page not in use,
+/// looking from `start` to the end first and then from the beginning.
+fn find_spare_page(
+    in_use_pages: &HashSet<i32>,
+    start: i32,
+    max_pages: Option<i32>,
+) -> io::Result<i32> {
+    let limit = max_pages.unwrap_or(i32::MAX);
+    let start = start.clamp(0, limit);
+    if let Some(offset_page) = (start..limit)
+        .chain(0..start)
+        .find(|p| !in_use_pages.contains(p))
+    {
+        Ok(offset_page)
+    } else if limit == i32::MAX {
+        Err(io::Error::other(CreateTableError::StorageFull))
     } else {
-        reader.seek_relative(-pos)?;
+        Err(io::Error::other(StorageError::QuotaExceeded))
     }
-    Ok(())
 }
 
-fn find_spare_page(in_use_pages: &HashSet<i32>) -> io::Result<i32> {
-    let mut offset_page = 0;
-    while offset_page < i32::MAX {
-        if !in_use_pages.contains(&offset_page) {
-            break;
+/// Pages of the file not in use, the lowest last.
+fn spare_pages(in_use_pages: &HashSet<i32>, file_len: u64, layout: Layout) -> Vec<i32> {
+    let page_count = (file_len / layout.page_size as u64) as i32;
+    (0..page_count)
+        .rev()
+        .filter(|page| !in_use_pages.contains(page))
+        .collect()
+}
+
+/// Find the first run of `count` pages not in use.
+fn find_spare_run(in_use_pages: &HashSet<i32>, count: i32, max_pages: Option<i32>) -> Option<i32> {
+    let limit = max_pages.unwrap_or(i32::MAX);
+    let mut start = 0;
+    while start <= limit - count {
+        match (start..start + count).find(|p| in_use_pages.contains(p)) {
+            Some(used) => start = used + 1,
+            None => return Some(start),
         }
-        offset_page += 1;
     }
-    if offset_page == i32::MAX {
-        Err(io::Error::new(
-            ErrorKind::Other,
-            CreateTableError::StorageFull,
-        ))
-    } else {
-        Ok(offset_page)
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An in-memory database with an `items` table of 64 byte rows
+    /// keyed by their first 4 bytes, holding the keys `1..=count`.
+    fn items_db(count: u32) -> Database<MemoryStorage> {
+        let mut db = Database::open_in_memory().unwrap();
+        let defs = [
+            ColumnDef {
+                name: "id",
+                column_type: ColumnType::UInt,
+                size: 4,
+            },
+            ColumnDef {
+                name: "payload",
+                column_type: ColumnType::Bytes,
+                size: 60,
+            },
+        ];
+        db.create_table("items", &defs).unwrap();
+        let rows: Vec<u8> = (1..=count)
+            .flat_map(|id| {
+                let mut row = id.to_be_bytes().to_vec();
+                row.resize(64, id as u8);
+                row
+            })
+            .collect();
+        db.insert_many("items", &rows).unwrap();
+        db
+    }
+
+    fn keys(rows: &[Vec<u8>]) -> Vec<u32> {
+        rows.iter()
+            .map(|row| u32::from_be_bytes(row[..4].try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn rebuild_metadata_repairs_drifted_offsets() {
+        let mut db = items_db(200);
+        let pages = db.data_pages("items").unwrap();
+        let meta = db.header_table.get_mut("items").unwrap();
+        meta.table_offsets = BTreeMap::from([(0, 999)]);
+        meta.free_slots.clear();
+        db.in_use_pages.clear();
+        db.free_pages.clear();
+        assert_eq!(db.data_pages("items").unwrap(), [999]);
+
+        db.rebuild_metadata().unwrap();
+        assert_eq!(db.data_pages("items").unwrap(), pages);
+        let all: [Condition<&[u8]>; 0] = [];
+        assert_eq!(
+            keys(&db.select("items", &all).unwrap()),
+            (1..=200).collect::<Vec<_>>()
+        );
+        // delete visits the table's pages again
+        let few = [Condition::lt(0..4, 11u32.to_be_bytes())];
+        assert_eq!(db.delete("items", &few).unwrap(), 10);
+        // and new pages do not land on pages in use
+        db.create_table(
+            "more",
+            &[ColumnDef {
+                name: "n",
+                column_type: ColumnType::UInt,
+                size: 4,
+            }],
+        )
+        .unwrap();
+        db.insert("more", &[1; 4]).unwrap();
+        assert_eq!(
+            keys(&db.select("items", &all).unwrap()),
+            (11..=200).collect::<Vec<_>>()
+        );
+        assert!(db.check_integrity().unwrap().is_empty());
+    }
+
+    #[test]
+    fn recompute_row_len_fixes_a_desynced_cache() {
+        let mut db = items_db(100);
+        let all: [Condition<&[u8]>; 0] = [];
+        let rows = db.select("items", &all).unwrap();
+        db.header_table.get_mut("items").unwrap().row_len = 60;
+
+        assert_eq!(db.recompute_row_len("items").unwrap(), 64);
+        assert_eq!(db.select("items", &all).unwrap(), rows);
+        // the mismatch is reported as a schema change
+        let err = db.insert("items", &[1; 60]).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                DatabaseError::RowLenMismatch {
+                    expected: 64,
+                    got: 60,
+                    schema_changed: true,
+                }
+            ),
+            "{err:?}"
+        );
+        db.insert("items", &[1; 64]).unwrap();
+        assert_eq!(db.select("items", &all).unwrap().len(), 101);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "cached row_len of items is stale")]
+    fn debug_scans_catch_a_desynced_row_len() {
+        let mut db = items_db(10);
+        db.header_table.get_mut("items").unwrap().row_len = 60;
+        let _ = db.select::<Condition<&[u8]>>("items", &[]);
+    }
+
+    #[cfg(feature = "raw")]
+    #[test]
+    fn read_bytes_at_matches_read_page() {
+        let mut db = items_db(100);
+        let page = db.data_pages("items").unwrap()[1];
+        let mut buf = vec![0; db.layout.page_size as usize];
+        db.read_page(page, &mut buf).unwrap();
+
+        let pos = db.layout.page_pos(page);
+        assert_eq!(db.read_bytes_at(pos, buf.len()).unwrap(), buf);
+        // the third row of the page
+        assert_eq!(db.read_bytes_at(pos + 128, 64).unwrap(), buf[128..192]);
+        let file_len = db.reader.get_ref().len().unwrap();
+        assert!(db.read_bytes_at(file_len - 10, 11).is_err());
+        assert!(db.read_bytes_at(u64::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn dropped_pages_are_reused_lifo() {
+        let mut db = items_db(200);
+        let meta = &db.header_table["items"];
+        let mut freed = vec![meta.col_def_offset, meta.meta_offset];
+        freed.extend(meta.table_offsets.values());
+        assert_eq!(freed.len(), 6);
+        db.drop("items").unwrap();
+
+        let taken: Vec<i32> = (0..freed.len())
+            .map(|_| db.alloc_page(0).unwrap())
+            .collect();
+        freed.reverse();
+        assert_eq!(taken, freed);
+        // with the list empty, pages come from the allocator again
+        assert!(!taken.contains(&db.alloc_page(0).unwrap()));
+    }
+
+    #[test]
+    fn rebuild_metadata_writes_the_option_records_back() {
+        let mut db = items_db(10);
+        let def_page = db.header_table["items"].col_def_offset;
+        let option_pos = db.layout.def_pos(def_page, db.layout.table_option_record());
+        let mut before = vec![0; db.layout.page_size as usize];
+        db.read_page(def_page, &mut before).unwrap();
+        // a hint far past the end of the file
+        db.write_at(option_pos + 2, &i32::MAX.to_be_bytes())
+            .unwrap();
+        db.flush().unwrap();
+
+        db.rebuild_metadata().unwrap();
+        assert_eq!(db.header_table["items"].alloc_hint, 0);
+        assert!(db.cache.dirty_pages().is_empty());
+        db.cache.discard();
+        let mut after = vec![0; db.layout.page_size as usize];
+        db.read_page(def_page, &mut after).unwrap();
+        // the hint was zero to begin with, the rest is unchanged
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn rollback_restores_the_page_usage_and_tables() {
+        let mut db = items_db(100);
+        let (free_pages, in_use_pages) = (db.free_pages.clone(), db.in_use_pages.clone());
+        let tables: Vec<String> = db.header_table.keys().cloned().collect();
+
+        db.begin().unwrap();
+        let defs = [ColumnDef {
+            name: "n",
+            column_type: ColumnType::UInt,
+            size: 4,
+        }];
+        db.create_table("more", &defs).unwrap();
+        db.insert("more", &[1; 4]).unwrap();
+        db.drop("items").unwrap();
+        db.rollback().unwrap();
+
+        assert_eq!(db.free_pages, free_pages);
+        assert_eq!(db.in_use_pages, in_use_pages);
+        assert_eq!(db.header_table.keys().cloned().collect::<Vec<_>>(), tables);
+        let all: [Condition<&[u8]>; 0] = [];
+        assert_eq!(db.select("items", &all).unwrap().len(), 100);
+    }
+
+    #[test]
+    fn insert_many_forgets_the_slots_it_fills() {
+        let mut db = Database::open_in_memory().unwrap();
+        let defs = [ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        }];
+        let options = TableOptions {
+            delete_policy: DeletePolicy::Tombstone,
+            ..TableOptions::default()
+        };
+        db.create_table_with_options("ids", &defs, &options)
+            .unwrap();
+        let rows: Vec<u8> = (1..=10u32).flat_map(u32::to_be_bytes).collect();
+        db.insert_many("ids", &rows).unwrap();
+        let page = db.data_pages("ids").unwrap()[0];
+        let few = [Condition::lt(0..4, 6u32.to_be_bytes())];
+        assert_eq!(db.delete("ids", &few).unwrap(), 5);
+        assert!(db.header_table["ids"].tombstone_pages.contains(&page));
+
+        // the tombstones come before the empty slots of the page
+        db.insert_many("ids", &rows[..5 * 4]).unwrap();
+        let meta = &db.header_table["ids"];
+        assert!(meta.tombstone_pages.is_empty());
+        let per_page = db.layout.rows_per_page(meta.row_len);
+        assert_eq!(meta.free_slots.get(&page), Some(&(per_page - 10)));
+    }
+
+    #[test]
+    fn pages_written_around_dirty_frames_fail_the_flush() {
+        let mut db = items_db(10);
+        let page = db.data_pages("items").unwrap()[0];
+        let mut buf = vec![0; db.layout.page_size as usize];
+        db.read_page(page, &mut buf).unwrap();
+        buf[0] ^= 1;
+        db.write_page(page, &buf).unwrap();
+        db.writer
+            .seek(SeekFrom::Start(db.layout.page_pos(page)))
+            .unwrap();
+        db.writer.write_all(&[1]).unwrap();
+        // one of the two changes would be lost
+        assert!(db.flush().is_err());
+        // partial writes land in the frame instead
+        db.cache.discard();
+        db.read_page(page, &mut buf).unwrap();
+        db.write_page(page, &buf).unwrap();
+        db.write_at(db.layout.page_pos(page), &[7]).unwrap();
+        db.flush().unwrap();
+        db.cache.discard();
+        db.read_page(page, &mut buf).unwrap();
+        assert_eq!(buf[0], 7);
     }
 }
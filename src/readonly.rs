@@ -0,0 +1,71 @@
+//! A database handle without write access.
+
+use crate::{
+    table::{ColumnDef, DatabaseError, Filter},
+    Database, RowIterator,
+};
+
+/// A database opened by [`Database::open_readonly`],
+/// for files on read-only media or snapshots that must not change.
+///
+/// The file is only ever opened for reading,
+/// and no method here writes to it.
+///
+/// ```compile_fail
+/// # fn insert(db: &mut rustub_storage::ReadOnlyDatabase) {
+/// db.insert("items", &[1; 4]).unwrap();
+/// # }
+/// ```
+pub struct ReadOnlyDatabase {
+    db: Database,
+}
+
+impl ReadOnlyDatabase {
+    pub(crate) fn new(db: Database) -> Self {
+        ReadOnlyDatabase { db }
+    }
+
+    /// See [`Database::select`].
+    pub fn select<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.db.select(table_name, conditions)
+    }
+
+    /// See [`Database::select_iter`].
+    pub fn select_iter<'a, F: Filter>(
+        &'a mut self,
+        table_name: &str,
+        conditions: &'a [F],
+    ) -> Result<RowIterator<'a, F>, DatabaseError> {
+        self.db.select_iter(table_name, conditions)
+    }
+
+    /// See [`Database::count`].
+    pub fn count<F: Filter>(
+        &mut self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<usize, DatabaseError> {
+        self.db.count(table_name, conditions)
+    }
+
+    /// See [`Database::get_table_def`].
+    pub fn get_table_def(
+        &mut self,
+        table_name: &str,
+    ) -> Result<Vec<ColumnDef<String>>, DatabaseError> {
+        self.db.get_table_def(table_name)
+    }
+
+    /// See [`Database::list_tables`].
+    pub fn list_tables(&self) -> Vec<String> {
+        self.db.list_tables()
+    }
+
+    pub fn table_exists(&self, table_name: &str) -> bool {
+        self.db.table_exists(table_name)
+    }
+}
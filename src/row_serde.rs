@@ -0,0 +1,838 @@
+//! Rows from and to serde types, see [`encode_row`] and [`decode_row`].
+
+use std::{
+    fmt::{self, Display},
+    io::{self, ErrorKind},
+};
+
+use serde::{
+    de::{
+        self, value::SeqDeserializer, DeserializeOwned, DeserializeSeed, IntoDeserializer,
+        MapAccess, SeqAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{self, Impossible, Serialize},
+};
+
+use crate::table::{
+    be_key_i32, be_key_i64, from_be_key_i32, from_be_key_i64, ColumnDef, ColumnType,
+};
+
+/// Encode a value as a row of the columns `defs`, ready for [`crate::Database::insert`].
+///
+/// A struct fills the columns named like its fields,
+/// a tuple or a sequence fills the columns in order.
+/// Columns no field names are left zero.
+///
+/// Int columns of 4 or 8 bytes take signed and unsigned integers,
+/// encoded with [`be_key_i32`] or [`be_key_i64`],
+/// UInt columns of up to 8 bytes take integers that fit, big-endian.
+/// Text columns take strings and chars of up to their size, padded with zeros,
+/// Bool columns take bools,
+/// and other columns take byte sequences of exactly their size.
+/// Blob columns hold pointers into the blob file, so they cannot be encoded.
+///
+/// ```
+/// use rustub_storage::{
+///     encode_row,
+///     table::{be_key_i32, ColumnDef, ColumnType},
+/// };
+///
+/// let defs = [
+///     ColumnDef { name: "id", column_type: ColumnType::Int, size: 4 },
+///     ColumnDef { name: "name", column_type: ColumnType::Text, size: 4 },
+/// ];
+/// let row = encode_row(&defs, &(-3, "ab")).unwrap();
+/// assert_eq!(row[..4], be_key_i32(-3));
+/// assert_eq!(row[4..], *b"ab\0\0");
+/// ```
+pub fn encode_row<T: Serialize + ?Sized>(
+    defs: &[ColumnDef<impl AsRef<str>>],
+    value: &T,
+) -> io::Result<Vec<u8>> {
+    let len = defs.iter().map(|def| def.size as usize).sum();
+    let mut row = RowSerializer {
+        defs: &columns(defs),
+        row: vec![0; len],
+        next: 0,
+    };
+    value.serialize(&mut row).map_err(RowError::into_io)?;
+    Ok(row.row)
+}
+
+/// Decode a row of the columns `defs`, as returned by [`crate::Database::select`].
+///
+/// The columns are handed to structs by name and to tuples and sequences in order,
+/// decoded as [`encode_row`] encodes them.
+/// Text is read up to its first zero byte,
+/// and UInt columns of up to 8 bytes are read as `u64`.
+pub fn decode_row<T: DeserializeOwned>(
+    defs: &[ColumnDef<impl AsRef<str>>],
+    bytes: &[u8],
+) -> io::Result<T> {
+    let columns = columns(defs);
+    let len: usize = columns.iter().map(|column| column.range.len()).sum();
+    if bytes.len() != len {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("row of {} bytes, the columns take {len}", bytes.len()),
+        ));
+    }
+    T::deserialize(RowDeserializer {
+        columns: &columns,
+        row: bytes,
+    })
+    .map_err(RowError::into_io)
+}
+
+struct Column<'a> {
+    name: &'a str,
+    column_type: ColumnType,
+    range: std::ops::Range<usize>,
+}
+
+fn columns<T: AsRef<str>>(defs: &[ColumnDef<T>]) -> Vec<Column<'_>> {
+    let mut start = 0;
+    defs.iter()
+        .map(|def| {
+            let range = start..start + def.size as usize;
+            start = range.end;
+            Column {
+                name: def.name.as_ref(),
+                column_type: def.column_type,
+                range,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct RowError(String);
+
+impl RowError {
+    fn into_io(self) -> io::Error {
+        io::Error::new(ErrorKind::InvalidInput, self.0)
+    }
+}
+
+impl Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RowError {}
+
+impl ser::Error for RowError {
+    fn custom<T: Display>(msg: T) -> Self {
+        RowError(msg.to_string())
+    }
+}
+
+impl de::Error for RowError {
+    fn custom<T: Display>(msg: T) -> Self {
+        RowError(msg.to_string())
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, RowError> {
+    Err(RowError(format!("{what} cannot be encoded as a row")))
+}
+
+struct RowSerializer<'a> {
+    defs: &'a [Column<'a>],
+    row: Vec<u8>,
+    /// Column the next element of a tuple or sequence goes to.
+    next: usize,
+}
+
+impl RowSerializer<'_> {
+    fn column<T: Serialize + ?Sized>(&mut self, index: usize, value: &T) -> Result<(), RowError> {
+        let Some(column) = self.defs.get(index) else {
+            return Err(RowError(format!(
+                "more values than the {} columns",
+                self.defs.len()
+            )));
+        };
+        let value = value.serialize(ValueSerializer)?;
+        let slot = &mut self.row[column.range.clone()];
+        encode_value(column, value, slot)
+            .map_err(|message| RowError(format!("column {}: {message}", column.name)))
+    }
+
+    fn next_column<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RowError> {
+        self.next += 1;
+        self.column(self.next - 1, value)
+    }
+
+    fn named_column<T: Serialize + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<(), RowError> {
+        match self.defs.iter().position(|column| column.name == name) {
+            Some(index) => self.column(index, value),
+            None => Err(RowError(format!("no column named {name}"))),
+        }
+    }
+}
+
+impl ser::Serializer for &mut RowSerializer<'_> {
+    type Ok = ();
+    type Error = RowError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), RowError>;
+    type SerializeMap = Impossible<(), RowError>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), RowError>;
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, RowError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, RowError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, RowError> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, RowError> {
+        Ok(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), RowError> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<(), RowError> {
+        unsupported("a bool")
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<(), RowError> {
+        unsupported("an integer")
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), RowError> {
+        unsupported("a float")
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), RowError> {
+        unsupported("a float")
+    }
+
+    fn serialize_char(self, _v: char) -> Result<(), RowError> {
+        unsupported("a char")
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<(), RowError> {
+        unsupported("a string")
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), RowError> {
+        unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> Result<(), RowError> {
+        unsupported("an option")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<(), RowError> {
+        unsupported("an option")
+    }
+
+    fn serialize_unit(self) -> Result<(), RowError> {
+        unsupported("a unit")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), RowError> {
+        unsupported("a unit struct")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), RowError> {
+        unsupported("an enum")
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), RowError> {
+        unsupported("an enum")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, RowError> {
+        unsupported("an enum")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, RowError> {
+        unsupported("a map")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, RowError> {
+        unsupported("an enum")
+    }
+}
+
+impl ser::SerializeSeq for &mut RowSerializer<'_> {
+    type Ok = ();
+    type Error = RowError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RowError> {
+        self.next_column(value)
+    }
+
+    fn end(self) -> Result<(), RowError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut RowSerializer<'_> {
+    type Ok = ();
+    type Error = RowError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RowError> {
+        self.next_column(value)
+    }
+
+    fn end(self) -> Result<(), RowError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut RowSerializer<'_> {
+    type Ok = ();
+    type Error = RowError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RowError> {
+        self.next_column(value)
+    }
+
+    fn end(self) -> Result<(), RowError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut RowSerializer<'_> {
+    type Ok = ();
+    type Error = RowError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), RowError> {
+        self.named_column(key, value)
+    }
+
+    fn end(self) -> Result<(), RowError> {
+        Ok(())
+    }
+}
+
+/// A column value as serde hands it over.
+enum Value {
+    Int(i128),
+    Bool(bool),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+fn encode_value(column: &Column, value: Value, slot: &mut [u8]) -> Result<(), String> {
+    let len = slot.len();
+    match (column.column_type, value) {
+        (ColumnType::Int, Value::Int(int)) if len == 4 => {
+            let int = i32::try_from(int).map_err(|_| format!("{int} does not fit in an i32"))?;
+            slot.copy_from_slice(&be_key_i32(int));
+        }
+        (ColumnType::Int, Value::Int(int)) if len == 8 => {
+            let int = i64::try_from(int).map_err(|_| format!("{int} does not fit in an i64"))?;
+            slot.copy_from_slice(&be_key_i64(int));
+        }
+        (ColumnType::UInt, Value::Int(int)) if len <= 8 => {
+            let bytes = u64::try_from(int)
+                .map_err(|_| format!("{int} is negative or too large"))?
+                .to_be_bytes();
+            if bytes[..8 - len].iter().any(|b| *b != 0) {
+                return Err(format!("{int} does not fit in {len} bytes"));
+            }
+            slot.copy_from_slice(&bytes[8 - len..]);
+        }
+        (ColumnType::Text, Value::Text(text)) => {
+            if text.len() > len {
+                return Err(format!("text of {} bytes, at most {len} fit", text.len()));
+            }
+            slot[..text.len()].copy_from_slice(text.as_bytes());
+        }
+        (ColumnType::Bool, Value::Bool(set)) => {
+            if let Some(last) = slot.last_mut() {
+                *last = set as u8;
+            }
+        }
+        (ColumnType::Bytes | ColumnType::Unknown(_), Value::Bytes(bytes)) => {
+            if bytes.len() != len {
+                return Err(format!("{} bytes for a column of {len}", bytes.len()));
+            }
+            slot.copy_from_slice(&bytes);
+        }
+        (ColumnType::Blob, _) => return Err("blob columns cannot be encoded".to_owned()),
+        (column_type, _) => {
+            return Err(format!(
+                "value does not fit a {column_type} column of {len} bytes"
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Turns a field into a [`Value`].
+struct ValueSerializer;
+
+/// Collects the bytes of a sequence into a [`Value::Bytes`].
+struct BytesSerializer(Vec<u8>);
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = RowError;
+    type SerializeSeq = BytesSerializer;
+    type SerializeTuple = BytesSerializer;
+    type SerializeTupleStruct = Impossible<Value, RowError>;
+    type SerializeTupleVariant = Impossible<Value, RowError>;
+    type SerializeMap = Impossible<Value, RowError>;
+    type SerializeStruct = Impossible<Value, RowError>;
+    type SerializeStructVariant = Impossible<Value, RowError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, RowError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, RowError> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Value, RowError> {
+        unsupported("a float")
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Value, RowError> {
+        unsupported("a float")
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, RowError> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, RowError> {
+        Ok(Value::Text(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, RowError> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, RowError> {
+        unsupported("an option")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<Value, RowError> {
+        unsupported("an option")
+    }
+
+    fn serialize_unit(self) -> Result<Value, RowError> {
+        unsupported("a unit")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, RowError> {
+        unsupported("a unit struct")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Value, RowError> {
+        unsupported("an enum")
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, RowError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, RowError> {
+        unsupported("an enum")
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<BytesSerializer, RowError> {
+        Ok(BytesSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<BytesSerializer, RowError> {
+        Ok(BytesSerializer(Vec::with_capacity(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, RowError> {
+        unsupported("a nested tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, RowError> {
+        unsupported("an enum")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, RowError> {
+        unsupported("a map")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, RowError> {
+        unsupported("a nested struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, RowError> {
+        unsupported("an enum")
+    }
+}
+
+impl BytesSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RowError> {
+        match value.serialize(ValueSerializer)? {
+            Value::Int(byte) => match u8::try_from(byte) {
+                Ok(byte) => {
+                    self.0.push(byte);
+                    Ok(())
+                }
+                Err(_) => Err(RowError(format!("{byte} is not a byte"))),
+            },
+            _ => unsupported("a sequence of anything but bytes"),
+        }
+    }
+}
+
+impl ser::SerializeSeq for BytesSerializer {
+    type Ok = Value;
+    type Error = RowError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RowError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, RowError> {
+        Ok(Value::Bytes(self.0))
+    }
+}
+
+impl ser::SerializeTuple for BytesSerializer {
+    type Ok = Value;
+    type Error = RowError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), RowError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, RowError> {
+        Ok(Value::Bytes(self.0))
+    }
+}
+
+struct RowDeserializer<'a> {
+    columns: &'a [Column<'a>],
+    row: &'a [u8],
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer<'_> {
+    type Error = RowError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RowError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RowError> {
+        visitor.visit_seq(ColumnAccess {
+            columns: self.columns,
+            row: self.row,
+            next: 0,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, RowError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, RowError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RowError> {
+        visitor.visit_map(ColumnAccess {
+            columns: self.columns,
+            row: self.row,
+            next: 0,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, RowError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, RowError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct enum identifier ignored_any
+    }
+}
+
+/// The columns of a row, as a sequence or as a map by column name.
+struct ColumnAccess<'a> {
+    columns: &'a [Column<'a>],
+    row: &'a [u8],
+    next: usize,
+}
+
+impl ColumnAccess<'_> {
+    fn value_deserializer(&mut self) -> ValueDeserializer<'_> {
+        let column = &self.columns[self.next];
+        self.next += 1;
+        ValueDeserializer {
+            column,
+            value: &self.row[column.range.clone()],
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for ColumnAccess<'_> {
+    type Error = RowError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, RowError> {
+        if self.next == self.columns.len() {
+            return Ok(None);
+        }
+        seed.deserialize(self.value_deserializer()).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.columns.len() - self.next)
+    }
+}
+
+impl<'de> MapAccess<'de> for ColumnAccess<'_> {
+    type Error = RowError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, RowError> {
+        let Some(column) = self.columns.get(self.next) else {
+            return Ok(None);
+        };
+        seed.deserialize(column.name.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, RowError> {
+        seed.deserialize(self.value_deserializer())
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.columns.len() - self.next)
+    }
+}
+
+struct ValueDeserializer<'a> {
+    column: &'a Column<'a>,
+    value: &'a [u8],
+}
+
+impl ValueDeserializer<'_> {
+    fn error(&self, message: impl Display) -> RowError {
+        RowError(format!("column {}: {message}", self.column.name))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = RowError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RowError> {
+        let value = self.value;
+        match (self.column.column_type, value.len()) {
+            (ColumnType::Int, 4) => visitor.visit_i32(from_be_key_i32(value.try_into().unwrap())),
+            (ColumnType::Int, 8) => visitor.visit_i64(from_be_key_i64(value.try_into().unwrap())),
+            (ColumnType::UInt, 0..=8) => {
+                let mut int = [0; 8];
+                int[8 - value.len()..].copy_from_slice(value);
+                visitor.visit_u64(u64::from_be_bytes(int))
+            }
+            (ColumnType::Text, _) => {
+                let end = value.iter().position(|b| *b == 0).unwrap_or(value.len());
+                match std::str::from_utf8(&value[..end]) {
+                    Ok(text) => visitor.visit_string(text.to_owned()),
+                    Err(e) => Err(self.error(e)),
+                }
+            }
+            (ColumnType::Bool, _) => visitor.visit_bool(value.last().is_some_and(|b| *b != 0)),
+            (ColumnType::Bytes | ColumnType::Unknown(_), _) => {
+                visitor.visit_seq(SeqDeserializer::new(value.iter().copied()))
+            }
+            (column_type, len) => Err(self.error(format!(
+                "a {column_type} column of {len} bytes cannot be decoded"
+            ))),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RowError> {
+        match self.column.column_type {
+            ColumnType::Bytes | ColumnType::Unknown(_) => visitor.visit_bytes(self.value),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RowError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, RowError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        option unit unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
@@ -0,0 +1,92 @@
+//! A database handle that can be shared between threads.
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    table::{ColumnDef, Condition, DatabaseError, Filter},
+    Database,
+};
+
+/// A [`Database`] behind a mutex, cheap to clone and to send to other threads.
+///
+/// Every call takes the lock for its whole duration,
+/// so access is serialized, one operation at a time.
+/// Use [`SharedDatabase::lock`] for anything without a method here,
+/// or to run several operations without others in between.
+#[derive(Clone)]
+pub struct SharedDatabase {
+    inner: Arc<Mutex<Database>>,
+}
+
+impl SharedDatabase {
+    pub fn new(db: Database) -> Self {
+        SharedDatabase {
+            inner: Arc::new(Mutex::new(db)),
+        }
+    }
+
+    /// Open a database file, see [`Database::open`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DatabaseError> {
+        Database::open(path).map(SharedDatabase::new)
+    }
+
+    /// Wait for the lock and get the database.
+    ///
+    /// A thread panicking while holding the lock does not make it unusable,
+    /// a mutation cut short leaves the database poisoned on its own.
+    pub fn lock(&self) -> MutexGuard<'_, Database> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn create_table(
+        &self,
+        table_name: &str,
+        table_def: &[ColumnDef<impl AsRef<str>>],
+    ) -> Result<(), DatabaseError> {
+        self.lock().create_table(table_name, table_def)
+    }
+
+    pub fn drop(&self, table_name: &str) -> Result<(), DatabaseError> {
+        Database::drop(&mut self.lock(), table_name)
+    }
+
+    pub fn insert(&self, table_name: &str, data: &[u8]) -> Result<(), DatabaseError> {
+        self.lock().insert(table_name, data)
+    }
+
+    pub fn select<F: Filter>(
+        &self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        self.lock().select(table_name, conditions)
+    }
+
+    pub fn count<F: Filter>(
+        &self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<usize, DatabaseError> {
+        self.lock().count(table_name, conditions)
+    }
+
+    pub fn update<F: Filter, N: AsRef<[u8]>>(
+        &self,
+        table_name: &str,
+        conditions: &[F],
+        new_value: &[Condition<N>],
+    ) -> Result<usize, DatabaseError> {
+        self.lock().update(table_name, conditions, new_value)
+    }
+
+    pub fn delete<F: Filter>(
+        &self,
+        table_name: &str,
+        conditions: &[F],
+    ) -> Result<usize, DatabaseError> {
+        self.lock().delete(table_name, conditions)
+    }
+}
@@ -0,0 +1,751 @@
+//! Where the bytes of a database are kept.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+#[cfg(feature = "compression")]
+pub use crate::compressed::CompressedStorage;
+
+/// Handle to the bytes of a database.
+///
+/// A database holds several handles to the same bytes at once,
+/// made with [`Storage::try_clone`], each at its own position.
+pub trait Storage: Read + Write + Seek + Send + Sized {
+    /// Length of the bytes.
+    fn len(&self) -> io::Result<u64>;
+
+    /// Whether there are no bytes at all.
+    fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Cut the bytes to `len`, or extend them with zeros.
+    fn set_len(&self, len: u64) -> io::Result<()>;
+
+    /// Make the bytes written so far durable,
+    /// where that means anything.
+    fn sync_all(&self) -> io::Result<()>;
+
+    /// Another handle to the same bytes, with its own position.
+    fn try_clone(&self) -> io::Result<Self>;
+}
+
+/// Bytes in a block of [`HeldWrites`].
+const HELD_BLOCK: u64 = 4096;
+
+/// A database file, the storage of [`crate::Database`] unless told otherwise.
+///
+/// Each handle opens the file anew,
+/// so that handles never share a position.
+#[derive(Debug)]
+pub struct FileStorage {
+    file: File,
+    path: PathBuf,
+    writable: bool,
+    /// Writes kept in memory until [`Storage::sync_all`], shared by every handle.
+    held: Option<Arc<Mutex<HeldWrites>>>,
+    /// Position of the handle while writes are held,
+    /// the file's own position is used otherwise.
+    pos: u64,
+}
+
+/// The bytes of a file as written, over the bytes the file holds.
+#[derive(Debug)]
+struct HeldWrites {
+    /// Whole blocks written since the last sync, by block number.
+    blocks: BTreeMap<u64, Box<[u8]>>,
+    /// Length of the bytes as written.
+    len: u64,
+    /// Bytes of the file not cut since the last sync,
+    /// those past it read as zeros.
+    file_len: u64,
+}
+
+impl FileStorage {
+    /// Open an existing file, for writing too if `writable` is set.
+    pub fn open(path: impl AsRef<Path>, writable: bool) -> io::Result<Self> {
+        let path = path.as_ref();
+        // read as well, to update checksums
+        let file = File::options().read(true).write(writable).open(path)?;
+        Ok(FileStorage {
+            file,
+            path: path.to_path_buf(),
+            writable,
+            held: None,
+            pos: 0,
+        })
+    }
+
+    /// Keep every write of this handle and its clones in memory,
+    /// the file only changing on [`Storage::sync_all`].
+    pub(crate) fn hold_writes(mut self) -> io::Result<Self> {
+        let len = self.file.metadata()?.len();
+        self.held = Some(Arc::new(Mutex::new(HeldWrites {
+            blocks: BTreeMap::new(),
+            len,
+            file_len: len,
+        })));
+        Ok(self)
+    }
+}
+
+impl HeldWrites {
+    /// Read `buf.len()` bytes from `pos`, which must all be below `len`.
+    fn read_at(&self, file: &mut File, mut pos: u64, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            let (block, offset) = (pos / HELD_BLOCK, (pos % HELD_BLOCK) as usize);
+            let n = buf.len().min(HELD_BLOCK as usize - offset);
+            let (head, rest) = buf.split_at_mut(n);
+            match self.blocks.get(&block) {
+                Some(bytes) => head.copy_from_slice(&bytes[offset..offset + n]),
+                None => {
+                    let from_file = self.file_len.saturating_sub(pos).min(n as u64) as usize;
+                    if from_file > 0 {
+                        file.seek(SeekFrom::Start(pos))?;
+                        file.read_exact(&mut head[..from_file])?;
+                    }
+                    head[from_file..].fill(0);
+                }
+            }
+            pos += n as u64;
+            buf = rest;
+        }
+        Ok(())
+    }
+
+    fn write_at(&mut self, file: &mut File, mut pos: u64, mut buf: &[u8]) -> io::Result<()> {
+        let end = pos + buf.len() as u64;
+        while !buf.is_empty() {
+            let (block, offset) = (pos / HELD_BLOCK, (pos % HELD_BLOCK) as usize);
+            let n = buf.len().min(HELD_BLOCK as usize - offset);
+            if !self.blocks.contains_key(&block) {
+                let mut bytes = vec![0; HELD_BLOCK as usize].into_boxed_slice();
+                let start = block * HELD_BLOCK;
+                let known = self.len.saturating_sub(start).min(HELD_BLOCK) as usize;
+                self.read_at(file, start, &mut bytes[..known])?;
+                self.blocks.insert(block, bytes);
+            }
+            self.blocks.get_mut(&block).unwrap()[offset..offset + n].copy_from_slice(&buf[..n]);
+            pos += n as u64;
+            buf = &buf[n..];
+        }
+        self.len = self.len.max(end);
+        Ok(())
+    }
+
+    fn set_len(&mut self, len: u64) {
+        if len < self.len {
+            let kept_blocks = len.div_ceil(HELD_BLOCK);
+            self.blocks.split_off(&kept_blocks);
+            if let Some(bytes) = self.blocks.get_mut(&(len / HELD_BLOCK)) {
+                bytes[(len % HELD_BLOCK) as usize..].fill(0);
+            }
+            self.file_len = self.file_len.min(len);
+        }
+        self.len = len;
+    }
+
+    /// Write the held bytes to the file.
+    fn apply(&mut self, mut file: &File) -> io::Result<()> {
+        file.set_len(self.file_len)?;
+        for (&block, bytes) in &self.blocks {
+            let start = block * HELD_BLOCK;
+            let n = self.len.saturating_sub(start).min(HELD_BLOCK) as usize;
+            file.seek(SeekFrom::Start(start))?;
+            file.write_all(&bytes[..n])?;
+        }
+        file.set_len(self.len)?;
+        self.blocks.clear();
+        self.file_len = self.len;
+        Ok(())
+    }
+}
+
+fn lock_held(held: &Mutex<HeldWrites>) -> MutexGuard<'_, HeldWrites> {
+    held.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Read for FileStorage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(held) = &self.held else {
+            return self.file.read(buf);
+        };
+        let held = lock_held(held);
+        let n = held.len.saturating_sub(self.pos).min(buf.len() as u64) as usize;
+        held.read_at(&mut self.file, self.pos, &mut buf[..n])?;
+        drop(held);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for FileStorage {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(held) = &self.held else {
+            return self.file.write(buf);
+        };
+        lock_held(held).write_at(&mut self.file, self.pos, buf)?;
+        self.pos += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for FileStorage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let Some(held) = &self.held else {
+            return self.file.seek(pos);
+        };
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (lock_held(held).len, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "seek before the start",
+            )),
+        }
+    }
+}
+
+impl Storage for FileStorage {
+    fn len(&self) -> io::Result<u64> {
+        match &self.held {
+            Some(held) => Ok(lock_held(held).len),
+            None => Ok(self.file.metadata()?.len()),
+        }
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        match &self.held {
+            Some(held) => {
+                lock_held(held).set_len(len);
+                Ok(())
+            }
+            None => self.file.set_len(len),
+        }
+    }
+
+    /// Write the held writes, if any, then sync the file with [`File::sync_all`].
+    fn sync_all(&self) -> io::Result<()> {
+        if let Some(held) = &self.held {
+            lock_held(held).apply(&self.file)?;
+        }
+        self.file.sync_all()
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        let mut storage = FileStorage::open(&self.path, self.writable)?;
+        storage.held = self.held.clone();
+        Ok(storage)
+    }
+}
+
+/// Bytes in memory, gone once the last handle is dropped.
+///
+/// Handles cloned from one another share the bytes.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    bytes: Arc<Mutex<Vec<u8>>>,
+    pos: u64,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.bytes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Read for MemoryStorage {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes = self.lock();
+        let start = (self.pos as usize).min(bytes.len());
+        let n = buf.len().min(bytes.len() - start);
+        buf[..n].copy_from_slice(&bytes[start..start + n]);
+        drop(bytes);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for MemoryStorage {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut bytes = self.lock();
+        let start = self.pos as usize;
+        let end = start + buf.len();
+        if bytes.len() < end {
+            bytes.resize(end, 0);
+        }
+        bytes[start..end].copy_from_slice(buf);
+        drop(bytes);
+        self.pos = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryStorage {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => {
+                self.pos = pos;
+                return Ok(pos);
+            }
+            SeekFrom::End(offset) => (self.lock().len() as u64, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "seek before the start",
+            )),
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn len(&self) -> io::Result<u64> {
+        Ok(self.lock().len() as u64)
+    }
+
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        self.lock().resize(len as usize, 0);
+        Ok(())
+    }
+
+    fn sync_all(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(MemoryStorage {
+            bytes: Arc::clone(&self.bytes),
+            pos: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        env, fs, process,
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::{
+        first_page,
+        table::{ColumnDef, ColumnType, Condition, DatabaseError, StorageError},
+        CreateOptions, Database, DatabaseOptions, DurabilityMode, TrackedWriter,
+    };
+
+    /// Bytes in memory, counting seeks and the calls to [`Storage::sync_all`],
+    /// recording where reads and writes start
+    /// and failing writes while `failing` is set.
+    struct CountingStorage {
+        inner: MemoryStorage,
+        seeks: Arc<AtomicUsize>,
+        syncs: Arc<AtomicUsize>,
+        reads: Arc<Mutex<Vec<u64>>>,
+        writes: Arc<Mutex<Vec<u64>>>,
+        failing: Arc<AtomicBool>,
+    }
+
+    impl Read for CountingStorage {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let pos = self.inner.stream_position()?;
+            self.reads.lock().unwrap().push(pos);
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for CountingStorage {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.failing.load(Ordering::Relaxed) {
+                return Err(io::Error::other("injected failure"));
+            }
+            let pos = self.inner.stream_position()?;
+            self.writes.lock().unwrap().push(pos);
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    impl Seek for CountingStorage {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.seeks.fetch_add(1, Ordering::Relaxed);
+            self.inner.seek(pos)
+        }
+    }
+
+    impl Storage for CountingStorage {
+        fn len(&self) -> io::Result<u64> {
+            self.inner.len()
+        }
+
+        fn set_len(&self, len: u64) -> io::Result<()> {
+            self.inner.set_len(len)
+        }
+
+        fn sync_all(&self) -> io::Result<()> {
+            self.syncs.fetch_add(1, Ordering::Relaxed);
+            self.inner.sync_all()
+        }
+
+        fn try_clone(&self) -> io::Result<Self> {
+            Ok(CountingStorage {
+                inner: self.inner.try_clone()?,
+                seeks: Arc::clone(&self.seeks),
+                syncs: Arc::clone(&self.syncs),
+                reads: Arc::clone(&self.reads),
+                writes: Arc::clone(&self.writes),
+                failing: Arc::clone(&self.failing),
+            })
+        }
+    }
+
+    /// A database over a [`CountingStorage`] with an `items` table of 4 byte rows.
+    fn items_db(options: DatabaseOptions) -> (Database<CountingStorage>, CountingStorage) {
+        let mut inner = MemoryStorage::new();
+        inner
+            .write_all(&first_page(CreateOptions::default()).unwrap())
+            .unwrap();
+        let storage = CountingStorage {
+            inner,
+            seeks: Arc::new(AtomicUsize::new(0)),
+            syncs: Arc::new(AtomicUsize::new(0)),
+            reads: Arc::new(Mutex::new(Vec::new())),
+            writes: Arc::new(Mutex::new(Vec::new())),
+            failing: Arc::new(AtomicBool::new(false)),
+        };
+        let handle = storage.try_clone().unwrap();
+        let mut db = Database::from_storage(storage, PathBuf::new(), options).unwrap();
+        let def = [ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        }];
+        db.create_table("items", &def).unwrap();
+        (db, handle)
+    }
+
+    /// Syncs made by three inserts, then by a call to [`Database::sync`].
+    fn syncs_of(durability: DurabilityMode) -> (usize, usize) {
+        let options = DatabaseOptions {
+            durability,
+            ..DatabaseOptions::default()
+        };
+        let (mut db, handle) = items_db(options);
+        let syncs = handle.syncs;
+
+        let before = syncs.load(Ordering::Relaxed);
+        for id in 0..3u32 {
+            db.insert("items", &id.to_be_bytes()).unwrap();
+        }
+        let inserts = syncs.load(Ordering::Relaxed) - before;
+        db.sync().unwrap();
+        (inserts, syncs.load(Ordering::Relaxed) - before - inserts)
+    }
+
+    #[test]
+    fn fsync_syncs_after_every_mutation() {
+        for (durability, per_insert) in [
+            (DurabilityMode::Fsync, 3),
+            (DurabilityMode::FlushPerOp, 0),
+            (DurabilityMode::Manual, 0),
+        ] {
+            let (inserts, sync) = syncs_of(durability);
+            assert_eq!(inserts, per_insert, "{durability:?}");
+            assert!(sync > 0, "{durability:?}");
+        }
+    }
+
+    #[test]
+    fn a_failed_write_refuses_later_operations() {
+        let (mut db, handle) = items_db(DatabaseOptions::default());
+        db.insert("items", &1u32.to_be_bytes()).unwrap();
+
+        handle.failing.store(true, Ordering::Relaxed);
+        assert!(db.insert("items", &2u32.to_be_bytes()).is_err());
+        handle.failing.store(false, Ordering::Relaxed);
+
+        let refused = db.insert("items", &3u32.to_be_bytes()).unwrap_err();
+        assert!(matches!(
+            refused,
+            DatabaseError::Storage(StorageError::Poisoned)
+        ));
+        assert!(matches!(
+            db.select::<Condition<&[u8]>>("items", &[]),
+            Err(DatabaseError::Storage(StorageError::Poisoned))
+        ));
+    }
+
+    #[test]
+    fn delete_reads_only_the_pages_of_the_table() {
+        // without the cache every page looked at is read from storage
+        let (mut db, handle) = items_db(DatabaseOptions {
+            cache_pages: 0,
+            ..DatabaseOptions::default()
+        });
+        for id in 1..=10u32 {
+            db.insert("items", &id.to_be_bytes()).unwrap();
+        }
+        // move the reader away from the pages of items
+        let def = [ColumnDef {
+            name: "n",
+            column_type: ColumnType::UInt,
+            size: 4,
+        }];
+        for name in ["a", "b", "c"] {
+            db.create_table(name, &def).unwrap();
+            db.insert(name, &[1; 4]).unwrap();
+        }
+        let mut table_pages = db.data_pages("items").unwrap();
+        assert_eq!(table_pages.len(), 1);
+        // read by the row length check of debug builds
+        table_pages.push(db.header_table["items"].col_def_offset);
+
+        handle.reads.lock().unwrap().clear();
+        let deleted = db
+            .delete("items", &[Condition::lt(0..4, 6u32.to_be_bytes())])
+            .unwrap();
+        assert_eq!(deleted, 5);
+        let reads = handle.reads.lock().unwrap();
+        assert!(!reads.is_empty());
+        for pos in reads.iter() {
+            let page = (pos / crate::DEFAULT_PAGE_SIZE as u64) as i32;
+            assert!(table_pages.contains(&page), "read at {pos}");
+        }
+    }
+
+    #[test]
+    fn inserts_read_one_page_when_free_slots_are_known() {
+        // without the cache every page looked at is read from storage
+        let (mut db, handle) = items_db(DatabaseOptions {
+            cache_pages: 0,
+            ..DatabaseOptions::default()
+        });
+        // 1024 rows per page
+        let rows: Vec<u8> = (1..=4096u32).flat_map(u32::to_be_bytes).collect();
+        db.insert_many("items", &rows).unwrap();
+        let data_pages = db.data_pages("items").unwrap();
+        assert_eq!(data_pages.len(), 4);
+        db.delete("items", &[Condition::eq(0..4, 3000u32.to_be_bytes())])
+            .unwrap();
+
+        handle.reads.lock().unwrap().clear();
+        db.insert("items", &5000u32.to_be_bytes()).unwrap();
+        let page_size = crate::DEFAULT_PAGE_SIZE as u64;
+        let read_pages: HashSet<i32> = handle
+            .reads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|pos| (pos / page_size) as i32)
+            .filter(|page| data_pages.contains(page))
+            .collect();
+        assert_eq!(read_pages, HashSet::from([data_pages[2]]));
+    }
+
+    /// Writes to `page` while `insert` runs.
+    fn page_writes(
+        db: &mut Database<CountingStorage>,
+        handle: &CountingStorage,
+        page: i32,
+        insert: impl FnOnce(&mut Database<CountingStorage>),
+    ) -> usize {
+        handle.writes.lock().unwrap().clear();
+        insert(db);
+        let page_size = crate::DEFAULT_PAGE_SIZE as u64;
+        let writes = handle.writes.lock().unwrap();
+        writes
+            .iter()
+            .filter(|pos| (**pos / page_size) as i32 == page)
+            .count()
+    }
+
+    #[test]
+    fn bulk_inserts_write_a_page_once() {
+        let (mut db, handle) = items_db(DatabaseOptions::default());
+        db.insert("items", &1u32.to_be_bytes()).unwrap();
+        let page = db.data_pages("items").unwrap()[0];
+
+        let rows: Vec<u8> = (2..=11u32).flat_map(u32::to_be_bytes).collect();
+        let writes = page_writes(&mut db, &handle, page, |db| {
+            assert_eq!(db.insert_many("items", &rows).unwrap(), 10);
+        });
+        assert_eq!(writes, 1);
+        // one row at a time writes once per row
+        let writes = page_writes(&mut db, &handle, page, |db| {
+            for id in 12..=21u32 {
+                db.insert("items", &id.to_be_bytes()).unwrap();
+            }
+        });
+        assert_eq!(writes, 10);
+        assert_eq!(db.data_pages("items").unwrap(), [page]);
+        assert_eq!(
+            db.select::<Condition<&[u8]>>("items", &[]).unwrap().len(),
+            21
+        );
+    }
+
+    /// Pages of `items` read from storage by a select after a first one.
+    fn pages_read_again(cache_pages: usize) -> HashSet<i32> {
+        let options = DatabaseOptions {
+            cache_pages,
+            ..DatabaseOptions::default()
+        };
+        let (mut db, handle) = items_db(options);
+        let rows: Vec<u8> = (1..=3000u32).flat_map(u32::to_be_bytes).collect();
+        db.insert_many("items", &rows).unwrap();
+        let mut table_pages = db.data_pages("items").unwrap();
+        table_pages.push(db.header_table["items"].meta_offset);
+        let all: [Condition<&[u8]>; 0] = [];
+        assert_eq!(db.select("items", &all).unwrap().len(), 3000);
+
+        handle.reads.lock().unwrap().clear();
+        assert_eq!(db.select("items", &all).unwrap().len(), 3000);
+        let page_size = crate::DEFAULT_PAGE_SIZE as u64;
+        let reads = handle.reads.lock().unwrap();
+        reads
+            .iter()
+            .map(|pos| (pos / page_size) as i32)
+            .filter(|page| table_pages.contains(page))
+            .collect()
+    }
+
+    #[test]
+    fn repeated_selects_read_cached_pages_once() {
+        assert!(pages_read_again(64).is_empty());
+        // without the cache the meta page and the 3 data pages are read again
+        assert_eq!(pages_read_again(0).len(), 4);
+    }
+
+    #[test]
+    fn mutations_go_through_the_cache() {
+        let (mut db, handle) = items_db(DatabaseOptions::default());
+        let rows: Vec<u8> = (1..=3000u32).flat_map(u32::to_be_bytes).collect();
+        db.insert_many("items", &rows).unwrap();
+        let mut table_pages = db.data_pages("items").unwrap();
+        table_pages.push(db.header_table["items"].meta_offset);
+
+        handle.reads.lock().unwrap().clear();
+        let few = [Condition::lt(0..4, 11u32.to_be_bytes())];
+        db.update("items", &few, &[Condition::eq(0..4, 5000u32.to_be_bytes())])
+            .unwrap();
+        db.delete("items", &[Condition::eq(0..4, 2000u32.to_be_bytes())])
+            .unwrap();
+        db.insert("items", &6000u32.to_be_bytes()).unwrap();
+        let all: [Condition<&[u8]>; 0] = [];
+        assert_eq!(db.select("items", &all).unwrap().len(), 3000);
+        // the pages written by insert_many are still cached
+        let page_size = crate::DEFAULT_PAGE_SIZE as u64;
+        let reads = handle.reads.lock().unwrap();
+        assert!(reads
+            .iter()
+            .all(|pos| !table_pages.contains(&((pos / page_size) as i32))));
+    }
+
+    #[test]
+    fn sequential_writes_seek_once() {
+        let storage = CountingStorage {
+            inner: MemoryStorage::new(),
+            seeks: Arc::new(AtomicUsize::new(0)),
+            syncs: Arc::new(AtomicUsize::new(0)),
+            reads: Arc::new(Mutex::new(Vec::new())),
+            writes: Arc::new(Mutex::new(Vec::new())),
+            failing: Arc::new(AtomicBool::new(false)),
+        };
+        let seeks = Arc::clone(&storage.seeks);
+        let plain = storage.try_clone().unwrap();
+        let mut check = storage.try_clone().unwrap();
+
+        // rows written one after the other, flushed in between like inserts are
+        let mut writer = TrackedWriter::new(storage, crate::DEFAULT_PAGE_SIZE);
+        for row in 0..100u64 {
+            writer.seek(SeekFrom::Start(4096 + row * 16)).unwrap();
+            writer.write_all(&[row as u8 + 1; 16]).unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(seeks.swap(0, Ordering::Relaxed), 1);
+        let mut written = vec![0; 1600];
+        check.seek(SeekFrom::Start(4096)).unwrap();
+        check.read_exact(&mut written).unwrap();
+        assert!(written.chunks(16).zip(1..).all(|(row, n)| row == [n; 16]));
+        seeks.store(0, Ordering::Relaxed);
+
+        let mut writer = io::BufWriter::new(plain);
+        for row in 0..100u64 {
+            writer.seek(SeekFrom::Start(8192 + row * 16)).unwrap();
+            writer.write_all(&[row as u8 + 1; 16]).unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(seeks.load(Ordering::Relaxed), 100);
+    }
+
+    #[test]
+    fn held_writes_reach_the_file_on_sync() {
+        let path = env::temp_dir().join(format!("rustub-{}-held.db", process::id()));
+        fs::write(&path, [1; 6000]).unwrap();
+        let mut storage = FileStorage::open(&path, true)
+            .unwrap()
+            .hold_writes()
+            .unwrap();
+        let mut other = storage.try_clone().unwrap();
+
+        storage.seek(SeekFrom::Start(4090)).unwrap();
+        storage.write_all(&[2; 20]).unwrap();
+        storage.set_len(5000).unwrap();
+        storage.set_len(9000).unwrap();
+        storage.seek(SeekFrom::End(-10)).unwrap();
+        storage.write_all(&[3; 10]).unwrap();
+
+        let mut expected = vec![1; 5000];
+        expected[4090..4110].fill(2);
+        expected.resize(9000, 0);
+        expected[8990..].fill(3);
+        let mut read = Vec::new();
+        other.read_to_end(&mut read).unwrap();
+        assert_eq!(read, expected);
+        assert_eq!(fs::read(&path).unwrap(), [1; 6000]);
+
+        storage.sync_all().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected);
+        fs::remove_file(&path).unwrap();
+    }
+}
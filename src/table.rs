@@ -4,28 +4,290 @@ use std::{
     cmp::Ordering,
     error::Error,
     fmt::{Debug, Display},
+    io::{self, ErrorKind},
     ops::Range,
 };
 
+/// Column types known by the crate,
+/// stored as the `column_type` byte of a [`ColumnDef`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    /// Raw bytes, any content is accepted.
+    Bytes,
+    /// Signed integer encoded by [`be_key_i32`] or [`be_key_i64`].
+    Int,
+    /// Big-endian unsigned integer.
+    UInt,
+    /// UTF-8 text, padded with zeros.
+    Text,
+    /// Boolean, stored as big-endian 0 or 1.
+    Bool,
+    /// Bytes of any length kept in the blob file next to the database,
+    /// the column holds a [`BLOB_POINTER_LEN`] byte pointer to them.
+    Blob,
+    /// A byte not known by this version of the crate,
+    /// treated like [`ColumnType::Bytes`].
+    Unknown(u8),
+}
+
+/// Size of a [`ColumnType::Blob`] column.
+pub const BLOB_POINTER_LEN: u16 = 12;
+
 /// Column definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ColumnDef<T: AsRef<str>> {
     pub name: T,
-    pub column_type: u8,
+    pub column_type: ColumnType,
     pub size: u16,
 }
 
+impl ColumnType {
+    /// Decode a `column_type` byte.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => ColumnType::Bytes,
+            1 => ColumnType::Int,
+            2 => ColumnType::UInt,
+            3 => ColumnType::Text,
+            4 => ColumnType::Bool,
+            5 => ColumnType::Blob,
+            _ => ColumnType::Unknown(value),
+        }
+    }
+
+    /// Encode as the `column_type` byte.
+    pub fn to_u8(self) -> u8 {
+        match self {
+            ColumnType::Bytes => 0,
+            ColumnType::Int => 1,
+            ColumnType::UInt => 2,
+            ColumnType::Text => 3,
+            ColumnType::Bool => 4,
+            ColumnType::Blob => 5,
+            ColumnType::Unknown(value) => value,
+        }
+    }
+}
+
+impl From<u8> for ColumnType {
+    fn from(value: u8) -> Self {
+        ColumnType::from_u8(value)
+    }
+}
+
+impl From<ColumnType> for u8 {
+    fn from(value: ColumnType) -> Self {
+        value.to_u8()
+    }
+}
+
+impl Display for ColumnType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ColumnType::Bytes => "bytes",
+            ColumnType::Int => "int",
+            ColumnType::UInt => "uint",
+            ColumnType::Text => "text",
+            ColumnType::Bool => "bool",
+            ColumnType::Blob => "blob",
+            ColumnType::Unknown(value) => return write!(f, "{value}"),
+        })
+    }
+}
+
+/// How deleted rows are marked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum DeletePolicy {
+    /// Deleted rows are zeroed,
+    /// and pages left without rows are freed.
+    #[default]
+    ZeroFill = 0,
+    /// Deleted rows are filled with `0xFF`,
+    /// the slots are skipped by scans and reused by inserts
+    /// before the empty slots of any page.
+    Tombstone = 1,
+    /// Each row is stored with a trailing byte set to 1,
+    /// zeroed with the rest of the row on delete,
+    /// so that rows of zeros can be stored.
+    ///
+    /// The byte is not part of the rows given and returned.
+    PresenceFlag = 2,
+}
+
+/// Options of a table, fixed when the table is created.
+#[derive(Debug, Clone, Default)]
+pub struct TableOptions {
+    pub delete_policy: DeletePolicy,
+    /// Data pages allocated up front,
+    /// in one run right after the def and meta pages.
+    /// Later pages are looked for from the start of that run.
+    ///
+    /// If no such run is free, the table is created as usual.
+    /// Zero disables this.
+    pub contiguous_pages: u16,
+    /// Header slot of the table, instead of the first empty one,
+    /// so that the header page does not depend on creation history.
+    ///
+    /// It's an error if the slot is taken.
+    pub header_slot: Option<u8>,
+    /// Index of a column whose values must be unique among the rows,
+    /// checked by a full scan on insert.
+    ///
+    /// Updates are not checked. Blob columns cannot be the key.
+    pub primary_key: Option<u8>,
+    /// Index of an 8 byte unsigned integer column
+    /// that inserts fill with the next number of the table's insertion sequence,
+    /// starting at 1, whatever the row held there.
+    ///
+    /// The last number handed out is kept in the def page.
+    /// Updates may change the column like any other.
+    /// See [`crate::Database::select_since`].
+    pub sequence_column: Option<u8>,
+}
+
+impl TryFrom<u8> for DeletePolicy {
+    type Error = io::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(DeletePolicy::ZeroFill),
+            1 => Ok(DeletePolicy::Tombstone),
+            2 => Ok(DeletePolicy::PresenceFlag),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "unknown delete policy",
+            )),
+        }
+    }
+}
+
 /// Error type when creating table.
 #[derive(Debug)]
 pub enum CreateTableError {
     HeaderTableFull,
+    HeaderSlotTaken,
     TableExists,
     TableNameInvalid,
     StorageFull,
     ColumnNameTooLong,
+    ColumnNameEmpty,
     TooManyColumns,
     ColumnTooBig,
 }
 
+/// Error type when the database refuses an operation.
+#[derive(Debug)]
+pub enum StorageError {
+    /// A previous mutation failed after writing to disk,
+    /// reopen the database or roll back the open transaction to continue.
+    Poisoned,
+    /// The page limit set in the database options is reached.
+    QuotaExceeded,
+    /// All meta records of the table point to full pages,
+    /// though the file may still have room.
+    TableFull,
+    /// The rows selected so far exceed the byte budget of the query.
+    ResultTooLarge,
+    /// [`crate::Database::begin`] was called inside a transaction.
+    TransactionOpen,
+    /// Commit or rollback without a transaction open.
+    NoTransaction,
+}
+
+/// Error type of [`crate::Database`] methods.
+///
+/// Failures without a variant of their own,
+/// such as invalid arguments or corrupt pages,
+/// are kept as [`DatabaseError::Io`] with a describing [`io::ErrorKind`].
+#[derive(Debug)]
+pub enum DatabaseError {
+    TableNotFound,
+    /// An insert would give a row the primary key of another one,
+    /// see [`TableOptions::primary_key`].
+    DuplicateKey,
+    /// A row of `got` bytes for a table of `expected` bytes rows.
+    ///
+    /// `schema_changed` is set if `got` was the row length
+    /// before [`crate::Database::recompute_row_len`] changed it.
+    RowLenMismatch {
+        expected: u16,
+        got: usize,
+        schema_changed: bool,
+    },
+    /// A value of `got` bytes for a column of `expected` bytes.
+    ColumnLenMismatch {
+        column: String,
+        expected: u16,
+        got: usize,
+    },
+    /// CSV input that could not be imported, see [`crate::Database::import_csv`].
+    ///
+    /// `line` counts from 1, the header being line 1.
+    Csv {
+        line: usize,
+        message: String,
+    },
+    Create(CreateTableError),
+    Storage(StorageError),
+    Io(io::Error),
+}
+
+/// Summary of a table, see [`crate::Database::tables_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableInfo {
+    pub row_len: u16,
+    /// Data pages only.
+    pub page_count: usize,
+    pub column_count: u8,
+}
+
+/// Position of a row, as used by [`crate::Database::select_pos`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RowId {
+    /// The data page.
+    pub page: i32,
+    /// The row index within the page.
+    pub slot: u32,
+}
+
+/// Result of [`crate::Database::insert_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InsertReport {
+    pub row_id: RowId,
+    /// Whether a data page was allocated for the row.
+    pub allocated_new_page: bool,
+}
+
+/// Statistics of a table, see [`crate::Database::analyze_table`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableStats {
+    pub rows: usize,
+    /// One entry per column, in def order.
+    pub columns: Vec<ColumnStats>,
+}
+
+/// Statistics of a column, over the rows present when analyzed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnStats {
+    /// Smallest value, in byte order.
+    pub min: Vec<u8>,
+    /// Largest value, in byte order.
+    pub max: Vec<u8>,
+    /// Number of different values.
+    pub distinct: usize,
+}
+
+/// Result of [`crate::Database::update_explain`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateReport {
+    /// Rows matching all conditions, and so updated.
+    pub updated: usize,
+    /// Rows each condition matched on its own,
+    /// in the order the conditions were given.
+    pub matched: Vec<usize>,
+}
+
 pub struct Condition<T: AsRef<[u8]>> {
     pub range: Range<usize>,
     pub data: T,
@@ -33,6 +295,328 @@ pub struct Condition<T: AsRef<[u8]>> {
     pub ord: Ordering,
 }
 
+impl<T: AsRef<[u8]>> Condition<T> {
+    /// Accept rows whose bytes in `range` equal `data`.
+    pub fn eq(range: Range<usize>, data: T) -> Self {
+        Condition {
+            range,
+            data,
+            ord: Ordering::Equal,
+        }
+    }
+
+    /// Accept rows whose bytes in `range` are less than `data`.
+    pub fn lt(range: Range<usize>, data: T) -> Self {
+        Condition {
+            range,
+            data,
+            ord: Ordering::Less,
+        }
+    }
+
+    /// Accept rows whose bytes in `range` are greater than `data`.
+    pub fn gt(range: Range<usize>, data: T) -> Self {
+        Condition {
+            range,
+            data,
+            ord: Ordering::Greater,
+        }
+    }
+}
+
+/// Accept rows whose bytes in `range` lie between `low` and `high`,
+/// compared in byte order.
+pub struct RangeCondition<T: AsRef<[u8]>> {
+    pub range: Range<usize>,
+    pub low: T,
+    pub high: T,
+    /// Whether rows equal to `low` or `high` are accepted.
+    pub inclusive: bool,
+}
+
+impl<T: AsRef<[u8]>> RangeCondition<T> {
+    /// Accept rows with `low <= bytes <= high`.
+    pub fn between(range: Range<usize>, low: T, high: T) -> Self {
+        RangeCondition {
+            range,
+            low,
+            high,
+            inclusive: true,
+        }
+    }
+}
+
+/// Test applied to the bytes of a row by scans.
+///
+/// Implemented by [`Condition`] and [`RangeCondition`],
+/// `&dyn Filter` mixes both in one slice.
+pub trait Filter {
+    /// Whether the row is accepted.
+    fn matches(&self, row: &[u8]) -> bool;
+
+    /// Check that the filter only reads bytes of a `row_len` bytes row
+    /// and compares them with values of the same length.
+    fn check(&self, row_len: usize) -> io::Result<()>;
+
+    /// A range and the bytes every accepted row has there, if known,
+    /// so that an index on the range can be used.
+    fn equality(&self) -> Option<(Range<usize>, &[u8])> {
+        None
+    }
+}
+
+impl<T: AsRef<[u8]>> Filter for Condition<T> {
+    fn matches(&self, row: &[u8]) -> bool {
+        row[self.range.clone()].cmp(self.data.as_ref()) == self.ord
+    }
+
+    fn check(&self, row_len: usize) -> io::Result<()> {
+        check_range(&self.range, row_len)?;
+        check_value_len(&self.range, self.data.as_ref())
+    }
+
+    fn equality(&self) -> Option<(Range<usize>, &[u8])> {
+        (self.ord == Ordering::Equal).then(|| (self.range.clone(), self.data.as_ref()))
+    }
+}
+
+impl<T: AsRef<[u8]>> Filter for RangeCondition<T> {
+    fn matches(&self, row: &[u8]) -> bool {
+        let value = &row[self.range.clone()];
+        let (low, high) = (value.cmp(self.low.as_ref()), value.cmp(self.high.as_ref()));
+        if self.inclusive {
+            low.is_ge() && high.is_le()
+        } else {
+            low.is_gt() && high.is_lt()
+        }
+    }
+
+    fn check(&self, row_len: usize) -> io::Result<()> {
+        check_range(&self.range, row_len)?;
+        check_value_len(&self.range, self.low.as_ref())?;
+        check_value_len(&self.range, self.high.as_ref())
+    }
+}
+
+/// Boolean combination of conditions.
+pub enum Predicate<T: AsRef<[u8]>> {
+    /// Accept rows accepted by every predicate, or any row if empty.
+    And(Vec<Predicate<T>>),
+    /// Accept rows accepted by some predicate, or no row if empty.
+    Or(Vec<Predicate<T>>),
+    Leaf(Condition<T>),
+}
+
+impl<T: AsRef<[u8]>> Predicate<T> {
+    /// Whether the row is accepted.
+    pub fn evaluate(&self, row: &[u8]) -> bool {
+        match self {
+            Predicate::And(predicates) => predicates.iter().all(|p| p.evaluate(row)),
+            Predicate::Or(predicates) => predicates.iter().any(|p| p.evaluate(row)),
+            Predicate::Leaf(condition) => condition.matches(row),
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Filter for Predicate<T> {
+    fn matches(&self, row: &[u8]) -> bool {
+        self.evaluate(row)
+    }
+
+    fn check(&self, row_len: usize) -> io::Result<()> {
+        match self {
+            Predicate::And(predicates) | Predicate::Or(predicates) => {
+                predicates.iter().try_for_each(|p| p.check(row_len))
+            }
+            Predicate::Leaf(condition) => condition.check(row_len),
+        }
+    }
+
+    fn equality(&self) -> Option<(Range<usize>, &[u8])> {
+        match self {
+            Predicate::And(predicates) => predicates.iter().find_map(|p| p.equality()),
+            Predicate::Or(_) => None,
+            Predicate::Leaf(condition) => condition.equality(),
+        }
+    }
+}
+
+impl<F: Filter + ?Sized> Filter for &F {
+    fn matches(&self, row: &[u8]) -> bool {
+        (**self).matches(row)
+    }
+
+    fn check(&self, row_len: usize) -> io::Result<()> {
+        (**self).check(row_len)
+    }
+
+    fn equality(&self) -> Option<(Range<usize>, &[u8])> {
+        (**self).equality()
+    }
+}
+
+fn check_range(range: &Range<usize>, row_len: usize) -> io::Result<()> {
+    if range.start > range.end || range.end > row_len {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("condition range {:?} out of row", range),
+        ));
+    }
+    Ok(())
+}
+
+fn check_value_len(range: &Range<usize>, value: &[u8]) -> io::Result<()> {
+    if value.len() != range.len() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "condition value of {} bytes for range {:?}",
+                value.len(),
+                range
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Encode an `i32` so that byte order matches numeric order.
+///
+/// The sign bit is flipped before the big-endian encoding,
+/// otherwise negative values would compare greater than positive ones.
+///
+/// ```
+/// use rustub_storage::{
+///     table::{be_key_i32, ColumnDef, ColumnType, Condition},
+///     Database,
+/// };
+///
+/// let mut db = Database::open_in_memory()?;
+/// let defs = [ColumnDef { name: "id", column_type: ColumnType::Int, size: 4 }];
+/// db.create_table("users", &defs)?;
+/// for id in [-200, 5, 100, 101, 4000] {
+///     db.insert("users", &be_key_i32(id))?;
+/// }
+///
+/// let id_range = 0..4;
+/// let rows = db.select("users", &[Condition::gt(id_range, &be_key_i32(100))])?;
+/// assert_eq!(rows, [be_key_i32(101), be_key_i32(4000)]);
+/// # Ok::<(), rustub_storage::table::DatabaseError>(())
+/// ```
+pub fn be_key_i32(v: i32) -> [u8; 4] {
+    ((v as u32) ^ (1 << 31)).to_be_bytes()
+}
+
+/// Decode bytes produced by [`be_key_i32`].
+///
+/// ```
+/// use rustub_storage::table::{be_key_i32, from_be_key_i32};
+///
+/// assert_eq!(from_be_key_i32(be_key_i32(-100)), -100);
+/// assert_eq!(from_be_key_i32([0x80, 0, 0, 1]), 1);
+/// ```
+pub fn from_be_key_i32(key: [u8; 4]) -> i32 {
+    (u32::from_be_bytes(key) ^ (1 << 31)) as i32
+}
+
+/// Encode an `i64` so that byte order matches numeric order.
+///
+/// See [`be_key_i32`].
+///
+/// ```
+/// use rustub_storage::table::{be_key_i64, Condition};
+///
+/// assert!(be_key_i64(-1) < be_key_i64(0));
+/// let before_epoch = Condition::lt(8..16, be_key_i64(0));
+/// # let _ = before_epoch;
+/// ```
+pub fn be_key_i64(v: i64) -> [u8; 8] {
+    ((v as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+/// Decode bytes produced by [`be_key_i64`].
+///
+/// ```
+/// use rustub_storage::table::{be_key_i64, from_be_key_i64};
+///
+/// assert_eq!(from_be_key_i64(be_key_i64(i64::MIN)), i64::MIN);
+/// ```
+pub fn from_be_key_i64(key: [u8; 8]) -> i64 {
+    (u64::from_be_bytes(key) ^ (1 << 63)) as i64
+}
+
+/// Encode a `u32`, this is just `to_be_bytes`.
+///
+/// ```
+/// use rustub_storage::table::{be_key_u32, Condition};
+///
+/// assert!(be_key_u32(255) < be_key_u32(256));
+/// let after = Condition::gt(0..4, be_key_u32(100));
+/// # let _ = after;
+/// ```
+pub fn be_key_u32(v: u32) -> [u8; 4] {
+    v.to_be_bytes()
+}
+
+/// Decode bytes produced by [`be_key_u32`].
+///
+/// ```
+/// use rustub_storage::table::{be_key_u32, from_be_key_u32};
+///
+/// assert_eq!(from_be_key_u32(be_key_u32(70_000)), 70_000);
+/// ```
+pub fn from_be_key_u32(key: [u8; 4]) -> u32 {
+    u32::from_be_bytes(key)
+}
+
+/// Encode a `u64`, this is just `to_be_bytes`.
+///
+/// ```
+/// use rustub_storage::{
+///     table::{be_key_u64, ColumnDef, ColumnType, Condition},
+///     Database,
+/// };
+///
+/// let mut db = Database::open_in_memory()?;
+/// let defs = [ColumnDef { name: "seq", column_type: ColumnType::UInt, size: 8 }];
+/// db.create_table("events", &defs)?;
+/// for seq in [1, 255, 256, 1 << 40] {
+///     db.insert("events", &be_key_u64(seq))?;
+/// }
+///
+/// let rows = db.select("events", &[Condition::gt(0..8, &be_key_u64(255))])?;
+/// assert_eq!(rows, [be_key_u64(256), be_key_u64(1 << 40)]);
+/// # Ok::<(), rustub_storage::table::DatabaseError>(())
+/// ```
+pub fn be_key_u64(v: u64) -> [u8; 8] {
+    v.to_be_bytes()
+}
+
+/// Decode bytes produced by [`be_key_u64`].
+///
+/// ```
+/// use rustub_storage::table::{be_key_u64, from_be_key_u64};
+///
+/// assert_eq!(from_be_key_u64(be_key_u64(u64::MAX)), u64::MAX);
+/// ```
+pub fn from_be_key_u64(key: [u8; 8]) -> u64 {
+    u64::from_be_bytes(key)
+}
+
+/// The value a column of `column_type` holds when none is given.
+///
+/// This is zero for numbers, false for bools and empty for text and bytes.
+/// Signed integers are encoded as by [`be_key_i32`],
+/// so their zero starts with `0x80`.
+pub fn default_bytes(column_type: ColumnType, size: u16) -> Vec<u8> {
+    let mut value = vec![0; size as usize];
+    if column_type == ColumnType::Int {
+        if let Some(first) = value.first_mut() {
+            *first = 0x80;
+        }
+    }
+    value
+}
+
 impl Display for CreateTableError {
     /// This is just Debug::fmt now.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -42,3 +626,96 @@ impl Display for CreateTableError {
 
 /// Empty impl.
 impl Error for CreateTableError {}
+
+impl Display for StorageError {
+    /// This is just Debug::fmt now.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self, f)
+    }
+}
+
+/// Empty impl.
+impl Error for StorageError {}
+
+impl Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseError::TableNotFound => write!(f, "table not found"),
+            DatabaseError::DuplicateKey => write!(f, "duplicate primary key"),
+            DatabaseError::RowLenMismatch {
+                expected,
+                got,
+                schema_changed,
+            } => {
+                write!(f, "row of {got} bytes for a table of {expected} bytes rows")?;
+                if *schema_changed {
+                    write!(f, ", the table schema changed")?;
+                }
+                Ok(())
+            }
+            DatabaseError::ColumnLenMismatch {
+                column,
+                expected,
+                got,
+            } => write!(
+                f,
+                "value of {got} bytes for column {column} of {expected} bytes"
+            ),
+            DatabaseError::Csv { line, message } => write!(f, "CSV line {line}: {message}"),
+            DatabaseError::Create(e) => Display::fmt(e, f),
+            DatabaseError::Storage(e) => Display::fmt(e, f),
+            DatabaseError::Io(e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for DatabaseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DatabaseError::Create(e) => Some(e),
+            DatabaseError::Storage(e) => Some(e),
+            DatabaseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Unwraps the typed errors the crate puts in [`io::Error`]s.
+impl From<io::Error> for DatabaseError {
+    fn from(e: io::Error) -> Self {
+        if e.get_ref().is_some_and(|inner| inner.is::<DatabaseError>()) {
+            return *e.into_inner().unwrap().downcast().unwrap();
+        }
+        if e.get_ref()
+            .is_some_and(|inner| inner.is::<CreateTableError>())
+        {
+            return DatabaseError::Create(*e.into_inner().unwrap().downcast().unwrap());
+        }
+        if e.get_ref().is_some_and(|inner| inner.is::<StorageError>()) {
+            return DatabaseError::Storage(*e.into_inner().unwrap().downcast().unwrap());
+        }
+        DatabaseError::Io(e)
+    }
+}
+
+/// Wraps the error, so that converting back gives the same error.
+impl From<DatabaseError> for io::Error {
+    fn from(e: DatabaseError) -> Self {
+        match e {
+            DatabaseError::Io(e) => e,
+            e => io::Error::other(e),
+        }
+    }
+}
+
+impl From<CreateTableError> for DatabaseError {
+    fn from(e: CreateTableError) -> Self {
+        DatabaseError::Create(e)
+    }
+}
+
+impl From<StorageError> for DatabaseError {
+    fn from(e: StorageError) -> Self {
+        DatabaseError::Storage(e)
+    }
+}
@@ -0,0 +1,167 @@
+//! Write-ahead log of whole pages, next to the database file.
+//!
+//! Records are `[page i32][commit u8][page bytes]`,
+//! the last record of each unit has the commit byte set.
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{cache::Frame, seal_page};
+
+// [page i32][commit u8] before the page bytes
+const RECORD_HEADER_LEN: usize = 5;
+
+/// Pages written since the last commit,
+/// kept in memory until they are logged.
+pub(crate) struct Wal {
+    log: File,
+    // reads the parts of pages not written to
+    source: File,
+    pending: BTreeMap<i32, Frame>,
+    page_size: u64,
+}
+
+impl Wal {
+    pub(crate) fn open(db_path: &Path, page_size: u32) -> io::Result<Self> {
+        let log = File::options()
+            .append(true)
+            .create(true)
+            .open(wal_path(db_path))?;
+        Ok(Wal {
+            log,
+            source: File::open(db_path)?,
+            pending: BTreeMap::new(),
+            page_size: page_size as u64,
+        })
+    }
+
+    /// Copy `buf` into the pending pages from byte `pos` of the database file on.
+    pub(crate) fn write_at(&mut self, pos: u64, buf: &[u8]) -> io::Result<()> {
+        let mut pos = pos;
+        let mut buf = buf;
+        while !buf.is_empty() {
+            let page = (pos / self.page_size) as i32;
+            let start = (pos % self.page_size) as usize;
+            let len = buf.len().min(self.page_size as usize - start);
+            let frame = match self.pending.entry(page) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    // past the end of the file the page reads as zeros
+                    let mut frame: Frame = vec![0; self.page_size as usize].into_boxed_slice();
+                    self.source
+                        .seek(SeekFrom::Start(page as u64 * self.page_size))?;
+                    let mut read = 0;
+                    while read < frame.len() {
+                        match self.source.read(&mut frame[read..])? {
+                            0 => break,
+                            n => read += n,
+                        }
+                    }
+                    entry.insert(frame)
+                }
+            };
+            frame[start..start + len].copy_from_slice(&buf[..len]);
+            pos += len as u64;
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+
+    /// Log the pending pages as one committed unit and sync the log,
+    /// updating their checksums first if `seal` is set.
+    ///
+    /// Returns the pages, which may be written to the database file afterwards.
+    pub(crate) fn commit(&mut self, seal: bool) -> io::Result<BTreeMap<i32, Frame>> {
+        if self.pending.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+        if seal {
+            for frame in self.pending.values_mut() {
+                seal_page(&mut frame[..]);
+            }
+        }
+        let last = *self.pending.keys().next_back().unwrap();
+        let record_len = RECORD_HEADER_LEN + self.page_size as usize;
+        let mut bytes = Vec::with_capacity(self.pending.len() * record_len);
+        for (&page, frame) in &self.pending {
+            bytes.extend_from_slice(&page.to_be_bytes());
+            bytes.push((page == last) as u8);
+            bytes.extend_from_slice(&frame[..]);
+        }
+        self.log.write_all(&bytes)?;
+        self.log.sync_data()?;
+        Ok(std::mem::take(&mut self.pending))
+    }
+
+    /// Empty the log, once the committed pages are in the synced database file.
+    pub(crate) fn clear(&mut self) -> io::Result<()> {
+        self.log.set_len(0)
+    }
+}
+
+pub(crate) fn wal_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.to_path_buf().into_os_string();
+    path.push(".wal");
+    PathBuf::from(path)
+}
+
+/// Write the committed units of the log to the database file,
+/// then empty the log, dropping a torn or uncommitted tail.
+///
+/// Returns the number of pages written.
+pub(crate) fn recover(db_path: &Path, page_size: u32) -> io::Result<usize> {
+    let mut log = match File::options()
+        .read(true)
+        .write(true)
+        .open(wal_path(db_path))
+    {
+        Ok(log) => log,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut bytes = Vec::new();
+    log.read_to_end(&mut bytes)?;
+
+    let mut committed = BTreeMap::new();
+    let mut unit = Vec::new();
+    for record in bytes.chunks_exact(RECORD_HEADER_LEN + page_size as usize) {
+        let page = i32::from_be_bytes(record[..4].try_into().unwrap());
+        unit.push((page, &record[RECORD_HEADER_LEN..]));
+        if record[4] != 0 {
+            committed.extend(unit.drain(..));
+        }
+    }
+
+    if !committed.is_empty() {
+        let mut file = File::options().write(true).open(db_path)?;
+        for (&page, bytes) in &committed {
+            file.seek(SeekFrom::Start(page as u64 * page_size as u64))?;
+            file.write_all(bytes)?;
+        }
+        file.sync_data()?;
+    }
+    log.set_len(0)?;
+    log.sync_data()?;
+    Ok(committed.len())
+}
+
+/// Whether there is a log to replay.
+pub(crate) fn is_pending(db_path: &Path) -> io::Result<bool> {
+    match std::fs::metadata(wal_path(db_path)) {
+        Ok(meta) => Ok(meta.len() > 0),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete the log, if any.
+pub(crate) fn remove(db_path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(wal_path(db_path)) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
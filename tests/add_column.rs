@@ -0,0 +1,59 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::table::{be_key_u32, ColumnDef, ColumnType, Condition};
+
+fn column(name: &str, size: u16) -> ColumnDef<&str> {
+    ColumnDef {
+        name,
+        column_type: ColumnType::Bytes,
+        size,
+    }
+}
+
+#[test]
+fn old_rows_hold_zeros_in_the_new_column() {
+    let temp = TempDb::new("add-column");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    // two full pages of 64 rows, which no longer fit two pages once wider
+    db.insert_many("items", &(1..=128).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    db.add_column("items", column("note", 8)).unwrap();
+    let mut row = item(200);
+    row.extend(b"new row!");
+    db.insert("items", &row).unwrap();
+    drop(db);
+
+    let mut db = temp.open();
+    assert_eq!(db.get_table_def("items").unwrap()[2].name, "note");
+    assert_eq!(db.data_pages("items").unwrap().len(), 3);
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(rows.len(), 129);
+    for row in &rows[..128] {
+        let id = u32::from_be_bytes(row[..4].try_into().unwrap());
+        assert_eq!(row[..64], item(id));
+        assert_eq!(row[64..], [0; 8]);
+    }
+    let found = db
+        .select("items", &[Condition::eq(64..72, *b"new row!")])
+        .unwrap();
+    assert_eq!(ids(&found), [200]);
+    let zeros = db.select("items", &[Condition::eq(64..72, [0; 8])]);
+    assert_eq!(zeros.unwrap().len(), 128);
+    assert!(db.check_integrity().unwrap().is_empty());
+}
+
+#[test]
+fn columns_that_do_not_fit_a_page_are_rejected() {
+    let temp = TempDb::new("add-column-big");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    assert!(db.add_column("items", column("huge", 4096)).is_err());
+    assert_eq!(db.get_table_def("items").unwrap().len(), 2);
+    let rows = db
+        .select("items", &[Condition::eq(0..4, be_key_u32(1))])
+        .unwrap();
+    assert_eq!(rows, [item(1)]);
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use std::{collections::HashSet, io, sync::Arc};
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Allocator, CreateOptions, Database, DatabaseOptions};
+
+/// Places every page from page 100 on, ignoring the hint.
+#[derive(Debug)]
+struct FromHundred;
+
+impl Allocator for FromHundred {
+    fn allocate(
+        &self,
+        in_use_pages: &HashSet<i32>,
+        _hint: i32,
+        max_pages: Option<i32>,
+    ) -> io::Result<i32> {
+        (100..max_pages.unwrap_or(i32::MAX))
+            .find(|page| !in_use_pages.contains(page))
+            .ok_or_else(|| io::Error::other("no page left"))
+    }
+}
+
+#[test]
+fn pages_are_placed_by_the_allocator() {
+    let temp = TempDb::new("allocator");
+    let options = DatabaseOptions {
+        allocator: Arc::new(FromHundred),
+        ..DatabaseOptions::default()
+    };
+    let mut db = Database::open_with_options(temp.path(), options).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=200).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+
+    // the def page takes 100, the file grows up to it
+    // and the pages it grew by are handed out before asking again
+    assert_eq!(db.data_pages("items").unwrap(), [2, 3, 4, 5]);
+    drop(db);
+    // the placement is not stored, the default allocator reads the pages alike
+    let mut db = temp.open();
+    let all = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&all), (1..=200).collect::<Vec<_>>());
+}
+
+/// Has no page to give.
+#[derive(Debug)]
+struct Exhausted;
+
+impl Allocator for Exhausted {
+    fn allocate(&self, _: &HashSet<i32>, _: i32, _: Option<i32>) -> io::Result<i32> {
+        Err(io::Error::other("no page left"))
+    }
+}
+
+#[test]
+fn allocator_errors_fail_the_allocation() {
+    let options = DatabaseOptions {
+        allocator: Arc::new(Exhausted),
+        ..DatabaseOptions::default()
+    };
+    let mut db = Database::open_in_memory_with_options(CreateOptions::default(), options).unwrap();
+    // the new file has no spare page, so the allocator is asked
+    assert!(db.create_table("items", &item_defs()).is_err());
+    assert!(db.list_tables().is_empty());
+}
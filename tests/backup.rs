@@ -0,0 +1,64 @@
+mod common;
+
+use std::fs;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+fn all(db: &mut Database, table: &str) -> Vec<Vec<u8>> {
+    let mut rows = db.select::<Condition<&[u8]>>(table, &[]).unwrap();
+    rows.sort_unstable();
+    rows
+}
+
+#[test]
+fn restored_backups_select_the_same_rows() {
+    let original = TempDb::new("backup-original");
+    let restored = TempDb::path_only("backup-restored");
+    let mut db = original.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.create_table("others", &item_defs()).unwrap();
+    db.insert_many("items", &(0..300).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    db.insert_many("others", &(0..10).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    db.delete("items", &[Condition::lt(0..4, be_key_u32(150))])
+        .unwrap();
+    db.create_index("items", 0..4).unwrap();
+
+    let mut backup = Vec::new();
+    db.backup(&mut backup).unwrap();
+    // spare pages are left out
+    assert!((backup.len() as u64) < fs::metadata(original.path()).unwrap().len());
+    Database::restore(restored.path(), &backup[..]).unwrap();
+
+    let mut copy = restored.open();
+    assert_eq!(copy.list_tables(), db.list_tables());
+    assert_eq!(all(&mut copy, "items"), all(&mut db, "items"));
+    assert_eq!(all(&mut copy, "others"), all(&mut db, "others"));
+    assert_eq!(copy.fingerprint().unwrap(), db.fingerprint().unwrap());
+    assert!(copy.check_integrity().unwrap().is_empty());
+    let condition = [Condition::eq(0..4, be_key_u32(200))];
+    assert_eq!(copy.select("items", &condition).unwrap(), [item(200)]);
+
+    // the copy is a database of its own
+    copy.insert("others", &item(10)).unwrap();
+    assert_eq!(all(&mut copy, "others").len(), 11);
+    assert_eq!(all(&mut db, "others").len(), 10);
+}
+
+#[test]
+fn restore_rejects_what_is_not_a_backup() {
+    let restored = TempDb::path_only("backup-bad");
+    assert!(Database::restore(restored.path(), &b"RUSTUBXX\0\0\x10\0"[..]).is_err());
+
+    let original = TempDb::new("backup-truncated");
+    let mut backup = Vec::new();
+    original.open().backup(&mut backup).unwrap();
+    // the header alone, without page 0
+    assert!(Database::restore(restored.path(), &backup[..12]).is_err());
+    assert!(Database::restore(restored.path(), &backup[..]).is_ok());
+}
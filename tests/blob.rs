@@ -0,0 +1,59 @@
+mod common;
+
+use common::TempDb;
+use rustub_storage::{
+    table::{be_key_u32, ColumnDef, ColumnType, Condition, BLOB_POINTER_LEN},
+    Database,
+};
+
+fn documents_defs() -> [ColumnDef<&'static str>; 2] {
+    [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "body",
+            column_type: ColumnType::Blob,
+            size: BLOB_POINTER_LEN,
+        },
+    ]
+}
+
+#[test]
+fn a_megabyte_blob_reads_back_intact() {
+    let temp = TempDb::new("blob");
+    let mut db = temp.open();
+    db.create_table("documents", &documents_defs()).unwrap();
+    let body: Vec<u8> = (0..1 << 20).map(|i: u32| (i * 31 % 251) as u8).collect();
+    db.insert_values("documents", &[&be_key_u32(1), &body])
+        .unwrap();
+    db.insert_values("documents", &[&be_key_u32(2), b"short"])
+        .unwrap();
+    // the row only holds the pointer
+    let rows = db.select::<Condition<&[u8]>>("documents", &[]).unwrap();
+    assert!(rows.iter().all(|row| row.len() == 16));
+    drop(db);
+
+    let mut db = temp.open();
+    let values = db
+        .select_values("documents", &[Condition::eq(0..4, be_key_u32(1))])
+        .unwrap();
+    assert_eq!(values.len(), 1);
+    assert_eq!(values[0][0], be_key_u32(1));
+    assert!(values[0][1] == body);
+    let values = db
+        .select_values("documents", &[Condition::eq(0..4, be_key_u32(2))])
+        .unwrap();
+    assert_eq!(values[0][1], b"short");
+}
+
+#[test]
+fn in_memory_databases_have_no_blob_file() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("documents", &documents_defs()).unwrap();
+    assert!(db
+        .insert_values("documents", &[&be_key_u32(1), b"body"])
+        .is_err());
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+};
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{Condition, DatabaseError},
+    CreateOptions, Database,
+};
+
+const PAGE_SIZE: u64 = 4096;
+
+fn checksummed(name: &str) -> TempDb {
+    let temp = TempDb::path_only(name);
+    let options = CreateOptions {
+        checksums: true,
+        ..CreateOptions::default()
+    };
+    Database::create_database_with_options(temp.path(), options).unwrap();
+    temp
+}
+
+fn select_all(db: &mut Database) -> Result<Vec<Vec<u8>>, DatabaseError> {
+    db.select::<Condition<&[u8]>>("items", &[])
+}
+
+/// A checksummed database holding 64 items,
+/// with one byte flipped in the first data page.
+fn corrupted(name: &str) -> TempDb {
+    let temp = checksummed(name);
+    let page = {
+        let mut db = temp.open();
+        db.create_table("items", &item_defs()).unwrap();
+        db.insert_many("items", &(0..64).flat_map(item).collect::<Vec<_>>())
+            .unwrap();
+        // the last 4 bytes of a page hold its checksum, so 64 rows no longer fit
+        assert_eq!(db.data_pages("items").unwrap().len(), 2);
+        db.data_pages("items").unwrap()[0]
+    };
+    let mut db = temp.open();
+    let mut rows = ids(&select_all(&mut db).unwrap());
+    rows.sort_unstable();
+    assert_eq!(rows, (0..64).collect::<Vec<_>>());
+    drop(db);
+
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .open(temp.path())
+        .unwrap();
+    let pos = page as u64 * PAGE_SIZE + 100;
+    let mut byte = [0];
+    file.seek(SeekFrom::Start(pos)).unwrap();
+    file.read_exact(&mut byte).unwrap();
+    file.seek(SeekFrom::Start(pos)).unwrap();
+    file.write_all(&[byte[0] ^ 1]).unwrap();
+    temp
+}
+
+fn assert_checksum_error<T: std::fmt::Debug>(res: Result<T, DatabaseError>) {
+    match res {
+        Err(DatabaseError::Io(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+        res => panic!("expected a checksum error, got {res:?}"),
+    }
+}
+
+#[test]
+fn a_corrupted_byte_fails_the_next_read() {
+    let temp = corrupted("checksums");
+    let mut db = temp.open();
+    assert_checksum_error(select_all(&mut db));
+}
+
+#[test]
+fn a_corrupted_byte_fails_writes_reading_the_page() {
+    let temp = corrupted("checksums-writes");
+    let all: [Condition<&[u8]>; 0] = [];
+
+    let mut db = temp.open();
+    assert_checksum_error(db.update("items", &all, &[Condition::eq(0..4, [0; 4])]));
+    drop(db);
+
+    let mut db = temp.open();
+    assert_checksum_error(db.delete("items", &all));
+    drop(db);
+
+    let mut db = temp.open();
+    assert_checksum_error(db.insert("items", &item(64)));
+}
+
+#[test]
+fn databases_without_checksums_read_corrupted_bytes() {
+    let temp = TempDb::new("no-checksums");
+    let page = {
+        let mut db = temp.open();
+        db.create_table("items", &item_defs()).unwrap();
+        db.insert("items", &item(1)).unwrap();
+        db.data_pages("items").unwrap()[0]
+    };
+    let mut bytes = fs::read(temp.path()).unwrap();
+    bytes[page as usize * PAGE_SIZE as usize + 10] ^= 1;
+    fs::write(temp.path(), bytes).unwrap();
+
+    let mut db = temp.open();
+    let rows = select_all(&mut db).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_ne!(rows[0], item(1));
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn rows_are_tagged_with_the_first_matching_set() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=200).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+
+    let predicates = vec![
+        vec![Condition::lt(0..4, be_key_u32(11))],
+        vec![
+            Condition::gt(0..4, be_key_u32(5)),
+            Condition::lt(0..4, be_key_u32(21)),
+        ],
+        vec![Condition::eq(0..4, be_key_u32(150))],
+    ];
+    let tagged = db.classify("items", &predicates).unwrap();
+    let tags: Vec<(usize, u32)> = tagged
+        .iter()
+        .map(|(tag, row)| (*tag, u32::from_be_bytes(row[..4].try_into().unwrap())))
+        .collect();
+    // rows 6 to 10 match the first two sets, and get the first tag
+    let expected: Vec<(usize, u32)> = (1..=10)
+        .map(|id| (0, id))
+        .chain((11..=20).map(|id| (1, id)))
+        .chain([(2, 150)])
+        .collect();
+    assert_eq!(tags, expected);
+    assert!(tagged.iter().all(|(_, row)| row.len() == 64));
+
+    let none: Vec<Vec<Condition<[u8; 4]>>> = Vec::new();
+    assert!(db.classify("items", &none).unwrap().is_empty());
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use std::fs;
+
+use common::TempDb;
+use rustub_storage::table::{ColumnDef, ColumnType};
+
+fn defs() -> [ColumnDef<&'static str>; 3] {
+    ["a", "b", "c"].map(|name| ColumnDef {
+        name,
+        column_type: ColumnType::UInt,
+        size: 4,
+    })
+}
+
+fn names(temp: &TempDb) -> Vec<String> {
+    let mut db = temp.open();
+    let defs = db.get_table_def("t").unwrap();
+    defs.into_iter().map(|def| def.name).collect()
+}
+
+#[test]
+fn a_gap_ends_the_defs() {
+    let temp = TempDb::new("defs-gap");
+    temp.open().create_table("t", &defs()).unwrap();
+    // [len]["t"][def i32][meta i32]
+    let mut bytes = fs::read(temp.path()).unwrap();
+    let def_page = i32::from_be_bytes(bytes[2..6].try_into().unwrap()) as usize;
+    let record = def_page * 4096 + 32;
+    bytes[record..record + 32].fill(0);
+    fs::write(temp.path(), bytes).unwrap();
+
+    assert_eq!(names(&temp), ["a"]);
+}
+
+#[test]
+fn dropping_a_column_leaves_no_gap() {
+    let temp = TempDb::new("defs-drop");
+    let mut db = temp.open();
+    db.create_table("t", &defs()).unwrap();
+    db.drop_column("t", "b").unwrap();
+    drop(db);
+    assert_eq!(names(&temp), ["a", "c"]);
+}
+
+#[test]
+fn empty_column_names_are_rejected() {
+    let temp = TempDb::new("defs-empty");
+    let mut db = temp.open();
+    let mut defs = defs();
+    defs[1].name = "";
+    let err = db.create_table("t", &defs).unwrap_err();
+    assert!(format!("{err:?}").contains("ColumnNameEmpty"), "{err:?}");
+    assert!(db.get_table_def("t").is_err());
+}
+
+#[test]
+fn mixed_length_names_survive_a_reopen() {
+    let temp = TempDb::new("defs-names");
+    let defs = [
+        ("k", ColumnType::UInt, 4),
+        ("a_much_longer_column_name", ColumnType::Bytes, 17),
+        ("mid_name", ColumnType::Int, 2),
+    ]
+    .map(|(name, column_type, size)| ColumnDef {
+        name,
+        column_type,
+        size,
+    });
+    let mut db = temp.open();
+    db.create_table("t", &defs).unwrap();
+    db.insert("t", &[1; 23]).unwrap();
+    drop(db);
+
+    let mut db = temp.open();
+    let read = db.get_table_def("t").unwrap();
+    assert_eq!(read.len(), 3);
+    for (read, def) in read.iter().zip(&defs) {
+        assert_eq!(read.name, def.name);
+        assert_eq!(read.column_type, def.column_type);
+        assert_eq!(read.size, def.size);
+    }
+    assert_eq!(db.tables_info()[0].1.row_len, 23);
+    assert!(db.insert("t", &[2; 23]).is_ok());
+}
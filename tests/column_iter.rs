@@ -0,0 +1,43 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+fn key(bytes: &[u8]) -> u64 {
+    u32::from_be_bytes(bytes.try_into().unwrap()) as u64
+}
+
+#[test]
+fn summing_the_iterator_matches_the_rows() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    // several pages, with holes from deletes
+    for id in 1..=300u32 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    for id in (7..=300).step_by(7) {
+        db.delete("items", &[Condition::eq(0..4, be_key_u32(id))])
+            .unwrap();
+    }
+
+    let sum: u64 = db
+        .column_iter("items", 0..4)
+        .unwrap()
+        .map(|value| key(&value.unwrap()))
+        .sum();
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    let expected: u64 = rows.iter().map(|row| key(&row[..4])).sum();
+    assert_eq!(sum, expected);
+    assert_eq!(sum, (1..=300u64).filter(|id| id % 7 != 0).sum());
+}
+
+#[test]
+fn ranges_past_the_row_are_rejected() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    assert!(db.column_iter("items", 60..65).is_err());
+    assert_eq!(db.column_iter("items", 60..64).unwrap().count(), 0);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::TempDb;
+use rustub_storage::table::{ColumnDef, ColumnType, BLOB_POINTER_LEN};
+
+#[test]
+fn every_byte_round_trips() {
+    for byte in 0..=u8::MAX {
+        let column_type = ColumnType::from_u8(byte);
+        assert_eq!(column_type.to_u8(), byte);
+        assert_eq!(u8::from(ColumnType::from(byte)), byte);
+    }
+}
+
+#[test]
+fn known_variants_keep_their_codes() {
+    let known = [
+        (ColumnType::Bytes, 0),
+        (ColumnType::Int, 1),
+        (ColumnType::UInt, 2),
+        (ColumnType::Text, 3),
+        (ColumnType::Bool, 4),
+        (ColumnType::Blob, 5),
+    ];
+    for (column_type, byte) in known {
+        assert_eq!(column_type.to_u8(), byte);
+        assert_eq!(ColumnType::from_u8(byte), column_type);
+    }
+    assert_eq!(ColumnType::from_u8(200), ColumnType::Unknown(200));
+}
+
+#[test]
+fn every_variant_round_trips_through_the_file() {
+    let types = [
+        ColumnType::Bytes,
+        ColumnType::Int,
+        ColumnType::UInt,
+        ColumnType::Text,
+        ColumnType::Bool,
+        ColumnType::Blob,
+        ColumnType::Unknown(200),
+    ];
+    let names = ["bytes", "int", "uint", "text", "bool", "blob", "unknown"];
+    let defs: Vec<ColumnDef<&str>> = types
+        .iter()
+        .zip(names)
+        .map(|(&column_type, name)| ColumnDef {
+            name,
+            column_type,
+            size: match column_type {
+                ColumnType::Blob => BLOB_POINTER_LEN,
+                _ => 4,
+            },
+        })
+        .collect();
+    let temp = TempDb::new("column-types");
+    temp.open().create_table("t", &defs).unwrap();
+
+    let read = temp.open().get_table_def("t").unwrap();
+    let read_types: Vec<ColumnType> = read.iter().map(|def| def.column_type).collect();
+    assert_eq!(read_types, types);
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "arrow")]
+
+use rustub_storage::{
+    table::{be_key_i64, be_key_u32, ColumnDef, ColumnType, Condition},
+    Database,
+};
+
+#[test]
+fn columns_rebuild_the_selected_rows() {
+    let mut db = Database::open_in_memory().unwrap();
+    let defs = [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "balance",
+            column_type: ColumnType::Int,
+            size: 8,
+        },
+        ColumnDef {
+            name: "name",
+            column_type: ColumnType::Text,
+            size: 6,
+        },
+    ];
+    db.create_table("accounts", &defs).unwrap();
+    for id in 1..=150u32 {
+        let balance = id as i64 * 1000 - 75_000;
+        let mut name = [0; 6];
+        name[..4].copy_from_slice(format!("n{id:03}").as_bytes());
+        db.insert_values("accounts", &[&be_key_u32(id), &be_key_i64(balance), &name])
+            .unwrap();
+    }
+
+    let conditions = [Condition::gt(0..4, be_key_u32(20))];
+    let rows = db.select("accounts", &conditions).unwrap();
+    let columns = db.select_columnar("accounts", &conditions).unwrap();
+    assert_eq!(columns.len(), 3);
+    assert_eq!(columns[0].len(), rows.len() * 4);
+    assert_eq!(columns[1].len(), rows.len() * 8);
+    assert_eq!(columns[2].len(), rows.len() * 6);
+
+    let ids = columns[0].chunks_exact(4);
+    let balances = columns[1].chunks_exact(8);
+    let names = columns[2].chunks_exact(6);
+    let rebuilt: Vec<Vec<u8>> = ids
+        .zip(balances)
+        .zip(names)
+        .map(|((id, balance), name)| {
+            let id = u32::from_ne_bytes(id.try_into().unwrap());
+            let balance = i64::from_ne_bytes(balance.try_into().unwrap());
+            [&be_key_u32(id)[..], &be_key_i64(balance), name].concat()
+        })
+        .collect();
+    assert_eq!(rebuilt, rows);
+}
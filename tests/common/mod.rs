@@ -0,0 +1,101 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rustub_storage::{
+    table::{be_key_u32, ColumnDef, ColumnType},
+    Database,
+};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A database file in the temp dir, removed with its log and blob file on drop.
+pub struct TempDb {
+    path: PathBuf,
+}
+
+impl TempDb {
+    /// Create an empty database file.
+    pub fn new(name: &str) -> Self {
+        let temp = TempDb::path_only(name);
+        Database::create_database(&temp.path).unwrap();
+        temp
+    }
+
+    /// Only pick a path, for tests that create the file themselves.
+    pub fn path_only(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("rustub-{}-{id}-{name}.db", process::id()));
+        let temp = TempDb { path };
+        temp.remove();
+        temp
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn sidecar(&self, extension: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(extension);
+        PathBuf::from(path)
+    }
+
+    pub fn open(&self) -> Database {
+        Database::open(&self.path).unwrap()
+    }
+
+    fn remove(&self) {
+        for path in [
+            self.path.clone(),
+            self.sidecar(".wal"),
+            self.sidecar(".blob"),
+        ] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+/// Columns of the `items` tables: a 4 byte key and 60 bytes of payload.
+pub fn item_defs() -> [ColumnDef<&'static str>; 2] {
+    [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "payload",
+            column_type: ColumnType::Bytes,
+            size: 60,
+        },
+    ]
+}
+
+/// A row of an `items` table with the given key,
+/// whose payload is derived from the key.
+pub fn item(id: u32) -> Vec<u8> {
+    let mut row = be_key_u32(id).to_vec();
+    row.extend((0..60).map(|i| (id as u8).wrapping_add(i)));
+    row
+}
+
+/// The keys of `items` rows.
+pub fn ids(rows: &[Vec<u8>]) -> Vec<u32> {
+    rows.iter()
+        .map(|row| u32::from_be_bytes(row[..4].try_into().unwrap()))
+        .collect()
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DeletePolicy, TableOptions},
+    Database,
+};
+
+#[test]
+fn compacting_keeps_live_rows_and_frees_tombstoned_slots() {
+    let mut db = Database::open_in_memory().unwrap();
+    let options = TableOptions {
+        delete_policy: DeletePolicy::Tombstone,
+        ..TableOptions::default()
+    };
+    db.create_table_with_options("items", &item_defs(), &options)
+        .unwrap();
+    let rows: Vec<u8> = (1..=64).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    for id in (1..=60).step_by(3) {
+        db.delete("items", &[Condition::eq(0..4, be_key_u32(id))])
+            .unwrap();
+    }
+    let all: [Condition<&[u8]>; 0] = [];
+    let mut live = ids(&db.select("items", &all).unwrap());
+    assert_eq!(live.len(), 44);
+
+    assert_eq!(db.compact_page("items", 0).unwrap(), 20);
+    let mut compacted = ids(&db.select("items", &all).unwrap());
+    live.sort_unstable();
+    compacted.sort_unstable();
+    assert_eq!(compacted, live);
+    assert_eq!(db.page_fill("items").unwrap()[0].1, 44);
+    // nothing is left to remove
+    assert_eq!(db.compact_page("items", 0).unwrap(), 0);
+
+    // the 20 slots take new rows before a page is added
+    let more: Vec<u8> = (100..120).flat_map(item).collect();
+    db.insert_many("items", &more).unwrap();
+    assert_eq!(db.data_pages("items").unwrap().len(), 1);
+    db.insert("items", &item(120)).unwrap();
+    assert_eq!(db.data_pages("items").unwrap().len(), 2);
+    assert!(db.compact_page("items", 2).is_err());
+}
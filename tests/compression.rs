@@ -0,0 +1,81 @@
+#![cfg(feature = "compression")]
+
+mod common;
+
+use std::fs;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Database};
+
+fn all(db: &mut Database<impl rustub_storage::storage::Storage>) -> Vec<Vec<u8>> {
+    let mut rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    rows.sort_unstable();
+    rows
+}
+
+#[test]
+fn compressed_copies_round_trip() {
+    let plain = TempDb::new("plain");
+    let compressed = TempDb::path_only("compressed");
+    let restored = TempDb::path_only("restored");
+    let fingerprint = {
+        let mut db = plain.open();
+        db.create_table("items", &item_defs()).unwrap();
+        let rows: Vec<u8> = (0..1000).flat_map(item).collect();
+        db.insert_many("items", &rows).unwrap();
+        db.delete("items", &[Condition::lt(0..4, &item(100)[..4])])
+            .unwrap();
+        db.fingerprint().unwrap()
+    };
+
+    Database::compress(plain.path(), compressed.path()).unwrap();
+    let plain_len = fs::metadata(plain.path()).unwrap().len();
+    let compressed_len = fs::metadata(compressed.path()).unwrap().len();
+    assert!(
+        compressed_len * 4 < plain_len,
+        "{compressed_len} bytes compressed, {plain_len} plain"
+    );
+
+    {
+        let mut db = Database::open_compressed(compressed.path()).unwrap();
+        assert_eq!(db.fingerprint().unwrap(), fingerprint);
+        let rows = all(&mut db);
+        assert_eq!(ids(&rows), (100..1000).collect::<Vec<_>>());
+        assert_eq!(rows[0], item(100));
+        assert!(db.check_integrity().unwrap().is_empty());
+
+        // written through, compressed as they are flushed
+        db.insert("items", &item(5)).unwrap();
+        db.delete("items", &[Condition::eq(0..4, &item(999)[..4])])
+            .unwrap();
+        db.delete("items", &[Condition::gt(0..4, &item(500)[..4])])
+            .unwrap();
+        // cuts the file
+        db.vacuum("items").unwrap();
+    }
+    let mut db = Database::open_compressed(compressed.path()).unwrap();
+    let mut want: Vec<u32> = (100..=500).collect();
+    want.insert(0, 5);
+    assert_eq!(ids(&all(&mut db)), want);
+    let fingerprint = db.fingerprint().unwrap();
+    drop(db);
+
+    Database::decompress(compressed.path(), restored.path()).unwrap();
+    let mut db = restored.open();
+    assert_eq!(db.fingerprint().unwrap(), fingerprint);
+    assert!(db.check_integrity().unwrap().is_empty());
+    assert_eq!(ids(&all(&mut db)), want);
+}
+
+#[test]
+fn plain_files_are_not_opened_as_compressed() {
+    let plain = TempDb::new("not-compressed");
+    assert!(Database::open_compressed(plain.path()).is_err());
+
+    let compressed = TempDb::path_only("other-version");
+    Database::compress(plain.path(), compressed.path()).unwrap();
+    let mut bytes = fs::read(compressed.path()).unwrap();
+    bytes[11] = 2;
+    fs::write(compressed.path(), bytes).unwrap();
+    assert!(Database::open_compressed(compressed.path()).is_err());
+}
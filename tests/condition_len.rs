@@ -0,0 +1,50 @@
+mod common;
+
+use std::io::ErrorKind;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, Condition, DatabaseError, RangeCondition},
+    Database,
+};
+
+fn items_db() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    db
+}
+
+fn is_invalid_input(err: &DatabaseError) -> bool {
+    matches!(err, DatabaseError::Io(e) if e.kind() == ErrorKind::InvalidInput)
+}
+
+#[test]
+fn too_short_values_are_errors_not_misses() {
+    let mut db = items_db();
+    // the key 1 without its leading zeros
+    let short = [Condition::eq(0..4, [1u8])];
+    let err = db.select("items", &short).unwrap_err();
+    assert!(is_invalid_input(&err), "{err:?}");
+    assert!(format!("{err:?}").contains("condition value of 1 bytes"));
+    assert!(is_invalid_input(&db.count("items", &short).unwrap_err()));
+    assert!(is_invalid_input(&db.delete("items", &short).unwrap_err()));
+    let set = [Condition::eq(4..8, [0u8; 2])];
+    let err = db
+        .update("items", &[Condition::eq(0..4, be_key_u32(1))], &set)
+        .unwrap_err();
+    assert!(is_invalid_input(&err), "{err:?}");
+
+    let long = [Condition::eq(0..4, [0u8; 5])];
+    assert!(is_invalid_input(&db.select("items", &long).unwrap_err()));
+    let between = [RangeCondition::between(0..4, vec![0; 4], vec![0; 3])];
+    assert!(is_invalid_input(&db.select("items", &between).unwrap_err()));
+
+    // nothing was changed
+    assert_eq!(
+        db.select("items", &[Condition::eq(0..4, be_key_u32(1))])
+            .unwrap(),
+        [item(1)]
+    );
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+/// Keys on both sides of the byte boundaries of a big-endian u32.
+const KEYS: [u32; 6] = [1, 255, 256, 257, 65_536, 70_000];
+
+fn items() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in KEYS {
+        db.insert("items", &item(id)).unwrap();
+    }
+    db
+}
+
+fn select(db: &mut Database<MemoryStorage>, condition: Condition<[u8; 4]>) -> Vec<u32> {
+    let mut keys = ids(&db.select("items", &[condition]).unwrap());
+    keys.sort_unstable();
+    keys
+}
+
+#[test]
+fn select_compares_multi_byte_keys_in_order() {
+    let mut db = items();
+    let key = be_key_u32(256);
+    assert_eq!(select(&mut db, Condition::lt(0..4, key)), [1, 255]);
+    assert_eq!(select(&mut db, Condition::eq(0..4, key)), [256]);
+    assert_eq!(
+        select(&mut db, Condition::gt(0..4, key)),
+        [257, 65_536, 70_000]
+    );
+}
+
+#[test]
+fn update_applies_the_ordering() {
+    let mut db = items();
+    let marker = Condition::eq(4..8, [0xff; 4]);
+    let updated = db
+        .update(
+            "items",
+            &[Condition::gt(0..4, be_key_u32(255))],
+            std::slice::from_ref(&marker),
+        )
+        .unwrap();
+    assert_eq!(updated, 4);
+    assert_eq!(select(&mut db, marker), [256, 257, 65_536, 70_000]);
+
+    let updated = db
+        .update(
+            "items",
+            &[Condition::lt(0..4, be_key_u32(256))],
+            &[Condition::eq(4..8, [0xee; 4])],
+        )
+        .unwrap();
+    assert_eq!(updated, 2);
+    assert_eq!(select(&mut db, Condition::eq(4..8, [0xee; 4])), [1, 255]);
+}
+
+#[test]
+fn delete_applies_the_ordering() {
+    let mut db = items();
+    assert_eq!(
+        db.delete("items", &[Condition::lt(0..4, be_key_u32(257))])
+            .unwrap(),
+        3
+    );
+    assert_eq!(
+        db.delete("items", &[Condition::eq(0..4, be_key_u32(65_536))])
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        db.delete("items", &[Condition::gt(0..4, be_key_u32(70_000))])
+            .unwrap(),
+        0
+    );
+    let rest = ids(&db.select::<Condition<&[u8]>>("items", &[]).unwrap());
+    assert_eq!(rest, [257, 70_000]);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::table::{Condition, TableOptions};
+
+#[test]
+fn contiguous_tables_start_with_consecutive_pages() {
+    let temp = TempDb::new("contiguous");
+    let mut db = temp.open();
+    // leave a hole too small for the run
+    db.create_table("small", &item_defs()).unwrap();
+    db.insert("small", &item(1)).unwrap();
+    db.create_table("other", &item_defs()).unwrap();
+    db.drop("small").unwrap();
+
+    let options = TableOptions {
+        contiguous_pages: 5,
+        ..TableOptions::default()
+    };
+    db.create_table_with_options("items", &item_defs(), &options)
+        .unwrap();
+    let pages = db.data_pages("items").unwrap();
+    assert_eq!(pages.len(), 5);
+    assert!(
+        pages.windows(2).all(|pair| pair[1] == pair[0] + 1),
+        "{pages:?}"
+    );
+    // def and meta page before them, past the three page hole
+    assert!(pages[0] >= 6, "{pages:?}");
+
+    let rows: Vec<u8> = (1..=5 * 64).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    assert_eq!(db.data_pages("items").unwrap(), pages);
+    drop(db);
+    let mut db = temp.open();
+    assert_eq!(db.data_pages("items").unwrap(), pages);
+    let all = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&all), (1..=5 * 64).collect::<Vec<_>>());
+}
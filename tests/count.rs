@@ -0,0 +1,52 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DeletePolicy, TableOptions},
+    Database,
+};
+
+#[test]
+fn count_matches_select_across_pages() {
+    for delete_policy in [
+        DeletePolicy::ZeroFill,
+        DeletePolicy::Tombstone,
+        DeletePolicy::PresenceFlag,
+    ] {
+        let mut db = Database::open_in_memory().unwrap();
+        let options = TableOptions {
+            delete_policy,
+            ..TableOptions::default()
+        };
+        db.create_table_with_options("items", &item_defs(), &options)
+            .unwrap();
+        // over four pages
+        let rows: Vec<u8> = (0..230).flat_map(item).collect();
+        db.insert_many("items", &rows).unwrap();
+        assert_eq!(db.count::<Condition<&[u8]>>("items", &[]).unwrap(), 230);
+
+        // every third row becomes an empty or deleted slot
+        for id in (0..230).step_by(3) {
+            db.delete("items", &[Condition::eq(0..4, be_key_u32(id))])
+                .unwrap();
+        }
+        assert_eq!(
+            db.count::<Condition<&[u8]>>("items", &[]).unwrap(),
+            230 - 77,
+            "{delete_policy:?}"
+        );
+        let conditions = [
+            Condition::gt(0..4, be_key_u32(99)),
+            Condition::lt(0..4, be_key_u32(200)),
+        ];
+        let selected = db.select("items", &conditions).unwrap().len();
+        assert_eq!(selected, 100 - 33);
+        assert_eq!(db.count("items", &conditions).unwrap(), selected);
+    }
+}
+
+#[test]
+fn count_of_a_missing_table_fails() {
+    let mut db = Database::open_in_memory().unwrap();
+    assert!(db.count::<Condition<&[u8]>>("missing", &[]).is_err());
+}
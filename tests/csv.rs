@@ -0,0 +1,135 @@
+use rustub_storage::{
+    table::{be_key_i32, ColumnDef, ColumnType, Condition, DatabaseError},
+    Database,
+};
+
+fn defs() -> [ColumnDef<&'static str>; 5] {
+    [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::Int,
+            size: 4,
+        },
+        ColumnDef {
+            name: "qty",
+            column_type: ColumnType::UInt,
+            size: 2,
+        },
+        ColumnDef {
+            name: "name",
+            column_type: ColumnType::Text,
+            size: 8,
+        },
+        ColumnDef {
+            name: "ok",
+            column_type: ColumnType::Bool,
+            size: 1,
+        },
+        ColumnDef {
+            name: "tag",
+            column_type: ColumnType::Bytes,
+            size: 2,
+        },
+    ]
+}
+
+fn row(id: i32, qty: u16, name: &str, ok: bool, tag: [u8; 2]) -> Vec<u8> {
+    let mut row = be_key_i32(id).to_vec();
+    row.extend_from_slice(&qty.to_be_bytes());
+    let mut text = [0; 8];
+    text[..name.len()].copy_from_slice(name.as_bytes());
+    row.extend_from_slice(&text);
+    row.push(ok as u8);
+    row.extend_from_slice(&tag);
+    row
+}
+
+fn sample_db() -> Database<rustub_storage::storage::MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &defs()).unwrap();
+    db.insert("items", &row(-7, 3, "apple", true, [0xab, 0x01]))
+        .unwrap();
+    db.insert("items", &row(2, 0, "gone", false, [0, 0]))
+        .unwrap();
+    db.insert("items", &row(40, 65535, "a,\"b\"", false, [0xff, 0x10]))
+        .unwrap();
+    db.delete("items", &[Condition::eq(0..4, be_key_i32(2))])
+        .unwrap();
+    db
+}
+
+#[test]
+fn export_writes_a_header_and_the_live_rows() {
+    let mut db = sample_db();
+    let mut out = Vec::new();
+    db.export_csv("items", &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "id,qty,name,ok,tag\n\
+         -7,3,apple,true,ab01\n\
+         40,65535,\"a,\"\"b\"\"\",false,ff10\n"
+    );
+}
+
+#[test]
+fn import_round_trips_an_export() {
+    let mut db = sample_db();
+    let mut out = Vec::new();
+    db.export_csv("items", &mut out).unwrap();
+    db.create_table("copy", &defs()).unwrap();
+    assert_eq!(db.import_csv("copy", &out[..]).unwrap(), 2);
+
+    let all = [] as [Condition<&[u8]>; 0];
+    let mut rows = db.select("items", &all).unwrap();
+    let mut copied = db.select("copy", &all).unwrap();
+    rows.sort_unstable();
+    copied.sort_unstable();
+    assert_eq!(copied, rows);
+}
+
+#[test]
+fn import_maps_columns_by_name() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &defs()).unwrap();
+    let text = "name,id,ok\n\
+                pear,5,1\n\
+                \n\
+                plum,-1,false\n";
+    assert_eq!(db.import_csv("items", text.as_bytes()).unwrap(), 2);
+    let mut rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    rows.sort_unstable();
+    assert_eq!(
+        rows,
+        [
+            row(-1, 0, "plum", false, [0, 0]),
+            row(5, 0, "pear", true, [0, 0])
+        ]
+    );
+}
+
+#[test]
+fn a_malformed_integer_names_its_line_and_imports_nothing() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &defs()).unwrap();
+    let text = "id,qty\n\
+                1,2\n\
+                2,x3\n";
+    match db.import_csv("items", text.as_bytes()) {
+        Err(DatabaseError::Csv { line, message }) => {
+            assert_eq!(line, 3);
+            assert!(message.contains("qty"), "{message}");
+        }
+        res => panic!("expected a CSV error, got {res:?}"),
+    }
+    let too_big = "id,qty\n1,65536\n";
+    assert!(matches!(
+        db.import_csv("items", too_big.as_bytes()),
+        Err(DatabaseError::Csv { line: 2, .. })
+    ));
+    let unknown = "id,price\n1,2\n";
+    assert!(matches!(
+        db.import_csv("items", unknown.as_bytes()),
+        Err(DatabaseError::Csv { line: 1, .. })
+    ));
+    assert_eq!(db.count::<Condition<&[u8]>>("items", &[]).unwrap(), 0);
+}
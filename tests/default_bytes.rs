@@ -0,0 +1,58 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{
+        be_key_u32, default_bytes, from_be_key_i32, from_be_key_i64, ColumnDef, ColumnType,
+        Condition,
+    },
+    Database,
+};
+
+#[test]
+fn defaults_decode_to_zero_false_and_empty() {
+    assert_eq!(
+        from_be_key_i32(default_bytes(ColumnType::Int, 4).try_into().unwrap()),
+        0
+    );
+    assert_eq!(
+        from_be_key_i64(default_bytes(ColumnType::Int, 8).try_into().unwrap()),
+        0
+    );
+    assert_eq!(
+        u64::from_be_bytes(default_bytes(ColumnType::UInt, 8).try_into().unwrap()),
+        0
+    );
+    assert_eq!(default_bytes(ColumnType::Bool, 1), [0]);
+    let text = default_bytes(ColumnType::Text, 10);
+    let end = text.iter().position(|b| *b == 0).unwrap_or(text.len());
+    assert_eq!(std::str::from_utf8(&text[..end]).unwrap(), "");
+    assert_eq!(default_bytes(ColumnType::Bytes, 3), [0; 3]);
+    assert_eq!(default_bytes(ColumnType::Blob, 12), [0; 12]);
+    for column_type in [ColumnType::Int, ColumnType::Text, ColumnType::Bytes] {
+        assert_eq!(default_bytes(column_type, 6).len(), 6);
+        assert!(default_bytes(column_type, 0).is_empty());
+    }
+}
+
+#[test]
+fn added_columns_hold_the_default() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    db.add_column(
+        "items",
+        ColumnDef {
+            name: "delta",
+            column_type: ColumnType::Int,
+            size: 4,
+        },
+    )
+    .unwrap();
+
+    let rows = db
+        .select("items", &[Condition::eq(0..4, be_key_u32(1))])
+        .unwrap();
+    assert_eq!(rows[0][64..], default_bytes(ColumnType::Int, 4));
+    assert_eq!(from_be_key_i32(rows[0][64..].try_into().unwrap()), 0);
+}
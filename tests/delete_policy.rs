@@ -0,0 +1,156 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DeletePolicy, TableOptions},
+    Database,
+};
+
+fn table_with(policy: DeletePolicy) -> Database<rustub_storage::storage::MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    let options = TableOptions {
+        delete_policy: policy,
+        ..TableOptions::default()
+    };
+    db.create_table_with_options("items", &item_defs(), &options)
+        .unwrap();
+    db
+}
+
+fn all() -> [Condition<&'static [u8]>; 0] {
+    []
+}
+
+#[test]
+fn scans_skip_deleted_rows_under_every_policy() {
+    for policy in [
+        DeletePolicy::ZeroFill,
+        DeletePolicy::Tombstone,
+        DeletePolicy::PresenceFlag,
+    ] {
+        let mut db = table_with(policy);
+        for id in 0..150 {
+            db.insert("items", &item(id)).unwrap();
+        }
+        let deleted = db
+            .delete("items", &[Condition::lt(0..4, be_key_u32(100))])
+            .unwrap();
+        assert_eq!(deleted, 100, "{policy:?}");
+
+        let rows = db.select("items", &all()).unwrap();
+        assert_eq!(ids(&rows), (100..150).collect::<Vec<_>>(), "{policy:?}");
+        assert_eq!(db.count("items", &all()).unwrap(), 50, "{policy:?}");
+        let iterated = db
+            .select_iter("items", &all())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(iterated, rows, "{policy:?}");
+    }
+}
+
+/// A tombstone on the third page, and an empty slot on the first page
+/// left by compacting it.
+fn tombstone_after_empty_slot(db: &mut Database<impl rustub_storage::storage::Storage>) {
+    for id in 0..150 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(3))])
+        .unwrap();
+    assert_eq!(db.compact_page("items", 0).unwrap(), 1);
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(140))])
+        .unwrap();
+}
+
+#[test]
+fn tombstones_are_reused_before_earlier_empty_slots() {
+    let mut db = table_with(DeletePolicy::Tombstone);
+    tombstone_after_empty_slot(&mut db);
+    let pages = db.data_pages("items").unwrap();
+
+    let report = db.insert_report("items", &item(1000)).unwrap();
+    assert!(!report.allocated_new_page);
+    assert_eq!((report.row_id.page, report.row_id.slot), (pages[2], 12));
+    // then the lowest empty slot
+    let report = db.insert_report("items", &item(1001)).unwrap();
+    assert_eq!((report.row_id.page, report.row_id.slot), (pages[0], 63));
+
+    let rows = db.select("items", &all()).unwrap();
+    assert_eq!(rows.len(), 150);
+    assert!(!ids(&rows).contains(&3) && !ids(&rows).contains(&140));
+}
+
+#[test]
+fn tombstones_are_reused_first_after_reopening() {
+    let temp = TempDb::path_only("tombstone-reopen");
+    Database::create_database(temp.path()).unwrap();
+    let options = TableOptions {
+        delete_policy: DeletePolicy::Tombstone,
+        ..TableOptions::default()
+    };
+    let pages = {
+        let mut db = temp.open();
+        db.create_table_with_options("items", &item_defs(), &options)
+            .unwrap();
+        tombstone_after_empty_slot(&mut db);
+        db.data_pages("items").unwrap()
+    };
+
+    let mut db = temp.open();
+    let report = db.insert_report("items", &item(1000)).unwrap();
+    assert_eq!((report.row_id.page, report.row_id.slot), (pages[2], 12));
+}
+
+#[test]
+fn zero_fill_reuses_the_lowest_free_slot() {
+    let mut db = table_with(DeletePolicy::ZeroFill);
+    for id in 0..150 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(140))])
+        .unwrap();
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(3))])
+        .unwrap();
+    let pages = db.data_pages("items").unwrap();
+
+    let report = db.insert_report("items", &item(1000)).unwrap();
+    assert_eq!(report.row_id.page, pages[0]);
+    assert_eq!(report.row_id.slot, 3);
+    let rows = db.select("items", &all()).unwrap();
+    assert_eq!(rows.len(), 149);
+    assert!(ids(&rows).contains(&1000));
+}
+
+#[test]
+fn presence_flags_keep_rows_of_zeros() {
+    let temp = TempDb::path_only("presence-zeros");
+    Database::create_database(temp.path()).unwrap();
+    let options = TableOptions {
+        delete_policy: DeletePolicy::PresenceFlag,
+        ..TableOptions::default()
+    };
+    {
+        let mut db = temp.open();
+        db.create_table_with_options("items", &item_defs(), &options)
+            .unwrap();
+        db.insert("items", &[0; 64]).unwrap();
+        db.insert("items", &item(1)).unwrap();
+        // the row of zeros is not a free slot
+        let report = db.insert_report("items", &item(2)).unwrap();
+        assert_eq!(report.row_id.slot, 2);
+    }
+
+    let mut db = temp.open();
+    let zeros = [Condition::eq(0..4, be_key_u32(0))];
+    assert_eq!(db.select("items", &zeros).unwrap(), [vec![0; 64]]);
+    assert_eq!(db.count("items", &all()).unwrap(), 3);
+    let updated = db
+        .update("items", &zeros, &[Condition::eq(63..64, [9])])
+        .unwrap();
+    assert_eq!(updated, 1);
+    let mut row = vec![0; 64];
+    row[63] = 9;
+    assert_eq!(db.select("items", &zeros).unwrap(), [row]);
+    assert_eq!(db.delete("items", &zeros).unwrap(), 1);
+    assert_eq!(ids(&db.select("items", &all()).unwrap()), [1, 2]);
+}
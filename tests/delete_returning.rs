@@ -0,0 +1,46 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn deleted_rows_come_back() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=10 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    let rows = db
+        .delete_returning("items", &[Condition::gt(0..4, be_key_u32(7))])
+        .unwrap();
+    assert_eq!(rows, [item(8), item(9), item(10)]);
+    let rest = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&rest), (1..=7).collect::<Vec<_>>());
+}
+
+#[test]
+fn emptied_pages_are_still_freed() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    // three pages of 64 rows
+    db.insert_many("items", &(1..=192).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    let pages = db.data_pages("items").unwrap();
+
+    let second_page = [
+        Condition::gt(0..4, be_key_u32(64)),
+        Condition::lt(0..4, be_key_u32(129)),
+    ];
+    let rows = db.delete_returning("items", &second_page).unwrap();
+    assert_eq!(ids(&rows), (65..=128).collect::<Vec<_>>());
+    assert_eq!(db.data_pages("items").unwrap(), [pages[0], pages[2]]);
+    assert!(db.check_integrity().unwrap().is_empty());
+
+    // the freed page is taken again
+    let report = db.insert_report("items", &item(1000)).unwrap();
+    assert!(report.allocated_new_page);
+    assert_eq!(report.row_id.page, pages[1]);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, TableOptions},
+    Database,
+};
+
+#[test]
+fn describe_lists_columns_row_len_and_pages() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+
+    assert_eq!(
+        db.describe("items").unwrap(),
+        "id: uint(4)\npayload: bytes(60)\nrow_len: 64\npages: 1\n"
+    );
+}
+
+#[test]
+fn describe_names_the_primary_key() {
+    let mut db = Database::open_in_memory().unwrap();
+    let defs = [
+        ColumnDef {
+            name: "name",
+            column_type: ColumnType::Text,
+            size: 16,
+        },
+        ColumnDef {
+            name: "admin",
+            column_type: ColumnType::Bool,
+            size: 1,
+        },
+        ColumnDef {
+            name: "balance",
+            column_type: ColumnType::Int,
+            size: 8,
+        },
+    ];
+    let options = TableOptions {
+        primary_key: Some(0),
+        ..TableOptions::default()
+    };
+    db.create_table_with_options("accounts", &defs, &options)
+        .unwrap();
+
+    assert_eq!(
+        db.describe("accounts").unwrap(),
+        "name: text(16)\nadmin: bool(1)\nbalance: int(8)\n\
+         primary key: name\nrow_len: 25\npages: 0\n"
+    );
+    assert!(db.describe("missing").is_err());
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use std::fs;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::table::{ColumnDef, ColumnType, Condition};
+
+/// The def and meta pages of the table in header slot 0,
+/// from its `[len][name][def i32][meta i32]` record.
+fn def_and_meta(temp: &TempDb) -> [i32; 2] {
+    let bytes = fs::read(temp.path()).unwrap();
+    let pos = 1 + bytes[0] as usize;
+    [pos, pos + 4].map(|pos| i32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()))
+}
+
+#[test]
+fn dropped_pages_are_zeroed() {
+    let temp = TempDb::new("drop-zero");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert_many("items", &(1..=100).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    let mut pages = db.data_pages("items").unwrap();
+    assert_eq!(pages.len(), 2);
+    pages.extend(def_and_meta(&temp));
+    db.drop("items").unwrap();
+    drop(db);
+
+    let bytes = fs::read(temp.path()).unwrap();
+    assert!(bytes[..4096].iter().all(|b| *b == 0));
+    for page in pages {
+        let pos = page as usize * 4096;
+        assert!(
+            bytes[pos..pos + 4096].iter().all(|b| *b == 0),
+            "page {page}"
+        );
+    }
+}
+
+#[test]
+fn tables_reusing_dropped_pages_start_clean() {
+    let temp = TempDb::new("drop-reuse");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert_many("items", &(1..=100).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    let mut old_pages = db.data_pages("items").unwrap();
+    old_pages.extend(def_and_meta(&temp));
+    db.drop("items").unwrap();
+
+    let def = [ColumnDef {
+        name: "n",
+        column_type: ColumnType::UInt,
+        size: 4,
+    }];
+    db.create_table("numbers", &def).unwrap();
+    db.insert("numbers", &[1; 4]).unwrap();
+    drop(db);
+
+    let mut db = temp.open();
+    let mut new_pages = db.data_pages("numbers").unwrap();
+    new_pages.extend(def_and_meta(&temp));
+    // every page of the new table was a page of the dropped one
+    assert!(new_pages.iter().all(|page| old_pages.contains(page)));
+    assert_eq!(db.get_table_def("numbers").unwrap().len(), 1);
+    let rows = db.select::<Condition<&[u8]>>("numbers", &[]).unwrap();
+    assert_eq!(rows, [vec![1; 4]]);
+}
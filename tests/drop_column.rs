@@ -0,0 +1,77 @@
+mod common;
+
+use common::TempDb;
+use rustub_storage::table::{ColumnDef, ColumnType, Condition};
+
+/// Columns `a`, `b` and `c` of 2, 3 and 4 bytes.
+fn defs() -> [ColumnDef<&'static str>; 3] {
+    [("a", 2), ("b", 3), ("c", 4)].map(|(name, size)| ColumnDef {
+        name,
+        column_type: ColumnType::Bytes,
+        size,
+    })
+}
+
+/// A 9 byte row whose columns are filled with `n`, `n + 1` and `n + 2`.
+fn row(n: u8) -> Vec<u8> {
+    [vec![n; 2], vec![n + 1; 3], vec![n + 2; 4]].concat()
+}
+
+/// Drop `column` from a table of 200 rows, returning the rows after a reopen.
+fn rows_without(column: &str) -> (Vec<String>, Vec<Vec<u8>>) {
+    let temp = TempDb::new("drop-column");
+    let mut db = temp.open();
+    db.create_table("t", &defs()).unwrap();
+    for n in 1..=200u8 {
+        db.insert("t", &row(n)).unwrap();
+    }
+    db.drop_column("t", column).unwrap();
+    drop(db);
+
+    let mut db = temp.open();
+    assert!(db.check_integrity().unwrap().is_empty());
+    let names = db
+        .get_table_def("t")
+        .unwrap()
+        .into_iter()
+        .map(|def| def.name)
+        .collect();
+    let rows = db.select::<Condition<&[u8]>>("t", &[]).unwrap();
+    (names, rows)
+}
+
+#[test]
+fn dropping_the_first_column() {
+    let (names, rows) = rows_without("a");
+    assert_eq!(names, ["b", "c"]);
+    let expected: Vec<Vec<u8>> = (1..=200).map(|n| row(n)[2..].to_vec()).collect();
+    assert_eq!(rows, expected);
+}
+
+#[test]
+fn dropping_a_middle_column() {
+    let (names, rows) = rows_without("b");
+    assert_eq!(names, ["a", "c"]);
+    let expected: Vec<Vec<u8>> = (1..=200)
+        .map(|n| [&row(n)[..2], &row(n)[5..]].concat())
+        .collect();
+    assert_eq!(rows, expected);
+}
+
+#[test]
+fn dropping_the_last_column() {
+    let (names, rows) = rows_without("c");
+    assert_eq!(names, ["a", "b"]);
+    let expected: Vec<Vec<u8>> = (1..=200).map(|n| row(n)[..5].to_vec()).collect();
+    assert_eq!(rows, expected);
+}
+
+#[test]
+fn unknown_and_only_columns_are_refused() {
+    let temp = TempDb::new("drop-column-refused");
+    let mut db = temp.open();
+    db.create_table("t", &defs()[..1]).unwrap();
+    assert!(db.drop_column("t", "z").is_err());
+    assert!(db.drop_column("t", "a").is_err());
+    assert_eq!(db.get_table_def("t").unwrap().len(), 1);
+}
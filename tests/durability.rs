@@ -0,0 +1,71 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Database, DatabaseOptions, DurabilityMode};
+
+fn options(durability: DurabilityMode) -> DatabaseOptions {
+    DatabaseOptions {
+        durability,
+        ..DatabaseOptions::default()
+    }
+}
+
+/// The keys of the rows in the file, as another opener sees them.
+fn durable_ids(temp: &TempDb) -> Option<Vec<u32>> {
+    let mut db = Database::open_readonly(temp.path()).unwrap();
+    if !db.table_exists("items") {
+        return None;
+    }
+    let mut res = ids(&db.select::<Condition<&[u8]>>("items", &[]).unwrap());
+    res.sort_unstable();
+    Some(res)
+}
+
+#[test]
+fn manual_writes_reach_the_file_only_on_sync() {
+    let temp = TempDb::new("manual");
+    let file = std::fs::read(temp.path()).unwrap();
+    {
+        let mut db =
+            Database::open_with_options(temp.path(), options(DurabilityMode::Manual)).unwrap();
+        db.create_table("items", &item_defs()).unwrap();
+        let rows: Vec<u8> = (0..100).flat_map(item).collect();
+        db.insert_many("items", &rows).unwrap();
+        db.delete("items", &[Condition::eq(0..4, &item(5)[..4])])
+            .unwrap();
+        // the database reads its own writes
+        assert_eq!(db.count::<Condition<&[u8]>>("items", &[]).unwrap(), 99);
+        assert_eq!(std::fs::read(temp.path()).unwrap(), file);
+        assert_eq!(durable_ids(&temp), None);
+
+        db.sync().unwrap();
+        let synced: Vec<u32> = (0..100).filter(|&id| id != 5).collect();
+        assert_eq!(durable_ids(&temp).as_ref(), Some(&synced));
+
+        db.insert("items", &item(200)).unwrap();
+        assert_eq!(durable_ids(&temp).as_ref(), Some(&synced));
+    }
+    // dropped without a sync
+    let synced: Vec<u32> = (0..100).filter(|&id| id != 5).collect();
+    assert_eq!(durable_ids(&temp), Some(synced));
+}
+
+#[test]
+fn flush_per_op_writes_reach_the_file_at_once() {
+    let temp = TempDb::new("flush-per-op");
+    let mut db =
+        Database::open_with_options(temp.path(), options(DurabilityMode::FlushPerOp)).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    assert_eq!(durable_ids(&temp), Some(vec![1]));
+}
+
+#[test]
+fn manual_mode_rejects_the_write_ahead_log() {
+    let temp = TempDb::new("manual-wal");
+    let options = DatabaseOptions {
+        wal: true,
+        ..options(DurabilityMode::Manual)
+    };
+    assert!(Database::open_with_options(temp.path(), options).is_err());
+}
@@ -0,0 +1,70 @@
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, ColumnDef, ColumnType, Condition},
+    Database,
+};
+
+/// 1000 rows, keys 0 to 999 and groups of 10 keys each.
+fn uniform_db() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    let defs = [
+        ColumnDef {
+            name: "key",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "group",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+    ];
+    db.create_table("uniform", &defs).unwrap();
+    let rows: Vec<u8> = (0..1000)
+        .flat_map(|key| [be_key_u32(key), be_key_u32(key % 100)].concat())
+        .collect();
+    db.insert_many("uniform", &rows).unwrap();
+    db
+}
+
+/// Whether `estimate` is within a factor of two of `actual`.
+fn close(estimate: usize, actual: usize) -> bool {
+    estimate * 2 >= actual && estimate <= actual * 2
+}
+
+#[test]
+fn estimates_are_close_on_uniform_data() {
+    let mut db = uniform_db();
+    let stats = db.analyze_table("uniform").unwrap();
+    // the row of zeros is not stored
+    assert_eq!(stats.rows, 999);
+    assert_eq!(stats.columns[1].distinct, 100);
+
+    let queries = [
+        vec![Condition::lt(0..4, be_key_u32(250))],
+        vec![Condition::gt(0..4, be_key_u32(900))],
+        vec![
+            Condition::gt(0..4, be_key_u32(200)),
+            Condition::lt(0..4, be_key_u32(700)),
+        ],
+        vec![Condition::eq(4..8, be_key_u32(42))],
+        vec![
+            Condition::lt(0..4, be_key_u32(500)),
+            Condition::eq(4..8, be_key_u32(7)),
+        ],
+    ];
+    for conditions in queries {
+        let estimate = db.estimate_matches("uniform", &conditions).unwrap();
+        let actual = db.count("uniform", &conditions).unwrap();
+        assert!(close(estimate, actual), "{estimate} for {actual}");
+    }
+}
+
+#[test]
+fn estimates_need_analyzed_tables() {
+    let mut db = uniform_db();
+    let all: [Condition<&[u8]>; 0] = [];
+    assert!(db.estimate_matches("uniform", &all).is_err());
+    db.analyze_table("uniform").unwrap();
+    assert_eq!(db.estimate_matches("uniform", &all).unwrap(), 999);
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+/// Tables created in `order`, each holding rows with the `ids`, inserted in order.
+fn tables(order: &[&str], ids: impl Iterator<Item = u32> + Clone) -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    for table in order {
+        db.create_table(table, &item_defs()).unwrap();
+        for id in ids.clone() {
+            db.insert(table, &item(id)).unwrap();
+        }
+    }
+    db
+}
+
+#[test]
+fn backups_share_the_fingerprint_until_modified() {
+    let original = TempDb::new("fingerprint");
+    let restored = TempDb::path_only("fingerprint-restored");
+    let mut db = original.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert_many("items", &(1..=100).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    let mut backup = Vec::new();
+    db.backup(&mut backup).unwrap();
+    Database::restore(restored.path(), &backup[..]).unwrap();
+
+    let mut copy = restored.open();
+    assert_eq!(copy.fingerprint().unwrap(), db.fingerprint().unwrap());
+    copy.update(
+        "items",
+        &[Condition::eq(0..4, be_key_u32(50))],
+        &[Condition::eq(4..5, [0xff])],
+    )
+    .unwrap();
+    assert_ne!(copy.fingerprint().unwrap(), db.fingerprint().unwrap());
+}
+
+#[test]
+fn physical_layout_does_not_matter() {
+    let mut a = tables(&["x", "y"], 1..=100);
+    let mut b = tables(&["y", "x"], (1..=100).rev());
+    assert_eq!(a.fingerprint().unwrap(), b.fingerprint().unwrap());
+
+    // moving a row between tables changes the multisets
+    a.delete("x", &[Condition::eq(0..4, be_key_u32(7))])
+        .unwrap();
+    a.insert("y", &item(7)).unwrap();
+    assert_ne!(a.fingerprint().unwrap(), b.fingerprint().unwrap());
+}
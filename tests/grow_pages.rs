@@ -0,0 +1,83 @@
+mod common;
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs, io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::{Allocator, Database, DatabaseOptions, LinearAllocator};
+
+/// File lengths, in pages, seen after each insert of 1500 rows.
+fn file_pages(temp: &TempDb, options: DatabaseOptions) -> BTreeSet<u64> {
+    let mut db = Database::open_with_options(temp.path(), options).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let mut lengths = BTreeSet::new();
+    for id in 1..=1500 {
+        db.insert("items", &item(id)).unwrap();
+        lengths.insert(fs::metadata(temp.path()).unwrap().len() / 4096);
+    }
+    lengths
+}
+
+#[test]
+fn files_grow_a_chunk_at_a_time() {
+    let temp = TempDb::new("grow");
+    let options = DatabaseOptions {
+        grow_pages: 16,
+        ..DatabaseOptions::default()
+    };
+    // the header page, then 16 pages at a time for the def, meta and 24 data pages
+    assert_eq!(file_pages(&temp, options), BTreeSet::from([17, 33]));
+}
+
+#[test]
+fn growth_stops_at_max_pages() {
+    let temp = TempDb::new("grow-max");
+    let options = DatabaseOptions {
+        grow_pages: 16,
+        max_pages: Some(30),
+        ..DatabaseOptions::default()
+    };
+    assert_eq!(file_pages(&temp, options), BTreeSet::from([17, 30]));
+}
+
+/// The default allocator, counting the calls.
+#[derive(Debug, Default)]
+struct Counting(AtomicUsize);
+
+impl Allocator for Counting {
+    fn allocate(
+        &self,
+        in_use_pages: &HashSet<i32>,
+        hint: i32,
+        max_pages: Option<i32>,
+    ) -> io::Result<i32> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        LinearAllocator.allocate(in_use_pages, hint, max_pages)
+    }
+}
+
+#[test]
+fn grown_pages_are_handed_out_lowest_first() {
+    let temp = TempDb::new("grow-list");
+    let allocator = Arc::new(Counting::default());
+    let options = DatabaseOptions {
+        grow_pages: 16,
+        allocator: allocator.clone(),
+        ..DatabaseOptions::default()
+    };
+    let mut db = Database::open_with_options(temp.path(), options).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=14 * 64).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+
+    // only the def page grew the file, the other pages come from the list
+    assert_eq!(allocator.0.load(Ordering::Relaxed), 1);
+    assert_eq!(fs::metadata(temp.path()).unwrap().len() / 4096, 17);
+    assert_eq!(db.data_pages("items").unwrap(), (3..17).collect::<Vec<_>>());
+}
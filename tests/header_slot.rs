@@ -0,0 +1,41 @@
+mod common;
+
+use std::fs;
+
+use common::{item_defs, TempDb};
+
+/// Page 0 after creating `b` at slot 2 and then `a` at slot 0 in a new file:
+/// `[len][name][def i32][meta i32]` records, zeros elsewhere.
+fn golden() -> Vec<u8> {
+    let mut page = vec![0; 4096];
+    page[..10].copy_from_slice(&[1, b'a', 0, 0, 0, 3, 0, 0, 0, 4]);
+    page[64..74].copy_from_slice(&[1, b'b', 0, 0, 0, 1, 0, 0, 0, 2]);
+    page
+}
+
+#[test]
+fn tables_land_in_the_chosen_slots() {
+    let temp = TempDb::new("header-slot");
+    let mut db = temp.open();
+    db.create_table_at("b", &item_defs(), 2).unwrap();
+    db.create_table_at("a", &item_defs(), 0).unwrap();
+    drop(db);
+
+    let bytes = fs::read(temp.path()).unwrap();
+    assert_eq!(bytes[..4096], golden());
+}
+
+#[test]
+fn occupied_slots_are_refused() {
+    let temp = TempDb::new("header-slot-taken");
+    let mut db = temp.open();
+    db.create_table_at("a", &item_defs(), 1).unwrap();
+    assert!(db.create_table_at("b", &item_defs(), 1).is_err());
+    // the default still takes the first free slot
+    db.create_table("b", &item_defs()).unwrap();
+    drop(db);
+
+    let bytes = fs::read(temp.path()).unwrap();
+    assert_eq!(&bytes[..2], [1, b'b']);
+    assert_eq!(&bytes[32..34], [1, b'a']);
+}
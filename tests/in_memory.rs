@@ -0,0 +1,42 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, Condition, BLOB_POINTER_LEN},
+    CreateOptions, Database, DatabaseOptions,
+};
+
+#[test]
+fn in_memory_databases_are_independent() {
+    let mut a = Database::open_in_memory().unwrap();
+    let mut b = Database::open_in_memory().unwrap();
+    a.create_table("items", &item_defs()).unwrap();
+    b.create_table("items", &item_defs()).unwrap();
+    a.insert_many("items", &(1..=200).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    b.insert("items", &item(1000)).unwrap();
+
+    let rows = a.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&rows), (1..=200).collect::<Vec<_>>());
+    let rows = b.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&rows), [1000]);
+    assert!(a.check_integrity().unwrap().is_empty());
+}
+
+#[test]
+fn file_only_features_are_refused() {
+    let options = DatabaseOptions {
+        wal: true,
+        ..DatabaseOptions::default()
+    };
+    assert!(Database::open_in_memory_with_options(CreateOptions::default(), options).is_err());
+
+    let mut db = Database::open_in_memory().unwrap();
+    let defs = [ColumnDef {
+        name: "data",
+        column_type: ColumnType::Blob,
+        size: BLOB_POINTER_LEN,
+    }];
+    db.create_table("blobs", &defs).unwrap();
+    assert!(db.insert_values("blobs", &[&[1; 100][..]]).is_err());
+}
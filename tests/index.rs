@@ -0,0 +1,124 @@
+mod common;
+
+use common::{item_defs, TempDb};
+use rustub_storage::{
+    storage::Storage,
+    table::{be_key_u32, Condition, DeletePolicy, TableOptions},
+    Database,
+};
+
+/// A row keyed `key`, whose payload is derived from `n`,
+/// so that keys repeat but rows do not.
+fn keyed(key: u32, n: u32) -> Vec<u8> {
+    let mut row = be_key_u32(key).to_vec();
+    row.extend((0..60).map(|i| (n as u8).wrapping_add(i)));
+    row[4..8].copy_from_slice(&n.to_be_bytes());
+    row
+}
+
+fn sorted(mut rows: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    rows.sort_unstable();
+    rows
+}
+
+/// Check that indexed lookups of keys `0..keys` find the rows a scan finds.
+fn assert_index_matches_scan(db: &mut Database<impl Storage>, keys: u32) {
+    for key in 0..keys {
+        let condition = [Condition::eq(0..4, be_key_u32(key))];
+        let indexed = sorted(db.select("items", &condition).unwrap());
+        let scanned = sorted(
+            db.select_iter("items", &condition)
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap(),
+        );
+        assert_eq!(indexed, scanned, "key {key}");
+    }
+}
+
+/// Fill `items` with 300 rows over 50 keys, index it,
+/// then delete, update and insert rows, checking the index after each step.
+fn exercise_index(db: &mut Database<impl Storage>) {
+    let rows: Vec<u8> = (0..300).flat_map(|n| keyed(n % 50, n)).collect();
+    db.insert_many("items", &rows).unwrap();
+    db.create_index("items", 0..4).unwrap();
+    assert_index_matches_scan(db, 51);
+    assert_eq!(
+        db.select("items", &[Condition::eq(0..4, be_key_u32(7))])
+            .unwrap()
+            .len(),
+        6
+    );
+
+    let deleted = db
+        .delete("items", &[Condition::eq(0..4, be_key_u32(3))])
+        .unwrap();
+    assert_eq!(deleted, 6);
+    db.delete("items", &[Condition::lt(4..8, 20u32.to_be_bytes())])
+        .unwrap();
+    assert_index_matches_scan(db, 51);
+
+    let updated = db
+        .update(
+            "items",
+            &[Condition::eq(0..4, be_key_u32(7))],
+            &[Condition::eq(0..4, be_key_u32(50))],
+        )
+        .unwrap();
+    // the row with n = 7 is gone
+    assert_eq!(updated, 5);
+    db.insert("items", &keyed(3, 1000)).unwrap();
+    assert_index_matches_scan(db, 51);
+    assert!(db
+        .select("items", &[Condition::eq(0..4, be_key_u32(7))])
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn indexed_select_matches_a_scan_under_every_delete_policy() {
+    for delete_policy in [
+        DeletePolicy::ZeroFill,
+        DeletePolicy::Tombstone,
+        DeletePolicy::PresenceFlag,
+    ] {
+        let mut db = Database::open_in_memory().unwrap();
+        let options = TableOptions {
+            delete_policy,
+            ..TableOptions::default()
+        };
+        db.create_table_with_options("items", &item_defs(), &options)
+            .unwrap();
+        exercise_index(&mut db);
+    }
+}
+
+#[test]
+fn indexed_select_matches_a_scan_after_vacuum_and_reopen() {
+    let temp = TempDb::new("index");
+    {
+        let mut db = temp.open();
+        db.create_table("items", &item_defs()).unwrap();
+        exercise_index(&mut db);
+        db.delete("items", &[Condition::gt(0..4, be_key_u32(10))])
+            .unwrap();
+        assert!(db.vacuum("items").unwrap() > 0);
+        assert_index_matches_scan(&mut db, 51);
+    }
+    let mut db = temp.open();
+    assert_index_matches_scan(&mut db, 51);
+    let rows = db
+        .select("items", &[Condition::eq(0..4, be_key_u32(3))])
+        .unwrap();
+    assert_eq!(rows, [keyed(3, 1000)]);
+}
+
+#[test]
+fn dropped_indexes_fall_back_to_a_scan() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    exercise_index(&mut db);
+    assert!(db.drop_index("items", 0..4).unwrap());
+    assert!(!db.drop_index("items", 0..4).unwrap());
+    assert_index_matches_scan(&mut db, 51);
+}
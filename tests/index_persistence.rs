@@ -0,0 +1,57 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+fn lookup(db: &mut Database, id: u32) -> Vec<Vec<u8>> {
+    db.select("items", &[Condition::eq(0..4, be_key_u32(id))])
+        .unwrap()
+}
+
+fn items_db(temp: &TempDb) -> Database {
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (0..500).flat_map(|id| item(id % 100)).collect();
+    db.insert_many("items", &rows).unwrap();
+    db.create_index("items", 0..4).unwrap();
+    db
+}
+
+#[test]
+fn indexes_are_loaded_on_open() {
+    let temp = TempDb::new("index-reopen");
+    let before = {
+        let mut db = items_db(&temp);
+        (0..100).map(|id| lookup(&mut db, id)).collect::<Vec<_>>()
+    };
+    assert!(before.iter().all(|rows| rows.len() == 5));
+
+    let mut db = temp.open();
+    let after: Vec<_> = (0..100).map(|id| lookup(&mut db, id)).collect();
+    assert_eq!(after, before);
+    // the index is known without being created again
+    assert!(db.drop_index("items", 0..4).unwrap());
+    drop(db);
+    let mut db = temp.open();
+    assert!(!db.drop_index("items", 0..4).unwrap());
+}
+
+#[test]
+fn indexes_left_stale_are_built_again() {
+    let temp = TempDb::new("index-stale");
+    let mut db = items_db(&temp);
+    db.sync().unwrap();
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(42))])
+        .unwrap();
+    db.insert("items", &item(1000)).unwrap();
+    // gone without saving the index again
+    std::mem::forget(db);
+
+    let mut db = temp.open();
+    assert!(lookup(&mut db, 42).is_empty());
+    assert_eq!(ids(&lookup(&mut db, 1000)), [1000]);
+    assert_eq!(lookup(&mut db, 41).len(), 5);
+}
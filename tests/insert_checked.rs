@@ -0,0 +1,76 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{Condition, DatabaseError},
+    Database,
+};
+
+fn items() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db
+}
+
+fn assert_payload_mismatch(err: DatabaseError, got: usize) {
+    assert!(
+        matches!(
+            &err,
+            DatabaseError::ColumnLenMismatch { column, expected: 60, got: g }
+                if column == "payload" && *g == got
+        ),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn values_of_the_column_size_are_joined() {
+    let mut db = items();
+    let row = item(7);
+    db.insert_checked("items", &[&row[..4], &row[4..]]).unwrap();
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&rows), [7]);
+    assert_eq!(rows[0], row);
+}
+
+#[test]
+fn short_and_long_values_are_rejected() {
+    let mut db = items();
+    let row = item(7);
+    let err = db.insert_checked("items", &[&row[..4], &row[4..63]]);
+    assert_payload_mismatch(err.unwrap_err(), 59);
+    let long = [row.as_slice(), &[1]].concat();
+    let err = db.insert_checked("items", &[&row[..4], &long[4..]]);
+    assert_payload_mismatch(err.unwrap_err(), 61);
+    // a short key made up by a long payload is still caught
+    let err = db
+        .insert_checked("items", &[&row[..3], &long[3..]])
+        .unwrap_err();
+    assert!(
+        matches!(&err, DatabaseError::ColumnLenMismatch { column, .. } if column == "id"),
+        "{err:?}"
+    );
+    assert!(db.insert_checked("items", &[&row[..]]).is_err());
+    assert!(db
+        .select::<Condition<&[u8]>>("items", &[])
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn plain_inserts_report_both_lengths() {
+    let mut db = items();
+    let err = db.insert("items", &[1; 10]).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DatabaseError::RowLenMismatch {
+                expected: 64,
+                got: 10,
+                ..
+            }
+        ),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    storage::Storage,
+    table::{Condition, TableInfo},
+    Database,
+};
+
+fn info(db: &Database<impl Storage>) -> TableInfo {
+    db.tables_info()
+        .into_iter()
+        .find(|(name, _)| name == "items")
+        .unwrap()
+        .1
+}
+
+#[test]
+fn batches_fill_the_last_page_then_spill() {
+    let temp = TempDb::new("insert-many");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=40 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    assert_eq!(info(&db).page_count, 1);
+
+    // 64 rows fit a page, so 24 go to the first page and 26 to a new one
+    let batch: Vec<u8> = (41..=90).flat_map(item).collect();
+    assert_eq!(db.insert_many("items", &batch).unwrap(), 50);
+    assert_eq!(info(&db).page_count, 2);
+    drop(db);
+
+    let mut db = temp.open();
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&rows), (1..=90).collect::<Vec<_>>());
+}
+
+#[test]
+fn partial_rows_are_rejected() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let mut batch: Vec<u8> = (1..=3).flat_map(item).collect();
+    batch.pop();
+    assert!(db.insert_many("items", &batch).is_err());
+    assert!(db
+        .select::<Condition<&[u8]>>("items", &[])
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition, RowId},
+    Database,
+};
+
+#[test]
+fn only_inserts_opening_a_page_allocate() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+
+    let first = db.insert_report("items", &item(1)).unwrap();
+    assert!(first.allocated_new_page);
+    assert_eq!(first.row_id.slot, 0);
+    let page = first.row_id.page;
+    assert_eq!(db.data_pages("items").unwrap(), [page]);
+    for id in 2..=64 {
+        let report = db.insert_report("items", &item(id)).unwrap();
+        assert!(!report.allocated_new_page, "row {id}");
+        assert_eq!(report.row_id, RowId { page, slot: id - 1 });
+    }
+
+    let next = db.insert_report("items", &item(65)).unwrap();
+    assert!(next.allocated_new_page);
+    assert_ne!(next.row_id.page, page);
+    // a freed slot is reused without allocating
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(10))])
+        .unwrap();
+    let reused = db.insert_report("items", &item(66)).unwrap();
+    assert!(!reused.allocated_new_page);
+    assert_eq!(reused.row_id, RowId { page, slot: 9 });
+    let rows = db.select_pos("items", page, 9..10).unwrap();
+    assert_eq!(rows, [item(66)]);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{Condition, DatabaseError, StorageError},
+    CreateOptions, Database, DatabaseOptions,
+};
+
+fn capped(max_pages: i32) -> DatabaseOptions {
+    DatabaseOptions {
+        max_pages: Some(max_pages),
+        ..DatabaseOptions::default()
+    }
+}
+
+fn is_quota_exceeded(err: &DatabaseError) -> bool {
+    matches!(err, DatabaseError::Storage(StorageError::QuotaExceeded))
+}
+
+#[test]
+fn allocations_fail_at_the_cap_and_rows_stay_readable() {
+    let temp = TempDb::new("max-pages");
+    // the header page, the def and meta pages of items and two data pages
+    let mut db = Database::open_with_options(temp.path(), capped(5)).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let mut inserted = 0;
+    let err = loop {
+        match db.insert("items", &item(inserted + 1)) {
+            Ok(()) => inserted += 1,
+            Err(err) => break err,
+        }
+    };
+    assert!(is_quota_exceeded(&err), "{err:?}");
+    assert_eq!(inserted, 128);
+    let err = db.create_table("more", &item_defs()).unwrap_err();
+    assert!(is_quota_exceeded(&err), "{err:?}");
+
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&rows), (1..=128).collect::<Vec<_>>());
+    // the file never grew past the cap
+    assert!(std::fs::metadata(temp.path()).unwrap().len() <= 5 * 4096);
+    drop(db);
+    assert_eq!(
+        temp.open().count::<Condition<&[u8]>>("items", &[]).unwrap(),
+        128
+    );
+}
+
+#[test]
+fn freed_pages_can_be_allocated_again_under_the_cap() {
+    let mut db =
+        Database::open_in_memory_with_options(CreateOptions::default(), capped(5)).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=128).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    assert!(db.insert("items", &item(129)).is_err());
+
+    db.truncate("items").unwrap();
+    db.insert_many("items", &rows).unwrap();
+    assert_eq!(db.count::<Condition<&[u8]>>("items", &[]).unwrap(), 128);
+}
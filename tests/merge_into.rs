@@ -0,0 +1,81 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, Condition, DatabaseError},
+    Database, DatabaseOptions,
+};
+
+fn all(db: &mut Database<impl rustub_storage::storage::Storage>, table: &str) -> Vec<u32> {
+    let mut res = ids(&db.select::<Condition<&[u8]>>(table, &[]).unwrap());
+    res.sort_unstable();
+    res
+}
+
+#[test]
+fn merge_moves_the_union_and_drops_the_source() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("dst", &item_defs()).unwrap();
+    db.create_table("src", &item_defs()).unwrap();
+    let dst_rows: Vec<u8> = (0..100).flat_map(item).collect();
+    let src_rows: Vec<u8> = (100..250).flat_map(item).collect();
+    db.insert_many("dst", &dst_rows).unwrap();
+    db.insert_many("src", &src_rows).unwrap();
+
+    assert_eq!(db.merge_into("dst", "src").unwrap(), 150);
+    assert_eq!(all(&mut db, "dst"), (0..250).collect::<Vec<_>>());
+    assert!(!db.list_tables().contains(&"src".to_string()));
+    assert!(matches!(
+        db.select::<Condition<&[u8]>>("src", &[]),
+        Err(DatabaseError::TableNotFound)
+    ));
+    // the rows are whole
+    let row = db
+        .select("dst", &[Condition::eq(0..4, &item(200)[..4])])
+        .unwrap();
+    assert_eq!(row, [item(200)]);
+}
+
+#[test]
+fn merge_needs_the_same_columns() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("dst", &item_defs()).unwrap();
+    let other = [ColumnDef {
+        name: "id",
+        column_type: ColumnType::UInt,
+        size: 4,
+    }];
+    db.create_table("src", &other).unwrap();
+    db.insert("src", &[0, 0, 0, 1]).unwrap();
+
+    assert!(db.merge_into("dst", "src").is_err());
+    assert!(db.merge_into("dst", "dst").is_err());
+    assert!(all(&mut db, "dst").is_empty());
+    assert_eq!(db.select::<Condition<&[u8]>>("src", &[]).unwrap().len(), 1);
+}
+
+#[test]
+fn merges_that_do_not_fit_are_rolled_back() {
+    let options = DatabaseOptions {
+        max_pages: Some(8),
+        ..DatabaseOptions::default()
+    };
+    let mut db = Database::open_in_memory_with_options(Default::default(), options).unwrap();
+    db.create_table("dst", &item_defs()).unwrap();
+    db.create_table("src", &item_defs()).unwrap();
+    let src_rows: Vec<u8> = (0..100).flat_map(item).collect();
+    db.insert_many("src", &src_rows).unwrap();
+    // the header, def, meta and source pages leave one page for dst
+    let free = db.stats().unwrap().free_pages;
+
+    let err = db.merge_into("dst", "src").unwrap_err();
+    assert!(err.to_string().contains("64 of 100 rows"), "{err}");
+    assert!(all(&mut db, "dst").is_empty());
+    assert_eq!(all(&mut db, "src"), (0..100).collect::<Vec<_>>());
+    assert_eq!(db.stats().unwrap().free_pages, free);
+
+    // and one transaction at a time
+    db.begin().unwrap();
+    assert!(db.merge_into("dst", "src").is_err());
+    db.rollback().unwrap();
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Database};
+
+fn all(db: &mut Database, table_name: &str) -> Vec<u32> {
+    ids(&db.select::<Condition<&[u8]>>(table_name, &[]).unwrap())
+}
+
+#[test]
+fn moved_tables_keep_their_rows_on_lower_pages() {
+    let temp = TempDb::new("move-table");
+    let mut db = temp.open();
+    db.create_table("filler", &item_defs()).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    // the data pages of items alternate with those of filler
+    for chunk in 0..4 {
+        let rows: Vec<u8> = (chunk * 64 + 1..=chunk * 64 + 64).flat_map(item).collect();
+        db.insert_many("filler", &rows).unwrap();
+        db.insert_many("items", &rows).unwrap();
+    }
+    db.drop("filler").unwrap();
+    let before = db.data_pages("items").unwrap();
+    assert_eq!(before.len(), 4);
+
+    db.move_table("items").unwrap();
+    let after = db.data_pages("items").unwrap();
+    assert_eq!(after.len(), 4);
+    assert!(
+        after.iter().max() < before.iter().max(),
+        "{before:?} {after:?}"
+    );
+    // the data pages follow the def and meta pages in one run
+    assert!(after.windows(2).all(|pages| pages[1] == pages[0] + 1));
+    assert_eq!(all(&mut db, "items"), (1..=256).collect::<Vec<_>>());
+
+    db.insert("items", &item(257)).unwrap();
+    drop(db);
+    let mut db = temp.open();
+    assert_eq!(db.data_pages("items").unwrap()[..4], after);
+    assert_eq!(all(&mut db, "items"), (1..=257).collect::<Vec<_>>());
+    assert!(db.check_integrity().unwrap().is_empty());
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use std::fs;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Database};
+
+#[test]
+fn missing_files_are_created() {
+    let temp = TempDb::path_only("open-or-create");
+    assert!(!temp.path().exists());
+    let mut db = Database::open_or_create(temp.path()).unwrap();
+    assert!(db.list_tables().is_empty());
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+}
+
+#[test]
+fn existing_files_are_opened_as_they_are() {
+    let temp = TempDb::new("open-or-create-existing");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    drop(db);
+
+    let mut db = Database::open_or_create(temp.path()).unwrap();
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(rows, [item(1)]);
+}
+
+#[test]
+fn corrupt_files_are_not_replaced() {
+    let temp = TempDb::path_only("open-or-create-corrupt");
+    fs::write(temp.path(), b"not a database").unwrap();
+    assert!(Database::open_or_create(temp.path()).is_err());
+    assert_eq!(fs::read(temp.path()).unwrap(), b"not a database");
+}
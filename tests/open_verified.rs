@@ -0,0 +1,57 @@
+mod common;
+
+use std::fs;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::Database;
+
+/// A database with one `items` table, returning its meta page.
+fn items_file(temp: &TempDb) -> usize {
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    drop(db);
+    // [len]["items"][def i32][meta i32]
+    let bytes = fs::read(temp.path()).unwrap();
+    i32::from_be_bytes(bytes[10..14].try_into().unwrap()) as usize
+}
+
+/// Point the second meta record of the table at `page`.
+fn corrupt(temp: &TempDb, meta_page: usize, page: i32) {
+    let mut bytes = fs::read(temp.path()).unwrap();
+    let pos = meta_page * 4096 + 32;
+    bytes[pos..pos + 4].copy_from_slice(&page.to_be_bytes());
+    fs::write(temp.path(), bytes).unwrap();
+}
+
+#[test]
+fn sound_files_open() {
+    let temp = TempDb::new("verified");
+    items_file(&temp);
+    let mut db = Database::open_verified(temp.path()).unwrap();
+    assert!(db.check_integrity().unwrap().is_empty());
+}
+
+#[test]
+fn pages_outside_the_file_are_reported() {
+    let temp = TempDb::new("verified-outside");
+    let meta_page = items_file(&temp);
+    corrupt(&temp, meta_page, 9999);
+
+    // a plain open does not notice
+    assert!(Database::open(temp.path()).is_ok());
+    let err = Database::open_verified(temp.path()).err().unwrap();
+    assert!(format!("{err:?}").contains("page 9999 of table items is outside the file"));
+}
+
+#[test]
+fn pages_owned_twice_are_reported() {
+    let temp = TempDb::new("verified-shared");
+    let meta_page = items_file(&temp);
+    corrupt(&temp, meta_page, meta_page as i32);
+
+    let issues = temp.open().check_integrity().unwrap();
+    assert_eq!(issues.len(), 1, "{issues:?}");
+    assert!(issues[0].contains("also belongs to items"), "{issues:?}");
+    assert!(Database::open_verified(temp.path()).is_err());
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DeletePolicy, TableOptions},
+    Database,
+};
+
+#[test]
+fn fills_match_the_rows_left_after_deletes() {
+    for delete_policy in [DeletePolicy::ZeroFill, DeletePolicy::Tombstone] {
+        let mut db = Database::open_in_memory().unwrap();
+        let options = TableOptions {
+            delete_policy,
+            ..TableOptions::default()
+        };
+        db.create_table_with_options("items", &item_defs(), &options)
+            .unwrap();
+        let rows: Vec<u8> = (1..=256).flat_map(item).collect();
+        db.insert_many("items", &rows).unwrap();
+
+        // the first page loses every other row, the third one all of them
+        for id in (2..=64).step_by(2) {
+            db.delete("items", &[Condition::eq(0..4, be_key_u32(id))])
+                .unwrap();
+        }
+        db.delete(
+            "items",
+            &[
+                Condition::gt(0..4, be_key_u32(128)),
+                Condition::lt(0..4, be_key_u32(193)),
+            ],
+        )
+        .unwrap();
+        db.delete("items", &[Condition::eq(0..4, be_key_u32(256))])
+            .unwrap();
+
+        let fill = db.page_fill("items").unwrap();
+        let counts: Vec<usize> = fill.iter().map(|(_, count)| *count).collect();
+        let pages: Vec<i32> = fill.iter().map(|(page, _)| *page).collect();
+        assert_eq!(pages, db.data_pages("items").unwrap());
+        match delete_policy {
+            // the emptied page is freed
+            DeletePolicy::ZeroFill => assert_eq!(counts, [32, 64, 63]),
+            // tombstoned pages keep their slots
+            _ => assert_eq!(counts, [32, 64, 0, 63]),
+        }
+        let live = db.count::<Condition<&[u8]>>("items", &[]).unwrap();
+        assert_eq!(counts.iter().sum::<usize>(), live);
+    }
+}
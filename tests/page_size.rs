@@ -0,0 +1,47 @@
+mod common;
+
+use std::fs;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Database};
+
+/// Store 300 items in a database of `page_size` byte pages,
+/// returning how many data pages they took after a reopen.
+fn round_trip(page_size: u32) -> usize {
+    let temp = TempDb::path_only("page-size");
+    Database::create_database_with_page_size(temp.path(), page_size).unwrap();
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=300 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    drop(db);
+    assert_eq!(
+        fs::metadata(temp.path()).unwrap().len() % page_size as u64,
+        0
+    );
+
+    let mut db = temp.open();
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(ids(&rows), (1..=300).collect::<Vec<_>>());
+    assert!(db.check_integrity().unwrap().is_empty());
+    db.data_pages("items").unwrap().len()
+}
+
+#[test]
+fn databases_of_either_size_reopen() {
+    // 16 and 256 rows of 64 bytes per page
+    assert_eq!(round_trip(1024), 19);
+    assert_eq!(round_trip(16384), 2);
+}
+
+#[test]
+fn bad_sizes_are_rejected() {
+    let temp = TempDb::path_only("page-size-bad");
+    for page_size in [3000, 32, 0, 1 << 17] {
+        assert!(
+            Database::create_database_with_page_size(temp.path(), page_size).is_err(),
+            "{page_size}"
+        );
+    }
+}
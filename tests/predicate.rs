@@ -0,0 +1,71 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, Condition, Predicate},
+    Database,
+};
+
+/// Keys 1 to 30, the payload starting with the key modulo 2 and modulo 3.
+fn items() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=30u32 {
+        let mut row = item(id);
+        row[4] = (id % 2) as u8;
+        row[5] = (id % 3) as u8;
+        db.insert("items", &row).unwrap();
+    }
+    db
+}
+
+/// `(odd AND divisible by 3) OR key = 10`
+fn predicate() -> Predicate<Vec<u8>> {
+    Predicate::Or(vec![
+        Predicate::And(vec![
+            Predicate::Leaf(Condition::eq(4..5, vec![1])),
+            Predicate::Leaf(Condition::eq(5..6, vec![0])),
+        ]),
+        Predicate::Leaf(Condition::eq(0..4, be_key_u32(10).to_vec())),
+    ])
+}
+
+const MATCHING: [u32; 6] = [3, 9, 10, 15, 21, 27];
+
+#[test]
+fn select_where_follows_and_or() {
+    let mut db = items();
+    assert_eq!(
+        ids(&db.select_where("items", &predicate()).unwrap()),
+        MATCHING
+    );
+    // empty And accepts every row, empty Or none
+    let all = db.select_where::<Vec<u8>>("items", &Predicate::And(vec![]));
+    assert_eq!(all.unwrap().len(), 30);
+    let none = db.select_where::<Vec<u8>>("items", &Predicate::Or(vec![]));
+    assert!(none.unwrap().is_empty());
+}
+
+#[test]
+fn update_where_follows_and_or() {
+    let mut db = items();
+    let marker = Condition::eq(6..7, [0xff]);
+    let updated = db
+        .update_where("items", &predicate(), std::slice::from_ref(&marker))
+        .unwrap();
+    assert_eq!(updated, MATCHING.len());
+    assert_eq!(ids(&db.select("items", &[marker]).unwrap()), MATCHING);
+}
+
+#[test]
+fn delete_where_follows_and_or() {
+    let mut db = items();
+    assert_eq!(
+        db.delete_where("items", &predicate()).unwrap(),
+        MATCHING.len()
+    );
+    let rest = ids(&db.select::<Condition<&[u8]>>("items", &[]).unwrap());
+    let expected: Vec<u32> = (1..=30).filter(|id| !MATCHING.contains(id)).collect();
+    assert_eq!(rest, expected);
+}
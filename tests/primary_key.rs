@@ -0,0 +1,74 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{Condition, DatabaseError, TableOptions},
+    Database,
+};
+
+fn keyed_options() -> TableOptions {
+    TableOptions {
+        primary_key: Some(0),
+        ..TableOptions::default()
+    }
+}
+
+fn all(db: &mut Database<impl rustub_storage::storage::Storage>) -> Vec<u32> {
+    let mut res = ids(&db.select::<Condition<&[u8]>>("items", &[]).unwrap());
+    res.sort_unstable();
+    res
+}
+
+#[test]
+fn duplicate_keys_are_rejected() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table_with_options("items", &item_defs(), &keyed_options())
+        .unwrap();
+    db.insert("items", &item(1)).unwrap();
+    db.insert("items", &item(2)).unwrap();
+
+    let err = db.insert("items", &item(1)).unwrap_err();
+    assert!(matches!(err, DatabaseError::DuplicateKey), "{err:?}");
+    // one duplicate fails the whole batch, also within the batch itself
+    let err = db
+        .insert_many("items", &[item(3), item(2)].concat())
+        .unwrap_err();
+    assert!(matches!(err, DatabaseError::DuplicateKey), "{err:?}");
+    let err = db
+        .insert_many("items", &[item(4), item(4)].concat())
+        .unwrap_err();
+    assert!(matches!(err, DatabaseError::DuplicateKey), "{err:?}");
+    assert_eq!(all(&mut db), [1, 2]);
+
+    // a deleted key can be inserted again
+    db.delete("items", &[Condition::eq(0..4, item(1)[..4].to_vec())])
+        .unwrap();
+    db.insert("items", &item(1)).unwrap();
+    assert_eq!(all(&mut db), [1, 2]);
+}
+
+#[test]
+fn tables_without_a_key_take_duplicates() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    assert_eq!(all(&mut db), [1, 1]);
+}
+
+#[test]
+fn primary_key_survives_reopening() {
+    let temp = TempDb::new("primary-key");
+    {
+        let mut db = temp.open();
+        db.create_table_with_options("items", &item_defs(), &keyed_options())
+            .unwrap();
+        db.insert("items", &item(7)).unwrap();
+    }
+    let mut db = temp.open();
+    assert!(db.describe("items").unwrap().contains("primary key: id"));
+    let err = db.insert("items", &item(7)).unwrap_err();
+    assert!(matches!(err, DatabaseError::DuplicateKey), "{err:?}");
+    db.insert("items", &item(8)).unwrap();
+    assert_eq!(all(&mut db), [7, 8]);
+}
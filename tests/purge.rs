@@ -0,0 +1,51 @@
+mod common;
+
+use std::fs;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DeletePolicy, TableOptions},
+    Database, DatabaseOptions,
+};
+
+#[test]
+fn purging_the_last_pages_shrinks_the_file() {
+    for delete_policy in [DeletePolicy::ZeroFill, DeletePolicy::Tombstone] {
+        let temp = TempDb::new("purge");
+        let options = DatabaseOptions {
+            grow_pages: 1,
+            ..DatabaseOptions::default()
+        };
+        let mut db = Database::open_with_options(temp.path(), options).unwrap();
+        let table_options = TableOptions {
+            delete_policy,
+            ..TableOptions::default()
+        };
+        db.create_table_with_options("items", &item_defs(), &table_options)
+            .unwrap();
+        // header, def and meta pages then five data pages
+        let rows: Vec<u8> = (1..=320).flat_map(item).collect();
+        db.insert_many("items", &rows).unwrap();
+        assert_eq!(fs::metadata(temp.path()).unwrap().len(), 8 * 4096);
+
+        // the last two pages and half of the third to last
+        let purged = db
+            .purge("items", &[Condition::gt(0..4, be_key_u32(160))])
+            .unwrap();
+        assert_eq!(purged, 160);
+        assert_eq!(db.data_pages("items").unwrap().len(), 3);
+        assert_eq!(
+            fs::metadata(temp.path()).unwrap().len(),
+            6 * 4096,
+            "{delete_policy:?}"
+        );
+        let all: [Condition<&[u8]>; 0] = [];
+        assert_eq!(
+            ids(&db.select("items", &all).unwrap()),
+            (1..=160).collect::<Vec<_>>()
+        );
+        drop(db);
+        let mut db = temp.open();
+        assert_eq!(db.count("items", &all).unwrap(), 160);
+    }
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, Condition, Filter, RangeCondition},
+    Database,
+};
+
+/// Keys 1 to 20, the payload starting with the key modulo 3.
+fn items() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=20u32 {
+        let mut row = item(id);
+        row[4] = (id % 3) as u8;
+        db.insert("items", &row).unwrap();
+    }
+    db
+}
+
+#[test]
+fn range_and_equality_mix_in_one_query() {
+    let mut db = items();
+    let range = RangeCondition::between(0..4, be_key_u32(5), be_key_u32(15));
+    let equal = Condition::eq(4..5, [1u8]);
+    let filters: [&dyn Filter; 2] = [&range, &equal];
+
+    let rows = db.select("items", &filters).unwrap();
+    assert_eq!(ids(&rows), [7, 10, 13]);
+
+    let marker = Condition::eq(5..6, [0xff]);
+    assert_eq!(
+        db.update("items", &filters, std::slice::from_ref(&marker))
+            .unwrap(),
+        3
+    );
+    assert_eq!(ids(&db.select("items", &[marker]).unwrap()), [7, 10, 13]);
+
+    assert_eq!(db.delete("items", &filters).unwrap(), 3);
+    let rest = ids(&db.select("items", &[range]).unwrap());
+    assert_eq!(rest, [5, 6, 8, 9, 11, 12, 14, 15]);
+}
+
+#[test]
+fn exclusive_bounds_leave_out_the_ends() {
+    let mut db = items();
+    let range = RangeCondition {
+        inclusive: false,
+        ..RangeCondition::between(0..4, be_key_u32(5), be_key_u32(9))
+    };
+    let rows = db.select("items", &[range]).unwrap();
+    assert_eq!(ids(&rows), [6, 7, 8]);
+}
+
+#[test]
+fn empty_slots_are_never_matched() {
+    let mut db = items();
+    // the page has empty, all zero slots after the 20 rows
+    let zeros = RangeCondition::between(0..64, [0u8; 64], [0u8; 64]);
+    assert!(db.select("items", &[zeros]).unwrap().is_empty());
+    let low = RangeCondition::between(0..4, be_key_u32(0), be_key_u32(2));
+    let zero_payload = Condition::eq(4..5, [0u8]);
+    let filters: [&dyn Filter; 2] = [&low, &zero_payload];
+    assert!(db.select("items", &filters).unwrap().is_empty());
+    assert_eq!(db.delete("items", &filters).unwrap(), 0);
+    assert_eq!(db.count::<Condition<&[u8]>>("items", &[]).unwrap(), 20);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+/// Keys 0, 3, 6, ... 597 inserted in a shuffled order over several pages,
+/// then every key divisible by 10 deleted.
+fn shuffled_items() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for i in 0..200u32 {
+        db.insert("items", &item((i * 77 % 200) * 3)).unwrap();
+    }
+    for id in (0..600).step_by(10) {
+        db.delete("items", &[Condition::eq(0..4, be_key_u32(id))])
+            .unwrap();
+    }
+    db
+}
+
+fn expected(range: impl Iterator<Item = u32>) -> Vec<u32> {
+    range.filter(|id| id % 3 == 0 && id % 10 != 0).collect()
+}
+
+fn scan(db: &mut Database<MemoryStorage>, start: u32, end: u32, inclusive: bool) -> Vec<u32> {
+    let rows = db
+        .range_scan("items", 0..4, be_key_u32(start), be_key_u32(end), inclusive)
+        .unwrap();
+    ids(&rows)
+}
+
+#[test]
+fn unindexed_scan_applies_both_bounds() {
+    let mut db = shuffled_items();
+    let mut exclusive = scan(&mut db, 30, 90, false);
+    exclusive.sort_unstable();
+    assert_eq!(exclusive, expected(30..90));
+    let mut inclusive = scan(&mut db, 30, 93, true);
+    inclusive.sort_unstable();
+    assert_eq!(inclusive, expected(30..94));
+    assert!(scan(&mut db, 90, 30, true).is_empty());
+}
+
+#[test]
+fn indexed_scan_returns_rows_in_key_order() {
+    let mut db = shuffled_items();
+    let mut unindexed = scan(&mut db, 100, 400, false);
+    db.create_index("items", 0..4).unwrap();
+
+    let indexed = scan(&mut db, 100, 400, false);
+    assert_eq!(indexed, expected(100..400));
+    unindexed.sort_unstable();
+    assert_eq!(indexed, unindexed);
+    assert_eq!(scan(&mut db, 597, 597, true), [597]);
+    assert!(scan(&mut db, 597, 597, false).is_empty());
+    assert!(scan(&mut db, 400, 100, false).is_empty());
+}
+
+#[test]
+fn indexed_scan_follows_later_writes() {
+    let mut db = shuffled_items();
+    db.create_index("items", 0..4).unwrap();
+    db.delete("items", &[Condition::lt(0..4, be_key_u32(50))])
+        .unwrap();
+    db.insert("items", &item(31)).unwrap();
+    db.vacuum("items").unwrap();
+
+    let mut want = expected(50..100);
+    want.insert(0, 31);
+    assert_eq!(scan(&mut db, 0, 100, false), want);
+    // the rows are whole
+    let rows = db
+        .range_scan("items", 0..4, be_key_u32(31), be_key_u32(31), true)
+        .unwrap();
+    assert_eq!(rows, [item(31)]);
+}
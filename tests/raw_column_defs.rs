@@ -0,0 +1,41 @@
+mod common;
+
+use common::{item_defs, TempDb};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType},
+    Database,
+};
+
+#[test]
+fn identical_tables_have_identical_def_bytes() {
+    let temp = TempDb::new("raw-defs");
+    let mut db = temp.open();
+    db.create_table("a", &item_defs()).unwrap();
+    let mut other = Database::open_in_memory().unwrap();
+    other.create_table("b", &item_defs()).unwrap();
+
+    let raw = db.raw_column_defs("a").unwrap();
+    // one 32 byte record per column
+    assert_eq!(raw.len(), 64);
+    assert_eq!(raw, other.raw_column_defs("b").unwrap());
+    drop(db);
+    assert_eq!(temp.open().raw_column_defs("a").unwrap(), raw);
+}
+
+#[test]
+fn different_tables_have_different_def_bytes() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("a", &item_defs()).unwrap();
+    let mut defs = item_defs();
+    defs[1] = ColumnDef {
+        name: "payload",
+        column_type: ColumnType::Text,
+        size: 60,
+    };
+    db.create_table("b", &defs).unwrap();
+    assert_ne!(
+        db.raw_column_defs("a").unwrap(),
+        db.raw_column_defs("b").unwrap()
+    );
+    assert!(db.raw_column_defs("missing").is_err());
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, ColumnDef, ColumnType, Condition},
+    CreateOptions, Database, DatabaseOptions,
+};
+
+/// Without the page cache, reads only see writes through the reader.
+fn uncached_db() -> Database<MemoryStorage> {
+    let options = DatabaseOptions {
+        cache_pages: 0,
+        ..DatabaseOptions::default()
+    };
+    Database::open_in_memory_with_options(CreateOptions::default(), options).unwrap()
+}
+
+fn all(db: &mut Database<MemoryStorage>, table_name: &str) -> Vec<Vec<u8>> {
+    db.select::<Condition<&[u8]>>(table_name, &[]).unwrap()
+}
+
+#[test]
+fn each_write_is_read_by_the_next_call() {
+    let mut db = uncached_db();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=100 {
+        db.insert("items", &item(id)).unwrap();
+        assert_eq!(all(&mut db, "items").len(), id as usize);
+    }
+
+    let key = [Condition::eq(0..4, be_key_u32(42))];
+    db.update("items", &key, &[Condition::eq(0..4, be_key_u32(142))])
+        .unwrap();
+    assert_eq!(ids(&all(&mut db, "items"))[41], 142);
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(142))])
+        .unwrap();
+    assert_eq!(all(&mut db, "items").len(), 99);
+    // the freed slot is found by reading the page written by delete
+    db.insert("items", &item(42)).unwrap();
+    assert_eq!(ids(&all(&mut db, "items")), (1..=100).collect::<Vec<_>>());
+}
+
+#[test]
+fn appends_build_on_the_previous_append() {
+    let mut db = uncached_db();
+    let defs = [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "log",
+            column_type: ColumnType::Text,
+            size: 12,
+        },
+    ];
+    db.create_table("logs", &defs).unwrap();
+    let mut log = [0; 12];
+    log[0] = b'a';
+    db.insert_values("logs", &[&be_key_u32(1), &log]).unwrap();
+
+    let key = [Condition::eq(0..4, be_key_u32(1))];
+    for suffix in [b"b", b"c", b"d"] {
+        assert_eq!(db.update_append("logs", &key, 4..16, suffix).unwrap(), 1);
+    }
+    assert_eq!(&all(&mut db, "logs")[0][4..8], b"abcd");
+}
+
+#[test]
+fn vacuum_reads_the_rows_it_moved() {
+    let mut db = uncached_db();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=300).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    // every page keeps half its rows
+    for id in (2..=300).step_by(2) {
+        db.delete("items", &[Condition::eq(0..4, be_key_u32(id))])
+            .unwrap();
+    }
+
+    assert_eq!(db.vacuum("items").unwrap(), 2);
+    let odd: Vec<u32> = (1..=300).step_by(2).collect();
+    assert_eq!(ids(&all(&mut db, "items")), odd);
+    let rows: Vec<u8> = (301..=400).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    let expected: Vec<u32> = odd.into_iter().chain(301..=400).collect();
+    assert_eq!(ids(&all(&mut db, "items")), expected);
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use std::fs;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn read_only_handles_query_without_writing() {
+    let temp = TempDb::new("readonly");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=100 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    drop(db);
+    let before = fs::read(temp.path()).unwrap();
+    let mut permissions = fs::metadata(temp.path()).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(temp.path(), permissions.clone()).unwrap();
+
+    let mut db = Database::open_readonly(temp.path()).unwrap();
+    assert_eq!(db.list_tables(), ["items"]);
+    assert!(db.table_exists("items"));
+    assert_eq!(db.get_table_def("items").unwrap().len(), 2);
+    let above = [Condition::gt(0..4, be_key_u32(97))];
+    assert_eq!(ids(&db.select("items", &above).unwrap()), [98, 99, 100]);
+    assert_eq!(db.count("items", &above).unwrap(), 3);
+    let first = db.select_iter("items", &above).unwrap().next();
+    assert_eq!(first.unwrap().unwrap(), item(98));
+    drop(db);
+
+    assert_eq!(fs::read(temp.path()).unwrap(), before);
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    fs::set_permissions(temp.path(), permissions).unwrap();
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{ids, item, TempDb};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, Condition, CreateTableError, DatabaseError},
+    Database,
+};
+
+fn long_defs(name_len: usize) -> [ColumnDef<String>; 2] {
+    [
+        ColumnDef {
+            name: "i".repeat(name_len),
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "payload".to_string(),
+            column_type: ColumnType::Bytes,
+            size: 60,
+        },
+    ]
+}
+
+#[test]
+fn long_records_take_long_names() {
+    let temp = TempDb::path_only("record-len");
+    Database::create_database_with_record_len(temp.path(), 64).unwrap();
+    let mut db = temp.open();
+    let table_name = "t".repeat(50);
+    // column names take up to 60 bytes of a 64 byte record
+    db.create_table(&table_name, &long_defs(60)).unwrap();
+    db.insert(&table_name, &item(1)).unwrap();
+    drop(db);
+
+    let mut db = temp.open();
+    assert_eq!(db.list_tables(), std::slice::from_ref(&table_name));
+    assert_eq!(
+        db.get_table_def(&table_name).unwrap()[0].name,
+        "i".repeat(60)
+    );
+    let rows = db.select::<Condition<&[u8]>>(&table_name, &[]).unwrap();
+    assert_eq!(ids(&rows), [1]);
+}
+
+#[test]
+fn names_still_have_a_limit() {
+    let temp = TempDb::path_only("record-len-limit");
+    Database::create_database_with_record_len(temp.path(), 64).unwrap();
+    let mut db = temp.open();
+    let err = db.create_table(&"t".repeat(56), &long_defs(1)).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DatabaseError::Create(CreateTableError::TableNameInvalid)
+        ),
+        "{err:?}"
+    );
+    assert!(db.create_table("t", &long_defs(61)).is_err());
+    // the default 32 byte records are too short for either
+    let mut small = Database::open_in_memory().unwrap();
+    assert!(small.create_table(&"t".repeat(50), &long_defs(1)).is_err());
+    assert!(Database::create_database_with_record_len(temp.path(), 48).is_err());
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use std::fs;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::table::{be_key_u32, Condition};
+
+#[test]
+fn renamed_tables_keep_their_rows_and_pages() {
+    let temp = TempDb::new("rename");
+    let mut db = temp.open();
+    db.create_table("inventory", &item_defs()).unwrap();
+    for id in 1..=100 {
+        db.insert("inventory", &item(id)).unwrap();
+    }
+    let pages = db.data_pages("inventory").unwrap();
+    let before = fs::read(temp.path()).unwrap();
+    db.rename_table("inventory", "items").unwrap();
+    drop(db);
+
+    let after = fs::read(temp.path()).unwrap();
+    // [len][name][def i32][meta i32], the old name's tail zeroed
+    let mut record = vec![5];
+    record.extend(b"items");
+    record.extend(&before[10..18]);
+    record.resize(32, 0);
+    assert_eq!(after[..32], record);
+    assert_eq!(after[32..], before[32..]);
+
+    let mut db = temp.open();
+    assert_eq!(db.list_tables(), ["items"]);
+    assert_eq!(db.data_pages("items").unwrap(), pages);
+    let rows = db
+        .select("items", &[Condition::lt(0..4, be_key_u32(4))])
+        .unwrap();
+    assert_eq!(ids(&rows), [1, 2, 3]);
+    assert!(db.select::<Condition<&[u8]>>("inventory", &[]).is_err());
+}
+
+#[test]
+fn bad_new_names_are_rejected() {
+    let temp = TempDb::new("rename-bad");
+    let mut db = temp.open();
+    db.create_table("a", &item_defs()).unwrap();
+    db.create_table("b", &item_defs()).unwrap();
+    assert!(db.rename_table("a", "b").is_err());
+    assert!(db.rename_table("a", "").is_err());
+    assert!(db.rename_table("a", &"n".repeat(40)).is_err());
+    assert!(db.rename_table("missing", "c").is_err());
+    assert_eq!(db.list_tables(), ["a", "b"]);
+}
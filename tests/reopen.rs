@@ -0,0 +1,60 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::table::{be_key_u32, Condition};
+
+fn all_ids(temp: &TempDb) -> Vec<u32> {
+    let mut db = temp.open();
+    ids(&db.select::<Condition<&[u8]>>("items", &[]).unwrap())
+}
+
+#[test]
+fn rows_survive_a_reopen() {
+    let temp = TempDb::new("reopen");
+    let mut db = temp.open();
+    // another table first, so data pages are not at the start of the file
+    db.create_table("other", &item_defs()).unwrap();
+    db.insert("other", &item(1000)).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=150 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    let before = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    drop(db);
+
+    let mut db = temp.open();
+    assert_eq!(db.select::<Condition<&[u8]>>("items", &[]).unwrap(), before);
+    assert_eq!(
+        ids(&db
+            .select("other", &[Condition::eq(0..4, be_key_u32(1000))])
+            .unwrap()),
+        [1000]
+    );
+}
+
+#[test]
+fn writes_after_a_reopen_land_in_the_same_pages() {
+    let temp = TempDb::new("reopen-write");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=100 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    drop(db);
+
+    let mut db = temp.open();
+    db.delete("items", &[Condition::lt(0..4, be_key_u32(51))])
+        .unwrap();
+    for id in 101..=120 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    drop(db);
+
+    let mut got = all_ids(&temp);
+    got.sort_unstable();
+    assert_eq!(got, (51..=120).collect::<Vec<_>>());
+    let mut db = temp.open();
+    // the freed slots were reused
+    assert_eq!(db.tables_info()[0].1.page_count, 2);
+    assert!(db.check_integrity().unwrap().is_empty());
+}
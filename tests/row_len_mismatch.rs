@@ -0,0 +1,70 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, DatabaseError},
+    Database,
+};
+
+#[test]
+fn old_width_rows_after_add_column_name_the_schema_change() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    db.add_column(
+        "items",
+        ColumnDef {
+            name: "extra",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+    )
+    .unwrap();
+
+    let err = db.insert("items", &item(2)).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DatabaseError::RowLenMismatch {
+                expected: 68,
+                got: 64,
+                schema_changed: true,
+            }
+        ),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "row of 64 bytes for a table of 68 bytes rows, the table schema changed"
+    );
+}
+
+#[test]
+fn other_widths_give_no_hint() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.drop_column("items", "payload").unwrap();
+
+    let err = db.insert("items", &[1; 10]).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            DatabaseError::RowLenMismatch {
+                expected: 4,
+                got: 10,
+                schema_changed: false,
+            }
+        ),
+        "{err:?}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "row of 10 bytes for a table of 4 bytes rows"
+    );
+    // the width before the drop is recognised
+    let err = db.insert("items", &item(1)).unwrap_err();
+    assert!(
+        err.to_string().ends_with("the table schema changed"),
+        "{err}"
+    );
+}
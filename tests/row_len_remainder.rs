@@ -0,0 +1,42 @@
+mod common;
+
+use std::fs;
+
+use common::TempDb;
+use rustub_storage::table::{ColumnDef, ColumnType, Condition};
+
+#[test]
+fn bytes_after_the_last_slot_are_never_a_row() {
+    let temp = TempDb::new("remainder");
+    let mut db = temp.open();
+    // 40 rows of 100 bytes per page, 96 bytes left over
+    let defs = [ColumnDef {
+        name: "value",
+        column_type: ColumnType::Bytes,
+        size: 100,
+    }];
+    db.create_table("rows", &defs).unwrap();
+    for n in 1..=41u8 {
+        db.insert("rows", &[n; 100]).unwrap();
+    }
+    let pages = db.data_pages("rows").unwrap();
+    assert_eq!(pages.len(), 2);
+    drop(db);
+
+    // garbage where a 41st slot would start
+    let mut bytes = fs::read(temp.path()).unwrap();
+    let end = (pages[0] as usize + 1) * 4096;
+    bytes[end - 96..end].fill(0xAB);
+    fs::write(temp.path(), bytes).unwrap();
+
+    let mut db = temp.open();
+    let all: [Condition<&[u8]>; 0] = [];
+    let rows = db.select("rows", &all).unwrap();
+    assert_eq!(rows.len(), 41);
+    assert!(rows.iter().all(|row| row[0] != 0xAB));
+    assert_eq!(db.count("rows", &all).unwrap(), 41);
+    let set = [Condition::eq(0..1, [0x11])];
+    assert_eq!(db.update("rows", &all, &set).unwrap(), 41);
+    assert_eq!(db.delete("rows", &all).unwrap(), 41);
+    assert_eq!(db.count("rows", &all).unwrap(), 0);
+}
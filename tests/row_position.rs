@@ -0,0 +1,28 @@
+mod common;
+
+use std::fs;
+
+use common::{item, item_defs, TempDb};
+
+#[test]
+fn a_second_row_follows_the_first_in_its_page() {
+    let temp = TempDb::new("row-position");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    let header = fs::read(temp.path()).unwrap()[..4096].to_vec();
+    db.insert("items", &item(2)).unwrap();
+    drop(db);
+
+    let bytes = fs::read(temp.path()).unwrap();
+    // the header page is left alone
+    assert_eq!(bytes[..4096], header);
+    // [len]["items"][def i32][meta i32], the meta page holds the data page
+    let meta_page = i32::from_be_bytes(bytes[10..14].try_into().unwrap()) as usize;
+    let pos = meta_page * 4096;
+    let data_page = i32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize;
+    let page = &bytes[data_page * 4096..(data_page + 1) * 4096];
+    assert_eq!(page[..64], item(1));
+    assert_eq!(page[64..128], item(2));
+    assert!(page[128..].iter().all(|b| *b == 0));
+}
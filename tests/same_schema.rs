@@ -0,0 +1,43 @@
+mod common;
+
+use common::item_defs;
+use rustub_storage::{
+    table::{ColumnDef, ColumnType},
+    Database,
+};
+
+#[test]
+fn tables_created_alike_share_a_schema() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("a", &item_defs()).unwrap();
+    db.create_table("b", &item_defs()).unwrap();
+    assert!(db.same_schema("a", "b").unwrap());
+    assert!(db.same_schema("a", "a").unwrap());
+}
+
+#[test]
+fn names_types_and_sizes_must_all_match() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let variants = [
+        ("renamed", "key", ColumnType::UInt, 4),
+        ("retyped", "id", ColumnType::Int, 4),
+        ("resized", "id", ColumnType::UInt, 8),
+    ];
+    for (table_name, name, column_type, size) in variants {
+        let mut defs = item_defs();
+        defs[0] = ColumnDef {
+            name,
+            column_type,
+            size,
+        };
+        db.create_table(table_name, &defs).unwrap();
+        assert!(
+            !db.same_schema("items", table_name).unwrap(),
+            "{table_name}"
+        );
+    }
+    db.create_table("shorter", &item_defs()[..1]).unwrap();
+    assert!(!db.same_schema("items", "shorter").unwrap());
+    assert!(db.same_schema("items", "missing").is_err());
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DatabaseError},
+    Database,
+};
+
+#[test]
+fn concatenated_batches_equal_select() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=300).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    db.delete("items", &[Condition::lt(0..4, be_key_u32(50))])
+        .unwrap();
+
+    let conditions = [Condition::lt(0..4, be_key_u32(250))];
+    let selected = db.select("items", &conditions).unwrap();
+    for batch_size in [1, 7, 64, 200, 1000] {
+        let mut batches = Vec::new();
+        db.select_batched("items", &conditions, batch_size, |rows| {
+            batches.push(rows.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        assert!(batches.iter().all(|batch| batch.len() <= batch_size));
+        assert_eq!(batches.len(), selected.len().div_ceil(batch_size));
+        assert_eq!(batches.concat(), selected, "batches of {batch_size}");
+    }
+}
+
+#[test]
+fn the_scan_stops_at_the_first_error_of_the_callback() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=100).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+
+    let mut seen = Vec::new();
+    let err = db
+        .select_batched::<&[u8], _>("items", &[], 10, |rows| {
+            seen.extend_from_slice(rows);
+            if seen.len() >= 30 {
+                return Err(DatabaseError::TableNotFound);
+            }
+            Ok(())
+        })
+        .unwrap_err();
+    assert!(matches!(err, DatabaseError::TableNotFound));
+    assert_eq!(ids(&seen), (1..=30).collect::<Vec<_>>());
+    assert!(db
+        .select_batched::<&[u8], _>("items", &[], 0, |_| Ok(()))
+        .is_err());
+}
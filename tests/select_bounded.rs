@@ -0,0 +1,31 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DatabaseError, StorageError},
+    Database,
+};
+
+#[test]
+fn results_past_the_budget_fail() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=100 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    let all: [Condition<&[u8]>; 0] = [];
+
+    // 100 rows of 64 bytes
+    assert_eq!(db.select_bounded("items", &all, 6400).unwrap().len(), 100);
+    let err = db.select_bounded("items", &all, 6399).unwrap_err();
+    assert!(
+        matches!(err, DatabaseError::Storage(StorageError::ResultTooLarge)),
+        "{err:?}"
+    );
+
+    // only the selected rows count
+    let few = [Condition::lt(0..4, be_key_u32(4))];
+    let rows = db.select_bounded("items", &few, 192).unwrap();
+    assert_eq!(ids(&rows), [1, 2, 3]);
+    assert!(db.select_bounded("items", &few, 191).is_err());
+}
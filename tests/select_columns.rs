@@ -0,0 +1,45 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn ranges_are_joined_in_the_given_order() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    for id in 1..=5 {
+        db.insert("items", &item(id)).unwrap();
+    }
+    let condition = [Condition::gt(0..4, be_key_u32(3))];
+
+    // out of order and overlapping
+    let columns = [60..64, 0..4, 2..6];
+    let rows = db.select_columns("items", &condition, &columns).unwrap();
+    let expected: Vec<Vec<u8>> = [4, 5]
+        .map(|id| {
+            let row = item(id);
+            columns
+                .iter()
+                .flat_map(|range| row[range.clone()].to_vec())
+                .collect()
+        })
+        .into();
+    assert_eq!(rows, expected);
+    assert_eq!(rows[0].len(), 12);
+}
+
+#[test]
+fn ranges_past_the_row_are_rejected() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &item(1)).unwrap();
+    let all: [Condition<&[u8]>; 0] = [];
+    assert!(db.select_columns("items", &all, &[0..4, 60..65]).is_err());
+    assert_eq!(
+        db.select_columns("items", &all, &[0..4, 4..64]).unwrap(),
+        [item(1)]
+    );
+}
@@ -0,0 +1,26 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn duplicates_are_returned_once_in_first_seen_order() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    // three copies of every row, over several pages
+    for _ in 0..3 {
+        let rows: Vec<u8> = (1..=50).rev().flat_map(item).collect();
+        db.insert_many("items", &rows).unwrap();
+    }
+    let all: [Condition<&[u8]>; 0] = [];
+    assert_eq!(db.select("items", &all).unwrap().len(), 150);
+
+    let distinct = db.select_distinct("items", &all).unwrap();
+    assert_eq!(ids(&distinct), (1..=50).rev().collect::<Vec<_>>());
+    let conditions = [Condition::lt(0..4, be_key_u32(4))];
+    let distinct = db.select_distinct("items", &conditions).unwrap();
+    assert_eq!(distinct, [item(3), item(2), item(1)]);
+}
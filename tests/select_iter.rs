@@ -0,0 +1,40 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn select_iter_stops_early_and_skips_empty_slots() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (0..200).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    db.delete("items", &[Condition::lt(0..4, be_key_u32(10))])
+        .unwrap();
+
+    let first: Vec<Vec<u8>> = db
+        .select_iter::<Condition<&[u8]>>("items", &[])
+        .unwrap()
+        .take(5)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(ids(&first), [10, 11, 12, 13, 14]);
+    assert_eq!(first[0], item(10));
+
+    // a filter matching rows on the third page only
+    let conditions = [Condition::gt(0..4, be_key_u32(149))];
+    let mut iter = db.select_iter("items", &conditions).unwrap();
+    assert_eq!(iter.next().unwrap().unwrap(), item(150));
+    assert_eq!(iter.count(), 49);
+
+    let all: Vec<Vec<u8>> = db
+        .select_iter::<Condition<&[u8]>>("items", &[])
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(all, db.select::<Condition<&[u8]>>("items", &[]).unwrap());
+    assert_eq!(all.len(), 190);
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn pages_together_cover_the_table() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=300).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    db.delete("items", &[Condition::eq(0..4, be_key_u32(70))])
+        .unwrap();
+
+    let pages = db.data_pages("items").unwrap();
+    assert_eq!(pages.len(), 5);
+    let mut seen = Vec::new();
+    for n in 0..pages.len() {
+        let rows = db.select_nth_page("items", n).unwrap();
+        assert!(rows.len() <= 64);
+        seen.extend(ids(&rows));
+    }
+    // the deleted row is skipped, no row is repeated
+    let expected: Vec<u32> = (1..=300).filter(|id| *id != 70).collect();
+    assert_eq!(seen, expected);
+    assert_eq!(db.select_nth_page("items", 1).unwrap().len(), 63);
+    assert!(db.select_nth_page("items", pages.len()).is_err());
+}
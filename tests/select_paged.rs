@@ -0,0 +1,51 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::table::{be_key_u32, Condition};
+
+/// Keys 1 to 256 over four data pages.
+fn items() -> TempDb {
+    let temp = TempDb::new("paged");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert_many("items", &(1..=256).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    temp
+}
+
+#[test]
+fn offsets_count_matches_not_slots() {
+    let temp = items();
+    let mut db = temp.open();
+    let above = [Condition::gt(0..4, be_key_u32(100))];
+    let rows = db.select_paged("items", &above, 10, Some(5)).unwrap();
+    assert_eq!(ids(&rows), [111, 112, 113, 114, 115]);
+    let rows = db.select_paged("items", &above, 150, None).unwrap();
+    assert_eq!(ids(&rows), (251..=256).collect::<Vec<_>>());
+}
+
+#[test]
+fn offsets_past_the_end_select_nothing() {
+    let temp = items();
+    let mut db = temp.open();
+    let all: [Condition<&[u8]>; 0] = [];
+    assert!(db
+        .select_paged("items", &all, 256, None)
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .select_paged("items", &all, 1000, Some(10))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn small_limits_stop_in_the_first_page() {
+    let temp = items();
+    let mut db = temp.open();
+    let all: [Condition<&[u8]>; 0] = [];
+    let rows = db.select_paged("items", &all, 0, Some(10)).unwrap();
+    assert_eq!(ids(&rows), (1..=10).collect::<Vec<_>>());
+    // the meta page and the first data page
+    assert_eq!(db.cache_stats().misses, 2);
+}
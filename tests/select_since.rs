@@ -0,0 +1,136 @@
+mod common;
+
+use common::{ids, item, TempDb};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, Condition, TableOptions},
+    Database,
+};
+
+/// An id, the sequence column, and a payload.
+fn event_defs() -> [ColumnDef<&'static str>; 3] {
+    [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "seq",
+            column_type: ColumnType::UInt,
+            size: 8,
+        },
+        ColumnDef {
+            name: "payload",
+            column_type: ColumnType::Bytes,
+            size: 52,
+        },
+    ]
+}
+
+fn sequenced() -> TableOptions {
+    TableOptions {
+        sequence_column: Some(1),
+        ..TableOptions::default()
+    }
+}
+
+/// An event row with `id`, whatever its sequence column holds.
+fn event(id: u32) -> Vec<u8> {
+    let mut row = item(id);
+    row[4..12].fill(0xAA);
+    row
+}
+
+fn events(ids: impl Iterator<Item = u32>) -> Vec<u8> {
+    ids.flat_map(event).collect()
+}
+
+#[test]
+fn only_rows_inserted_after_the_number_are_returned() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table_with_options("events", &event_defs(), &sequenced())
+        .unwrap();
+    db.insert_many("events", &events(0..100)).unwrap();
+    db.insert("events", &event(100)).unwrap();
+
+    let seen = db.select_since("events", 0).unwrap();
+    let seqs: Vec<u64> = seen.iter().map(|(seq, _)| *seq).collect();
+    assert_eq!(seqs, (1..=101).collect::<Vec<_>>());
+    let rows: Vec<Vec<u8>> = seen.into_iter().map(|(_, row)| row).collect();
+    assert_eq!(ids(&rows), (0..=100).collect::<Vec<_>>());
+    let max = seqs[seqs.len() - 1];
+
+    db.insert_many("events", &events(101..110)).unwrap();
+    db.insert("events", &event(110)).unwrap();
+    let new = db.select_since("events", max).unwrap();
+    assert_eq!(
+        new.iter().map(|(seq, _)| *seq).collect::<Vec<_>>(),
+        (102..=111).collect::<Vec<_>>()
+    );
+    let rows: Vec<Vec<u8>> = new.into_iter().map(|(_, row)| row).collect();
+    assert_eq!(ids(&rows), (101..=110).collect::<Vec<_>>());
+    // the column holds the number in the returned rows
+    assert_eq!(rows[0][4..12], 102u64.to_be_bytes());
+    assert!(db.select_since("events", 111).unwrap().is_empty());
+}
+
+#[test]
+fn deleted_rows_are_not_seen() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table_with_options("events", &event_defs(), &sequenced())
+        .unwrap();
+    db.insert_many("events", &events(0..10)).unwrap();
+    db.delete("events", &[Condition::lt(0..4, 5u32.to_be_bytes())])
+        .unwrap();
+
+    let rows: Vec<Vec<u8>> = db
+        .select_since("events", 0)
+        .unwrap()
+        .into_iter()
+        .map(|(_, row)| row)
+        .collect();
+    assert_eq!(ids(&rows), (5..10).collect::<Vec<_>>());
+    // numbers are not handed out again
+    db.insert("events", &event(10)).unwrap();
+    assert_eq!(db.select_since("events", 10).unwrap()[0].0, 11);
+}
+
+#[test]
+fn the_counter_is_kept_in_the_file() {
+    let temp = TempDb::new("select-since");
+    {
+        let mut db = temp.open();
+        db.create_table_with_options("events", &event_defs(), &sequenced())
+            .unwrap();
+        db.insert_many("events", &events(0..3)).unwrap();
+    }
+    let mut db = temp.open();
+    db.insert("events", &event(3)).unwrap();
+    let new = db.select_since("events", 3).unwrap();
+    assert_eq!(new.len(), 1);
+    assert_eq!(new[0].0, 4);
+    assert_eq!(ids(&[new[0].1.clone()]), [3]);
+}
+
+#[test]
+fn tables_need_a_sequence_column() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("events", &event_defs()).unwrap();
+    assert!(db.select_since("events", 0).is_err());
+
+    // it must be an 8 byte uint
+    let options = TableOptions {
+        sequence_column: Some(2),
+        ..TableOptions::default()
+    };
+    assert!(db
+        .create_table_with_options("other", &event_defs(), &options)
+        .is_err());
+    // and it stays
+    db.create_table_with_options("more", &event_defs(), &sequenced())
+        .unwrap();
+    assert!(db.drop_column("more", "seq").is_err());
+    db.drop_column("more", "id").unwrap();
+    db.insert("more", &[0; 60]).unwrap();
+    assert_eq!(db.select_since("more", 0).unwrap()[0].0, 1);
+}
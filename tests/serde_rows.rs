@@ -0,0 +1,119 @@
+#![cfg(feature = "serde")]
+
+use rustub_storage::{
+    decode_row, encode_row,
+    table::{be_key_i32, ColumnDef, ColumnType, Condition},
+    Database,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Account {
+    id: i32,
+    owner: String,
+    balance: u64,
+    active: bool,
+    tag: [u8; 3],
+}
+
+fn account_defs() -> [ColumnDef<&'static str>; 5] {
+    [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::Int,
+            size: 4,
+        },
+        // in another order than the fields
+        ColumnDef {
+            name: "active",
+            column_type: ColumnType::Bool,
+            size: 1,
+        },
+        ColumnDef {
+            name: "owner",
+            column_type: ColumnType::Text,
+            size: 8,
+        },
+        ColumnDef {
+            name: "balance",
+            column_type: ColumnType::UInt,
+            size: 6,
+        },
+        ColumnDef {
+            name: "tag",
+            column_type: ColumnType::Bytes,
+            size: 3,
+        },
+    ]
+}
+
+fn account(id: i32) -> Account {
+    Account {
+        id,
+        owner: format!("user{id}"),
+        balance: 1000 * id.unsigned_abs() as u64,
+        active: id % 2 == 0,
+        tag: [id as u8, 0, 7],
+    }
+}
+
+#[test]
+fn derived_structs_round_trip_through_a_table() {
+    let defs = account_defs();
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("accounts", &defs).unwrap();
+    for id in -2..3 {
+        db.insert("accounts", &encode_row(&defs, &account(id)).unwrap())
+            .unwrap();
+    }
+
+    let row = encode_row(&defs, &account(-2)).unwrap();
+    assert_eq!(row[..4], be_key_i32(-2));
+    assert_eq!(row[4], 1);
+    assert_eq!(&row[5..13], b"user-2\0\0");
+    assert_eq!(row[13..19], 2000u64.to_be_bytes()[2..]);
+    assert_eq!(row[19..], [254, 0, 7]);
+
+    let rows = db
+        .select("accounts", &[Condition::gt(0..4, &be_key_i32(0))])
+        .unwrap();
+    let mut accounts: Vec<Account> = rows
+        .iter()
+        .map(|row| decode_row(&defs, row).unwrap())
+        .collect();
+    accounts.sort_unstable_by_key(|account| account.id);
+    assert_eq!(accounts, [account(1), account(2)]);
+}
+
+#[test]
+fn tuples_map_to_columns_in_order() {
+    let defs = account_defs();
+    let value = (5i32, false, "five", 9u8, vec![1u8, 2, 3]);
+    let row = encode_row(&defs, &value).unwrap();
+    let decoded: (i32, bool, String, u8, Vec<u8>) = decode_row(&defs, &row).unwrap();
+    assert_eq!(decoded, (5, false, "five".to_owned(), 9, vec![1, 2, 3]));
+}
+
+#[test]
+fn values_that_do_not_fit_are_rejected() {
+    #[derive(Serialize)]
+    struct Unknown {
+        nickname: String,
+    }
+
+    let defs = account_defs();
+    let long_owner = Account {
+        owner: "much too long".to_owned(),
+        ..account(1)
+    };
+    assert!(encode_row(&defs, &long_owner).is_err());
+    let big_balance = (1i32, true, "a", 1u64 << 48, [0u8; 3]);
+    assert!(encode_row(&defs, &big_balance).is_err());
+    let short_tag = (1i32, true, "a", 1u8, [0u8; 2]);
+    assert!(encode_row(&defs, &short_tag).is_err());
+    let unknown = Unknown {
+        nickname: "x".to_owned(),
+    };
+    assert!(encode_row(&defs, &unknown).is_err());
+    assert!(decode_row::<Account>(&defs, &[0; 5]).is_err());
+}
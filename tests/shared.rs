@@ -0,0 +1,40 @@
+mod common;
+
+use std::thread;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{table::Condition, SharedDatabase};
+
+#[test]
+fn threads_insert_into_their_own_tables() {
+    let temp = TempDb::new("shared");
+    let db = SharedDatabase::open(temp.path()).unwrap();
+    let handles: Vec<_> = (0..4u32)
+        .map(|thread| {
+            let db = db.clone();
+            thread::spawn(move || {
+                let table = format!("items{thread}");
+                db.create_table(&table, &item_defs()).unwrap();
+                for id in 1..=100 {
+                    db.insert(&table, &item(thread * 1000 + id)).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(db.lock().list_tables().len(), 4);
+    for thread in 0..4u32 {
+        let rows = db
+            .select::<Condition<&[u8]>>(&format!("items{thread}"), &[])
+            .unwrap();
+        let expected: Vec<u32> = (1..=100).map(|id| thread * 1000 + id).collect();
+        assert_eq!(ids(&rows), expected);
+    }
+    drop(db);
+
+    let mut db = temp.open();
+    assert!(db.check_integrity().unwrap().is_empty());
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::fs;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Database, DatabaseOptions};
+
+fn file_len(temp: &TempDb) -> u64 {
+    fs::metadata(temp.path()).unwrap().len()
+}
+
+#[test]
+fn dropping_the_last_table_shrinks_the_file() {
+    let temp = TempDb::new("shrink");
+    // grow one page at a time, so the file ends at the last page
+    let options = DatabaseOptions {
+        grow_pages: 1,
+        ..DatabaseOptions::default()
+    };
+    let mut db = Database::open_with_options(temp.path(), options).unwrap();
+    db.create_table("first", &item_defs()).unwrap();
+    db.insert("first", &item(1)).unwrap();
+    let first_len = file_len(&temp);
+    assert_eq!(first_len, 4 * 4096);
+    db.create_table("last", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=300).flat_map(item).collect();
+    db.insert_many("last", &rows).unwrap();
+    assert_eq!(file_len(&temp), 4 * 4096 + 7 * 4096);
+
+    db.drop_table_and_shrink("last").unwrap();
+    assert_eq!(file_len(&temp), first_len);
+    assert!(!db.table_exists("last"));
+    assert_eq!(db.count::<Condition<&[u8]>>("first", &[]).unwrap(), 1);
+    drop(db);
+    let mut db = temp.open();
+    assert_eq!(db.list_tables(), ["first"]);
+    db.insert("first", &item(2)).unwrap();
+}
+
+#[test]
+fn dropping_an_inner_table_keeps_the_length() {
+    let temp = TempDb::new("shrink-inner");
+    let options = DatabaseOptions {
+        grow_pages: 1,
+        ..DatabaseOptions::default()
+    };
+    let mut db = Database::open_with_options(temp.path(), options).unwrap();
+    db.create_table("first", &item_defs()).unwrap();
+    db.insert("first", &item(1)).unwrap();
+    db.create_table("last", &item_defs()).unwrap();
+    db.insert("last", &item(1)).unwrap();
+    let len = file_len(&temp);
+
+    db.drop_table_and_shrink("first").unwrap();
+    assert_eq!(file_len(&temp), len);
+}
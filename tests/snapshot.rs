@@ -0,0 +1,50 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn later_inserts_are_hidden_from_a_snapshot() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.create_table("other", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=100).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    let snapshot = db.snapshot().unwrap();
+
+    // into the free slots of the last page and onto new pages
+    let more: Vec<u8> = (101..=300).flat_map(item).collect();
+    db.insert_many("items", &more).unwrap();
+    db.insert("other", &item(1)).unwrap();
+
+    let all: [Condition<&[u8]>; 0] = [];
+    let seen = db.select_snapshot("items", &all, &snapshot).unwrap();
+    assert_eq!(ids(&seen), (1..=100).collect::<Vec<_>>());
+    assert!(db
+        .select_snapshot("other", &all, &snapshot)
+        .unwrap()
+        .is_empty());
+    let conditions = [Condition::gt(0..4, be_key_u32(90))];
+    let seen = db.select_snapshot("items", &conditions, &snapshot).unwrap();
+    assert_eq!(ids(&seen), (91..=100).collect::<Vec<_>>());
+    assert_eq!(db.select("items", &all).unwrap().len(), 300);
+}
+
+#[test]
+fn deleted_rows_stay_hidden() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=100).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    let snapshot = db.snapshot().unwrap();
+
+    db.delete("items", &[Condition::lt(0..4, be_key_u32(11))])
+        .unwrap();
+    let all: [Condition<&[u8]>; 0] = [];
+    let seen = db.select_snapshot("items", &all, &snapshot).unwrap();
+    assert_eq!(ids(&seen), (11..=100).collect::<Vec<_>>());
+    assert!(db.select_snapshot("missing", &all, &snapshot).is_err());
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType},
+    Database, DatabaseOptions, TableUsage,
+};
+
+#[test]
+fn stats_count_the_pages_of_each_table() {
+    let temp = TempDb::new("stats");
+    let options = DatabaseOptions {
+        grow_pages: 16,
+        ..DatabaseOptions::default()
+    };
+    let mut db = Database::open_with_options(temp.path(), options).unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert_many("items", &(1..=130).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    let def = [ColumnDef {
+        name: "n",
+        column_type: ColumnType::UInt,
+        size: 4,
+    }];
+    db.create_table("numbers", &def).unwrap();
+    db.insert("numbers", &[1; 4]).unwrap();
+
+    let stats = db.stats().unwrap();
+    assert_eq!(
+        stats.tables,
+        [
+            (
+                "items".to_string(),
+                TableUsage {
+                    row_len: 64,
+                    data_pages: 3,
+                    total_pages: 5,
+                }
+            ),
+            (
+                "numbers".to_string(),
+                TableUsage {
+                    row_len: 4,
+                    data_pages: 1,
+                    total_pages: 3,
+                }
+            ),
+        ]
+    );
+    // the header page and the pages of both tables
+    assert_eq!(stats.used_pages, 9);
+    // grown 16 pages at a time past the header page
+    assert_eq!(stats.file_pages, 17);
+    assert_eq!(stats.free_pages, 8);
+}
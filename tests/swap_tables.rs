@@ -0,0 +1,45 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, Condition},
+    Database,
+};
+
+fn all(db: &mut Database, table_name: &str) -> Vec<Vec<u8>> {
+    db.select::<Condition<&[u8]>>(table_name, &[]).unwrap()
+}
+
+#[test]
+fn swapped_names_resolve_to_the_other_data() {
+    let temp = TempDb::new("swap-tables");
+    let mut db = temp.open();
+    db.create_table("live", &item_defs()).unwrap();
+    let staging_defs = [ColumnDef {
+        name: "n",
+        column_type: ColumnType::UInt,
+        size: 4,
+    }];
+    db.create_table("staging", &staging_defs).unwrap();
+    for id in 1..=100 {
+        db.insert("live", &item(id)).unwrap();
+    }
+    db.insert("staging", &7u32.to_be_bytes()).unwrap();
+
+    db.swap_tables("live", "staging").unwrap();
+    assert_eq!(all(&mut db, "live"), [7u32.to_be_bytes()]);
+    assert_eq!(ids(&all(&mut db, "staging")), (1..=100).collect::<Vec<_>>());
+    assert_eq!(db.get_table_def("live").unwrap()[0].name, "n");
+    // inserts go to the data now under each name
+    db.insert("staging", &item(101)).unwrap();
+    db.insert("live", &8u32.to_be_bytes()).unwrap();
+
+    drop(db);
+    let mut db = temp.open();
+    assert_eq!(
+        all(&mut db, "live"),
+        [7u32.to_be_bytes(), 8u32.to_be_bytes()]
+    );
+    assert_eq!(ids(&all(&mut db, "staging")), (1..=101).collect::<Vec<_>>());
+    assert!(db.swap_tables("live", "missing").is_err());
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::Database;
+
+const PAGE_SIZE: u64 = 4096;
+
+#[test]
+fn table_bytes_count_data_def_meta_and_index_pages() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    assert_eq!(db.table_bytes("items").unwrap(), 2 * PAGE_SIZE);
+
+    // 64 rows of 64 bytes fill a page
+    let rows: Vec<u8> = (0..300).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    let data_pages = db.stats().unwrap().tables[0].1.data_pages as u64;
+    assert_eq!(data_pages, 5);
+    assert_eq!(
+        db.table_bytes("items").unwrap(),
+        (data_pages + 2) * PAGE_SIZE
+    );
+
+    let used_before = db.stats().unwrap().used_pages as u64;
+    db.create_index("items", 0..4).unwrap();
+    db.sync().unwrap();
+    let stats = db.stats().unwrap();
+    let index_pages = stats.used_pages as u64 - used_before;
+    assert!(index_pages > 0);
+    assert_eq!(
+        db.table_bytes("items").unwrap(),
+        (data_pages + 2 + index_pages) * PAGE_SIZE
+    );
+    assert_eq!(
+        db.table_bytes("items").unwrap(),
+        stats.tables[0].1.total_pages as u64 * PAGE_SIZE
+    );
+    assert!(db.table_bytes("missing").is_err());
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::item_defs;
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{ColumnDef, ColumnType, Condition, DatabaseError, StorageError},
+    CreateOptions, Database, DatabaseOptions,
+};
+
+/// A table whose rows take a whole page each.
+fn page_rows_db(options: DatabaseOptions) -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory_with_options(CreateOptions::default(), options).unwrap();
+    let defs = [ColumnDef {
+        name: "page",
+        column_type: ColumnType::Bytes,
+        size: 4096,
+    }];
+    db.create_table("pages", &defs).unwrap();
+    db
+}
+
+#[test]
+fn a_full_meta_table_is_table_full() {
+    let mut db = page_rows_db(DatabaseOptions::default());
+    // a meta page of 4 KiB points to 128 data pages
+    for n in 0..128u32 {
+        let mut row = vec![1; 4096];
+        row[..4].copy_from_slice(&n.to_be_bytes());
+        db.insert("pages", &row).unwrap();
+    }
+
+    let err = db.insert("pages", &[2; 4096]).unwrap_err();
+    assert!(
+        matches!(err, DatabaseError::Storage(StorageError::TableFull)),
+        "{err:?}"
+    );
+    let err = db.insert_many("pages", &[2; 4096]).unwrap_err();
+    assert!(
+        matches!(err, DatabaseError::Storage(StorageError::TableFull)),
+        "{err:?}"
+    );
+    // the file still has room for other tables
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert("items", &[3; 64]).unwrap();
+    assert_eq!(db.count::<Condition<&[u8]>>("pages", &[]).unwrap(), 128);
+}
+
+#[test]
+fn running_out_of_pages_is_not_table_full() {
+    let options = DatabaseOptions {
+        max_pages: Some(10),
+        ..DatabaseOptions::default()
+    };
+    let mut db = page_rows_db(options);
+    // the header, def and meta pages leave seven data pages
+    for _ in 0..7 {
+        db.insert("pages", &[1; 4096]).unwrap();
+    }
+
+    let err = db.insert("pages", &[2; 4096]).unwrap_err();
+    assert!(
+        matches!(err, DatabaseError::Storage(StorageError::QuotaExceeded)),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::{item, item_defs};
+use rustub_storage::{
+    table::{ColumnDef, ColumnType, DeletePolicy, TableInfo, TableOptions},
+    Database,
+};
+
+#[test]
+fn every_table_is_summarized() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=100).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    let flag_defs = [ColumnDef {
+        name: "on",
+        column_type: ColumnType::Bool,
+        size: 1,
+    }];
+    // the presence flag is not part of the row length
+    let options = TableOptions {
+        delete_policy: DeletePolicy::PresenceFlag,
+        ..TableOptions::default()
+    };
+    db.create_table_with_options("flags", &flag_defs, &options)
+        .unwrap();
+    db.insert("flags", &[1]).unwrap();
+    db.create_table("empty", &item_defs()[..1]).unwrap();
+
+    let mut info = db.tables_info();
+    info.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let expected = [
+        (
+            "empty",
+            TableInfo {
+                row_len: 4,
+                page_count: 0,
+                column_count: 1,
+            },
+        ),
+        (
+            "flags",
+            TableInfo {
+                row_len: 1,
+                page_count: 1,
+                column_count: 1,
+            },
+        ),
+        (
+            "items",
+            TableInfo {
+                row_len: 64,
+                page_count: 2,
+                column_count: 2,
+            },
+        ),
+    ]
+    .map(|(name, info)| (name.to_string(), info));
+    assert_eq!(info, expected);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::fs;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{be_key_u32, ColumnDef, ColumnType, Condition, DeletePolicy, TableOptions},
+    Database,
+};
+
+#[test]
+fn taken_rows_are_returned_and_removed() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("queue", &item_defs()).unwrap();
+    for id in 0..200 {
+        db.insert("queue", &item(id)).unwrap();
+    }
+
+    let conditions = [Condition::lt(0..4, be_key_u32(100))];
+    let taken = db.take_rows("queue", &conditions, 50).unwrap();
+    assert_eq!(taken.len(), 50);
+    assert_eq!(taken, (0..50).map(item).collect::<Vec<_>>());
+    let left = db.select("queue", &conditions).unwrap();
+    assert_eq!(ids(&left), (50..100).collect::<Vec<_>>());
+    assert_eq!(db.count("queue", &[] as &[Condition<&[u8]>]).unwrap(), 150);
+}
+
+#[test]
+fn second_take_returns_the_next_batch() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("queue", &item_defs()).unwrap();
+    // over several data pages
+    for id in 0..300 {
+        db.insert("queue", &item(id)).unwrap();
+    }
+
+    let all: [Condition<&[u8]>; 0] = [];
+    let first = db.take_rows("queue", &all, 100).unwrap();
+    let second = db.take_rows("queue", &all, 100).unwrap();
+    assert_eq!(ids(&first), (0..100).collect::<Vec<_>>());
+    assert_eq!(ids(&second), (100..200).collect::<Vec<_>>());
+    let rest = db.take_rows("queue", &all, 1000).unwrap();
+    assert_eq!(ids(&rest), (200..300).collect::<Vec<_>>());
+    assert!(db.take_rows("queue", &all, 10).unwrap().is_empty());
+}
+
+#[test]
+fn taken_rows_free_their_blobs() {
+    let temp = TempDb::new("take-blobs");
+    let mut db = temp.open();
+    let defs = [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "body",
+            column_type: ColumnType::Blob,
+            size: 12,
+        },
+    ];
+    db.create_table("messages", &defs).unwrap();
+    let body = vec![7; 1000];
+    db.insert_values("messages", &[&be_key_u32(1), &body])
+        .unwrap();
+    let blob_len = fs::metadata(temp.sidecar(".blob")).unwrap().len();
+
+    let taken = db
+        .take_rows("messages", &[Condition::eq(0..4, be_key_u32(1))], 1)
+        .unwrap();
+    assert_eq!(taken.len(), 1);
+    // the extent of the taken value is reused
+    db.insert_values("messages", &[&be_key_u32(2), &body])
+        .unwrap();
+    assert_eq!(fs::metadata(temp.sidecar(".blob")).unwrap().len(), blob_len);
+}
+
+#[test]
+fn emptied_pages_are_freed_per_delete_policy() {
+    let all: [Condition<&[u8]>; 0] = [];
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("queue", &item_defs()).unwrap();
+    let rows: Vec<u8> = (0..200).flat_map(item).collect();
+    db.insert_many("queue", &rows).unwrap();
+    let pages = db.data_pages("queue").unwrap();
+    assert_eq!(pages.len(), 4);
+
+    // the first page holds the first 64 rows
+    assert_eq!(db.take_rows("queue", &all, 64).unwrap().len(), 64);
+    assert_eq!(db.data_pages("queue").unwrap(), pages[1..]);
+
+    // tombstoned pages keep their slots
+    let options = TableOptions {
+        delete_policy: DeletePolicy::Tombstone,
+        ..TableOptions::default()
+    };
+    db.create_table_with_options("tombs", &item_defs(), &options)
+        .unwrap();
+    db.insert_many("tombs", &rows).unwrap();
+    let pages = db.data_pages("tombs").unwrap();
+    assert_eq!(db.take_rows("tombs", &all, 64).unwrap().len(), 64);
+    assert_eq!(db.data_pages("tombs").unwrap(), pages);
+    db.insert("tombs", &item(500)).unwrap();
+    assert_eq!(db.select("tombs", &all).unwrap()[0], item(500));
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{
+    table::{be_key_u32, Condition, DatabaseError, StorageError},
+    Database,
+};
+
+fn all(db: &mut Database) -> Vec<u32> {
+    let mut res = ids(&db.select::<Condition<&[u8]>>("items", &[]).unwrap());
+    res.sort_unstable();
+    res
+}
+
+#[test]
+fn rolled_back_inserts_are_gone() {
+    let temp = TempDb::new("rollback-insert");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+
+    db.begin().unwrap();
+    db.insert("items", &item(1)).unwrap();
+    assert_eq!(all(&mut db), [1]);
+    db.rollback().unwrap();
+    assert!(all(&mut db).is_empty());
+
+    drop(db);
+    let mut db = temp.open();
+    assert!(all(&mut db).is_empty());
+}
+
+#[test]
+fn rollback_restores_rows_and_tables() {
+    let temp = TempDb::new("rollback-restore");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (0..150).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+
+    db.begin().unwrap();
+    db.delete("items", &[Condition::lt(0..4, be_key_u32(100))])
+        .unwrap();
+    db.insert_many("items", &(150..300).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    db.create_table("other", &item_defs()).unwrap();
+    db.rollback().unwrap();
+
+    assert_eq!(all(&mut db), (0..150).collect::<Vec<_>>());
+    assert!(!db.table_exists("other"));
+    assert!(db.check_integrity().unwrap().is_empty());
+    // the freed and allocated pages are as before
+    db.insert("items", &item(150)).unwrap();
+    assert_eq!(all(&mut db), (0..151).collect::<Vec<_>>());
+}
+
+#[test]
+fn committed_writes_stay() {
+    let temp = TempDb::new("commit");
+    {
+        let mut db = temp.open();
+        db.create_table("items", &item_defs()).unwrap();
+        db.begin().unwrap();
+        db.insert("items", &item(1)).unwrap();
+        db.commit().unwrap();
+        assert!(matches!(
+            db.rollback(),
+            Err(DatabaseError::Storage(StorageError::NoTransaction))
+        ));
+    }
+    let mut db = temp.open();
+    assert_eq!(all(&mut db), [1]);
+}
+
+#[test]
+fn nested_begin_is_rejected() {
+    let temp = TempDb::new("nested");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.begin().unwrap();
+    db.insert("items", &item(1)).unwrap();
+    assert!(matches!(
+        db.begin(),
+        Err(DatabaseError::Storage(StorageError::TransactionOpen))
+    ));
+    // the open transaction is untouched
+    db.rollback().unwrap();
+    assert!(all(&mut db).is_empty());
+    assert!(matches!(
+        db.commit(),
+        Err(DatabaseError::Storage(StorageError::NoTransaction))
+    ));
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::table::Condition;
+
+fn all() -> [Condition<&'static [u8]>; 0] {
+    []
+}
+
+#[test]
+fn truncated_tables_keep_their_schema() {
+    let temp = TempDb::new("truncate");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    db.insert_many("items", &(1..=200).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    db.truncate("items").unwrap();
+    assert_eq!(db.count("items", &all()).unwrap(), 0);
+    assert!(db.data_pages("items").unwrap().is_empty());
+    drop(db);
+
+    let mut db = temp.open();
+    assert_eq!(db.count("items", &all()).unwrap(), 0);
+    assert_eq!(db.get_table_def("items").unwrap().len(), 2);
+    db.insert("items", &item(7)).unwrap();
+    assert_eq!(ids(&db.select("items", &all()).unwrap()), [7]);
+    assert_eq!(db.data_pages("items").unwrap().len(), 1);
+    assert!(db.check_integrity().unwrap().is_empty());
+    assert!(db.truncate("missing").is_err());
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_u32, ColumnDef, ColumnType, Condition},
+    Database,
+};
+
+/// A `logs` table of a 4 byte key and an 8 byte text column.
+fn logs_db() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    let defs = [
+        ColumnDef {
+            name: "id",
+            column_type: ColumnType::UInt,
+            size: 4,
+        },
+        ColumnDef {
+            name: "log",
+            column_type: ColumnType::Text,
+            size: 8,
+        },
+    ];
+    db.create_table("logs", &defs).unwrap();
+    for (id, log) in [(1, "a"), (2, ""), (3, "abcdef"), (4, "x")] {
+        let mut row = be_key_u32(id).to_vec();
+        row.extend(log.bytes());
+        row.resize(12, 0);
+        db.insert("logs", &row).unwrap();
+    }
+    db
+}
+
+fn logs(db: &mut Database<MemoryStorage>) -> Vec<String> {
+    db.select::<Condition<&[u8]>>("logs", &[])
+        .unwrap()
+        .iter()
+        .map(|row| {
+            let log = &row[4..];
+            let end = log.iter().position(|b| *b == 0).unwrap_or(log.len());
+            String::from_utf8(log[..end].to_vec()).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn suffixes_are_appended_to_every_matching_row() {
+    let mut db = logs_db();
+    let conditions = [Condition::lt(0..4, be_key_u32(3))];
+    assert_eq!(
+        db.update_append("logs", &conditions, 4..12, b"+").unwrap(),
+        2
+    );
+    assert_eq!(
+        db.update_append("logs", &conditions, 4..12, b"yz").unwrap(),
+        2
+    );
+    assert_eq!(logs(&mut db), ["a+yz", "+yz", "abcdef", "x"]);
+
+    // up to the column width exactly
+    let third = [Condition::eq(0..4, be_key_u32(3))];
+    assert_eq!(db.update_append("logs", &third, 4..12, b"gh").unwrap(), 1);
+    assert_eq!(logs(&mut db)[2], "abcdefgh");
+}
+
+#[test]
+fn an_overflowing_append_changes_no_row() {
+    let mut db = logs_db();
+    let all: [Condition<&[u8]>; 0] = [];
+    // fits all rows but the third
+    assert!(db.update_append("logs", &all, 4..12, b"123").is_err());
+    assert_eq!(logs(&mut db), ["a", "", "abcdef", "x"]);
+    assert!(db.update_append("logs", &all, 4..13, b"1").is_err());
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{ids, item, item_defs};
+use rustub_storage::{
+    table::{be_key_u32, Condition},
+    Database,
+};
+
+#[test]
+fn each_condition_is_counted_on_its_own() {
+    let mut db = Database::open_in_memory().unwrap();
+    db.create_table("items", &item_defs()).unwrap();
+    let rows: Vec<u8> = (1..=200).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+
+    let conditions = [
+        Condition::gt(0..4, be_key_u32(50).to_vec()),
+        Condition::lt(0..4, be_key_u32(81).to_vec()),
+        // the payload starts with the low byte of the key
+        Condition::eq(4..5, vec![70]),
+    ];
+    let set = [Condition::eq(5..6, [0xEE])];
+    let report = db.update_explain("items", &conditions, &set).unwrap();
+    assert_eq!(report.matched, [150, 80, 1]);
+    assert_eq!(report.updated, 1);
+
+    let changed = db.select("items", &[Condition::eq(5..6, [0xEE])]).unwrap();
+    assert_eq!(ids(&changed), [70]);
+    let report = db
+        .update_explain::<Condition<&[u8]>, _>("items", &[], &set)
+        .unwrap();
+    assert_eq!(report.updated, 200);
+    assert!(report.matched.is_empty());
+}
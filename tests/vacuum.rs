@@ -0,0 +1,31 @@
+mod common;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::table::{be_key_u32, Condition};
+
+#[test]
+fn vacuum_packs_a_fragmented_table() {
+    let temp = TempDb::new("vacuum");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    // eight pages of 64 rows, of which one row in eight is kept
+    db.insert_many("items", &(1..=512).flat_map(item).collect::<Vec<_>>())
+        .unwrap();
+    for id in (1..=512).filter(|id| id % 8 != 0) {
+        db.delete("items", &[Condition::eq(0..4, be_key_u32(id))])
+            .unwrap();
+    }
+    assert_eq!(db.data_pages("items").unwrap().len(), 8);
+    let before = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+
+    assert_eq!(db.vacuum("items").unwrap(), 7);
+    assert_eq!(db.data_pages("items").unwrap().len(), 1);
+    assert_eq!(db.vacuum("items").unwrap(), 0);
+    drop(db);
+
+    let mut db = temp.open();
+    let rows = db.select::<Condition<&[u8]>>("items", &[]).unwrap();
+    assert_eq!(rows, before);
+    assert_eq!(ids(&rows), (8..=512).step_by(8).collect::<Vec<_>>());
+    assert!(db.check_integrity().unwrap().is_empty());
+}
@@ -0,0 +1,66 @@
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{be_key_i64, ColumnDef, ColumnType, Condition},
+    Database,
+};
+
+fn accounts_db() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    let defs = [
+        ColumnDef {
+            name: "balance",
+            column_type: ColumnType::Int,
+            size: 8,
+        },
+        ColumnDef {
+            name: "closed",
+            column_type: ColumnType::Bool,
+            size: 1,
+        },
+        ColumnDef {
+            name: "token",
+            column_type: ColumnType::Bytes,
+            size: 4,
+        },
+    ];
+    db.create_table("accounts", &defs).unwrap();
+    db
+}
+
+#[test]
+fn numeric_ranges_are_valid() {
+    let mut db = accounts_db();
+    let conditions = [
+        Condition::gt(0..8, be_key_i64(-100)),
+        Condition::lt(0..8, be_key_i64(100)),
+    ];
+    db.validate_conditions("accounts", &conditions).unwrap();
+    db.validate_conditions("accounts", &[Condition::eq(8..9, [1])])
+        .unwrap();
+    db.validate_conditions("accounts", &[Condition::eq(9..13, *b"abcd")])
+        .unwrap();
+}
+
+#[test]
+fn orderings_on_bool_and_bytes_columns_are_invalid() {
+    let mut db = accounts_db();
+    let err = db
+        .validate_conditions("accounts", &[Condition::gt(8..9, [0])])
+        .unwrap_err();
+    assert!(format!("{err:?}").contains("closed"), "{err:?}");
+    let err = db
+        .validate_conditions("accounts", &[Condition::lt(9..13, *b"abcd")])
+        .unwrap_err();
+    assert!(format!("{err:?}").contains("token"), "{err:?}");
+}
+
+#[test]
+fn ranges_outside_the_row_are_invalid() {
+    let mut db = accounts_db();
+    assert!(db
+        .validate_conditions("accounts", &[Condition::eq(12..14, [0, 0])])
+        .is_err());
+    assert!(db
+        .validate_conditions("accounts", &[Condition::eq(0..4, [0; 3])])
+        .is_err());
+}
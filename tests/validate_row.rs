@@ -0,0 +1,55 @@
+use rustub_storage::{
+    storage::MemoryStorage,
+    table::{ColumnDef, ColumnType},
+    Database,
+};
+
+fn flags_db() -> Database<MemoryStorage> {
+    let mut db = Database::open_in_memory().unwrap();
+    let defs = [
+        ColumnDef {
+            name: "active",
+            column_type: ColumnType::Bool,
+            size: 2,
+        },
+        ColumnDef {
+            name: "label",
+            column_type: ColumnType::Text,
+            size: 6,
+        },
+    ];
+    db.create_table("flags", &defs).unwrap();
+    db
+}
+
+#[test]
+fn valid_rows_pass() {
+    let mut db = flags_db();
+    db.validate_row("flags", b"\0\x01abc\0\0\0").unwrap();
+    db.validate_row("flags", b"\0\0\0\0\0\0\0\0").unwrap();
+    // bytes after the first zero of a text value are not looked at
+    db.validate_row("flags", b"\0\x01ok\0\xff\xfe\x00").unwrap();
+}
+
+#[test]
+fn an_invalid_bool_byte_is_rejected() {
+    let mut db = flags_db();
+    let err = db.validate_row("flags", b"\0\x02abc\0\0\0").unwrap_err();
+    assert!(format!("{err:?}").contains("active"), "{err:?}");
+    // a bool is big-endian, the high byte must be zero
+    assert!(db.validate_row("flags", b"\x01\0abc\0\0\0").is_err());
+}
+
+#[test]
+fn invalid_utf8_in_a_text_column_is_rejected() {
+    let mut db = flags_db();
+    let err = db.validate_row("flags", b"\0\x01a\xffc\0\0\0").unwrap_err();
+    assert!(format!("{err:?}").contains("label"), "{err:?}");
+}
+
+#[test]
+fn rows_of_the_wrong_length_are_rejected() {
+    let mut db = flags_db();
+    assert!(db.validate_row("flags", b"\0\x01abc").is_err());
+    assert!(db.validate_row("missing", b"").is_err());
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use std::fs;
+
+use common::{ids, item, item_defs, TempDb};
+use rustub_storage::{table::Condition, Database, DatabaseOptions};
+
+const PAGE_SIZE: usize = 4096;
+
+fn wal_options() -> DatabaseOptions {
+    DatabaseOptions {
+        wal: true,
+        ..DatabaseOptions::default()
+    }
+}
+
+fn all(db: &mut Database) -> Vec<u32> {
+    let mut res = ids(&db.select::<Condition<&[u8]>>("items", &[]).unwrap());
+    res.sort_unstable();
+    res
+}
+
+/// A log record, see the `wal` module.
+fn record(page: usize, commit: bool, bytes: &[u8]) -> Vec<u8> {
+    let mut record = (page as i32).to_be_bytes().to_vec();
+    record.push(commit as u8);
+    record.extend_from_slice(bytes);
+    record
+}
+
+/// The file before and after inserting rows `100..200` through the log,
+/// with the file put back as it was before the insert.
+fn crashed_insert(temp: &TempDb) -> (Vec<u8>, Vec<u8>) {
+    {
+        let mut db = Database::open_with_options(temp.path(), wal_options()).unwrap();
+        db.create_table("items", &item_defs()).unwrap();
+        db.insert_many("items", &(0..100).flat_map(item).collect::<Vec<_>>())
+            .unwrap();
+    }
+    let before = fs::read(temp.path()).unwrap();
+    {
+        let mut db = Database::open_with_options(temp.path(), wal_options()).unwrap();
+        db.insert_many("items", &(100..200).flat_map(item).collect::<Vec<_>>())
+            .unwrap();
+    }
+    let after = fs::read(temp.path()).unwrap();
+    // the file grows before the log is written
+    let mut crashed = before.clone();
+    crashed.resize(after.len(), 0);
+    fs::write(temp.path(), &crashed).unwrap();
+    (crashed, after)
+}
+
+/// Records of the pages that differ, the last one committing them.
+fn unit(before: &[u8], after: &[u8], commit: bool) -> Vec<u8> {
+    let changed: Vec<usize> = (0..after.len() / PAGE_SIZE)
+        .filter(|page| {
+            let range = page * PAGE_SIZE..(page + 1) * PAGE_SIZE;
+            before[range.clone()] != after[range]
+        })
+        .collect();
+    assert!(!changed.is_empty());
+    let last = *changed.last().unwrap();
+    changed
+        .iter()
+        .flat_map(|&page| {
+            let bytes = &after[page * PAGE_SIZE..(page + 1) * PAGE_SIZE];
+            record(page, commit && page == last, bytes)
+        })
+        .collect()
+}
+
+#[test]
+fn committed_units_are_replayed_and_the_tail_dropped() {
+    let temp = TempDb::new("wal-replay");
+    let (crashed, after) = crashed_insert(&temp);
+    let mut log = unit(&crashed, &after, true);
+    // an uncommitted unit that would wreck the header page, then a torn record
+    log.extend(record(0, false, &[0xAB; PAGE_SIZE]));
+    log.extend(&record(1, true, &[0xCD; PAGE_SIZE])[..100]);
+    fs::write(temp.sidecar(".wal"), log).unwrap();
+
+    let mut db = temp.open();
+    assert_eq!(all(&mut db), (0..200).collect::<Vec<_>>());
+    assert!(db.check_integrity().unwrap().is_empty());
+    drop(db);
+    assert_eq!(fs::metadata(temp.sidecar(".wal")).unwrap().len(), 0);
+    assert_eq!(fs::read(temp.path()).unwrap(), after);
+}
+
+#[test]
+fn an_uncommitted_unit_is_not_replayed() {
+    let temp = TempDb::new("wal-uncommitted");
+    let (crashed, after) = crashed_insert(&temp);
+    // the crash came before the unit was committed
+    fs::write(temp.sidecar(".wal"), unit(&crashed, &after, false)).unwrap();
+    assert!(Database::open_readonly(temp.path()).is_err());
+
+    let mut db = Database::open_with_options(temp.path(), wal_options()).unwrap();
+    assert_eq!(all(&mut db), (0..100).collect::<Vec<_>>());
+    assert!(db.check_integrity().unwrap().is_empty());
+    // and the database goes on from there
+    db.insert("items", &item(100)).unwrap();
+    drop(db);
+    let mut db = temp.open();
+    assert_eq!(all(&mut db), (0..101).collect::<Vec<_>>());
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::{item, item_defs, TempDb};
+use rustub_storage::{table::Condition, CacheStats};
+
+#[test]
+fn selects_after_warm_hit_the_page_cache() {
+    let temp = TempDb::new("warm");
+    let mut db = temp.open();
+    db.create_table("items", &item_defs()).unwrap();
+    // four data pages
+    let rows: Vec<u8> = (1..=256).flat_map(item).collect();
+    db.insert_many("items", &rows).unwrap();
+    drop(db);
+
+    let mut db = temp.open();
+    db.warm("items").unwrap();
+    let warmed = db.cache_stats();
+    assert_eq!(warmed.misses, 4);
+
+    let all: [Condition<&[u8]>; 0] = [];
+    assert_eq!(db.select("items", &all).unwrap().len(), 256);
+    let stats = db.cache_stats();
+    // only the meta page is read from the file
+    assert_eq!(
+        stats,
+        CacheStats {
+            hits: warmed.hits + 4,
+            misses: warmed.misses + 1,
+        }
+    );
+    assert!(db.warm("missing").is_err());
+}